@@ -0,0 +1,122 @@
+// Building release archives (Brotli compressed TAR files). The registry and
+// `pm install` check them with `pm_lib::archive::validate_archive`.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use tar::{Builder, EntryType, Header};
+
+/// Build a TAR archive of `files`, which are relative to `root`. The archive
+/// depends only on the paths, contents and executable bits of the files:
+/// entries are sorted, and ownership and timestamps are zeroed, so building
+/// the same source twice gives the same bytes.
+pub fn build_archive<F>(
+    root: &Path,
+    files: &[String],
+    mut on_file: F,
+) -> Result<Vec<u8>, failure::Error>
+where
+    F: FnMut(&str),
+{
+    let mut files = files.to_vec();
+    files.sort();
+    files.dedup();
+    let mut tar = Builder::new(Vec::new());
+    for local_path in &files {
+        on_file(local_path);
+        let file = File::open(root.join(local_path))?;
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(file.metadata()?.len());
+        header.set_mode(if is_executable(&file)? { 0o755 } else { 0o644 });
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        tar.append_data(&mut header, local_path, file)?;
+    }
+    tar.finish()?;
+    Ok(tar.into_inner()?)
+}
+
+#[cfg(unix)]
+fn is_executable(file: &File) -> io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(file.metadata()?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_file: &File) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Compress a TAR archive with the settings used for releases.
+pub fn compress<R: Read>(mut tar: R) -> io::Result<Vec<u8>> {
+    let mut tar_br = vec![];
+    let mut brotli_encoder_params = brotli::enc::BrotliEncoderInitParams();
+    brotli_encoder_params.quality = 9;
+    brotli_encoder_params.lgwin = 22; // log2 of window size
+    brotli::BrotliCompress(&mut tar, &mut tar_br, &brotli_encoder_params)?;
+    Ok(tar_br)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pm-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn build_reproducible_archives() {
+        let root = temp_dir("build-test");
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("bin/run"), "#!/bin/sh").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(root.join("bin/run"), fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        let files = vec!["bin/run".to_string(), "a.txt".to_string()];
+
+        let tar = build_archive(&root, &files, |_| {}).unwrap();
+        let mut listed = vec![];
+        let reversed: Vec<String> = files.iter().rev().cloned().collect();
+        assert_eq!(
+            build_archive(&root, &reversed, |path| listed.push(path.to_string())).unwrap(),
+            tar
+        );
+        assert_eq!(listed, vec!["a.txt", "bin/run"]);
+
+        let mut archive = tar::Archive::new(&tar[..]);
+        let headers: Vec<(PathBuf, u32, u64, u64)> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let header = entry.unwrap().header().clone();
+                (
+                    header.path().unwrap().to_path_buf(),
+                    header.mode().unwrap(),
+                    header.mtime().unwrap(),
+                    header.uid().unwrap(),
+                )
+            })
+            .collect();
+        let executable = if cfg!(unix) { 0o755 } else { 0o644 };
+        assert_eq!(
+            headers,
+            vec![
+                (PathBuf::from("a.txt"), 0o644, 0, 0),
+                (PathBuf::from("bin/run"), executable, 0, 0),
+            ]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs;
+
+use crate::lockfile::Lockfile;
+use crate::manifest::DependencyManifest;
+use crate::manifest_editor::add_dependency;
+use crate::project::{find_project_paths, ProjectPaths};
+use crate::resolve::fetch_index;
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::index::{self, Dependencies, Index, Package};
+use pm_lib::package::PackageName;
+use pm_lib::solver::{solve, PrereleasePolicy, ResolutionStrategy, Solution};
+
+pub const USAGE: &str = "Add a dependency to the manifest.
+
+Usage:
+    pm add [options] <package> [<constraint>...]
+
+Options:
+    -h, --help     Display this message.
+
+Without a constraint, the dependency is added as ^<latest version>. If the
+package is already a dependency, its constraint is replaced.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    arg_constraint: Vec<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package_name = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name: {}", args.arg_package))?;
+    let project_paths = find_project_paths()?;
+    let source = fs::read_to_string(&project_paths.manifest)?;
+    let index = fetch_index()?;
+
+    let constraint = if args.arg_constraint.is_empty() {
+        let prereleases = DependencyManifest::from_str(source.clone())?.prereleases;
+        let any = VersionConstraint::Range(None, None);
+        // Index versions are ordered from most to least preferred.
+        let latest = index
+            .get(&package_name)
+            .ok_or_else(|| format_err!("package not found in index: {}", package_name))?
+            .keys()
+            .find(|v| prereleases.allows(&package_name, &any, v))
+            .ok_or_else(|| format_err!("{} has no installable versions", package_name))?;
+        VersionConstraint::Caret(latest.clone())
+    } else {
+        let constraint_string = args.arg_constraint.join(" ");
+        VersionConstraint::from_str(&constraint_string)
+            .ok_or_else(|| format_err!("Invalid version constraint: {}", constraint_string))?
+    };
+
+    let new_source = add_dependency(&source, &package_name, &constraint)?;
+    update_manifest(&project_paths, &new_source, &index)?;
+    println!("Added {} {}", package_name, constraint);
+    Ok(())
+}
+
+/// Resolve the dependencies in `new_source` and write both the manifest and
+/// the new lockfile. Nothing is written if resolution fails.
+pub fn update_manifest(
+    project_paths: &ProjectPaths,
+    new_source: &str,
+    index: &Index,
+) -> Result<(), failure::Error> {
+    let manifest = DependencyManifest::from_str(new_source.to_string())?;
+    let dependencies = index::dependencies_from_slice(&manifest.dependencies);
+    let lockfile = Lockfile::from_file(project_paths)?;
+    let solution = solve_keeping_locked(
+        index,
+        &dependencies,
+        manifest.prereleases,
+        lockfile.as_ref(),
+    )?;
+    let lockfile = Lockfile::from_solution(&solution, index)?;
+    fs::write(&project_paths.manifest, new_source)?;
+    fs::write(&project_paths.lockfile, lockfile.to_string())?;
+    Ok(())
+}
+
+/// Solve `dependencies`, keeping the versions in `lockfile` wherever they
+/// still satisfy the manifest, so that adding or removing a dependency
+/// doesn't upgrade unrelated packages. If the locked versions can't be kept,
+/// everything is resolved again.
+fn solve_keeping_locked(
+    index: &Index,
+    dependencies: &Dependencies,
+    prereleases: PrereleasePolicy,
+    lockfile: Option<&Lockfile>,
+) -> Result<Solution, failure::Error> {
+    if let Some(lockfile) = lockfile {
+        let mut pinned = index.clone();
+        for locked in &lockfile.locked_dependencies {
+            if let Some(constraint) = dependencies.get(&locked.package_name) {
+                if !constraint.contains(&locked.version)
+                    || !prereleases.allows(&locked.package_name, constraint, &locked.version)
+                {
+                    continue;
+                }
+            }
+            let release = index
+                .get(&locked.package_name)
+                .and_then(|package| package.get_key_value(&locked.version));
+            if let Some((version, deps)) = release {
+                let mut package = Package::new();
+                package.insert(version.clone(), deps.clone());
+                pinned.insert(locked.package_name.clone(), package);
+            }
+        }
+        let solution = solve(
+            &pinned,
+            dependencies,
+            ResolutionStrategy::Highest,
+            prereleases.clone(),
+        );
+        if let Ok(solution) = solution {
+            return Ok(solution);
+        }
+    }
+    Ok(solve(
+        index,
+        dependencies,
+        ResolutionStrategy::Highest,
+        prereleases,
+    )?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lockfile::{LockedDependency, LockfileMeta};
+    use pm_lib::test_helpers::*;
+    use pm_lib::{deps, gen_registry};
+
+    fn lockfile(locked: &[(&str, &str)]) -> Lockfile {
+        Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: locked
+                .iter()
+                .map(|(name, version)| LockedDependency {
+                    package_name: pkg(name),
+                    version: ver(version),
+                    dependencies: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn keep_unrelated_locked_versions() {
+        let index = gen_registry!(
+            A => ( "1.0.0" => deps!(), "1.1.0" => deps!() ),
+            B => ( "1.0.0" => deps!(), "1.2.0" => deps!() )
+        );
+        let lockfile = lockfile(&[("A", "1.0.0")]);
+        let solution = solve_keeping_locked(
+            &index,
+            &deps!(A => "^1", B => "^1"),
+            PrereleasePolicy::default(),
+            Some(&lockfile),
+        )
+        .unwrap();
+        assert_eq!(solution.get(&pkg("A")), Some(&ver("1.0.0")));
+        assert_eq!(solution.get(&pkg("B")), Some(&ver("1.2.0")));
+    }
+
+    #[test]
+    fn resolve_unsatisfied_locked_versions() {
+        let index = gen_registry!(
+            A => ( "1.0.0" => deps!(), "1.1.0" => deps!(), "1.2.0" => deps!() ),
+            B => ( "1.0.0" => deps!(A => ">= 1.1.0"), "1.1.0" => deps!() )
+        );
+        let lockfile = lockfile(&[("A", "1.0.0"), ("B", "1.0.0")]);
+        let solution = solve_keeping_locked(
+            &index,
+            &deps!(A => "^1.2", B => "^1"),
+            PrereleasePolicy::default(),
+            Some(&lockfile),
+        )
+        .unwrap();
+        assert_eq!(solution.get(&pkg("A")), Some(&ver("1.2.0")));
+        assert_eq!(solution.get(&pkg("B")), Some(&ver("1.0.0")));
+    }
+}
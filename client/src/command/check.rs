@@ -0,0 +1,46 @@
+use std::fs;
+
+use crate::manifest::check_manifest;
+use crate::project::find_project_paths;
+
+pub const USAGE: &str = "Check the manifest for errors.
+
+Usage:
+    pm check [options]
+
+Options:
+    --message-format=<fmt>  Print errors as text or json [default: text].
+    -h, --help              Display this message.
+
+With --message-format json, each error is printed to stdout as a JSON object
+on its own line, with the message, the line and column where it starts and
+ends, and the rendered text. The package section is only checked if present.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_message_format: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let project_paths = find_project_paths()?;
+    let source = fs::read_to_string(&project_paths.manifest)?;
+    let result = check_manifest(source, &project_paths.root);
+    match args.flag_message_format.as_str() {
+        "text" => {
+            result?;
+            println!("No errors found in {}", project_paths.manifest.display());
+        }
+        "json" => {
+            if let Err(error) = result {
+                let diagnostics = error.diagnostics();
+                for diagnostic in &diagnostics {
+                    println!("{}", serde_json::to_string(diagnostic)?);
+                }
+                bail!("Found {} error(s) in the manifest", diagnostics.len());
+            }
+        }
+        format => bail!("Invalid message format {:?}; expected \"text\" or \"json\"", format),
+    }
+    Ok(())
+}
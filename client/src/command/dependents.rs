@@ -0,0 +1,170 @@
+use std::fmt::Write;
+
+use console::Style;
+
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::package::PackageName;
+use pm_lib::package_info::DependentsPage;
+
+use crate::registry::get;
+
+pub const USAGE: &str = "Show which packages in the registry depend on a package.
+
+Usage:
+    pm dependents [options] <package>
+
+Options:
+    --range=<range>    Only show packages which accept a release of <package>
+                       in this version range, like \"^1.0\".
+    --page=<page>      Show this page of the dependents [default: 1].
+    --limit=<limit>    Show this many dependents on a page [default: 50].
+    --format=<format>  Print the dependents as text or json [default: text].
+    -h, --help         Display this message.
+
+Shows the highest release of each dependent package, with the versions of
+<package> it accepts.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    flag_range: Option<String>,
+    flag_page: usize,
+    flag_limit: usize,
+    flag_format: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    if args.flag_format != "text" && args.flag_format != "json" {
+        bail!(
+            "Invalid format {:?}; expected \"text\" or \"json\"",
+            args.flag_format
+        );
+    }
+    let name = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name {:?}", args.arg_package))?;
+    let mut query = ordmap! {
+        "page".to_string() => args.flag_page.to_string(),
+        "limit".to_string() => args.flag_limit.to_string()
+    };
+    if let Some(range) = args.flag_range {
+        let range = VersionConstraint::from_str(&range)
+            .ok_or_else(|| format_err!("Invalid version range {:?}", range))?;
+        query.insert("range".to_string(), range.to_string());
+    }
+    let page: DependentsPage = get(
+        &format!("api/packages/{}/{}/dependents", name.namespace, name.name),
+        query,
+    )??;
+    if args.flag_format == "json" {
+        println!("{}", ::serde_json::to_string_pretty(&page)?);
+    } else {
+        print!("{}", render_dependents(&name, &page));
+    }
+    Ok(())
+}
+
+pub fn render_dependents(package: &PackageName, page: &DependentsPage) -> String {
+    let mut out = String::new();
+    if page.total == 0 {
+        writeln!(out, "No packages depend on {}.", package).unwrap();
+        return out;
+    }
+    if page.dependents.is_empty() {
+        writeln!(
+            out,
+            "There is no page {} of {} dependents of {}.",
+            page.page, page.total, package
+        )
+        .unwrap();
+        return out;
+    }
+    let releases: Vec<String> = page
+        .dependents
+        .iter()
+        .map(|dependent| format!("{} {}", dependent.package, dependent.version))
+        .collect();
+    let width = releases.iter().map(String::len).max().unwrap_or(0);
+    for (release, dependent) in releases.iter().zip(&page.dependents) {
+        writeln!(
+            out,
+            "{}  requires {}",
+            Style::new().bold().apply_to(format!("{:1$}", release, width)),
+            dependent.constraint
+        )
+        .unwrap();
+    }
+    let first = (page.page - 1) * page.limit + 1;
+    writeln!(
+        out,
+        "\nShowing {}-{} of {} dependents of {}.",
+        first,
+        first + page.dependents.len() - 1,
+        page.total,
+        package
+    )
+    .unwrap();
+    if first + page.dependents.len() <= page.total {
+        writeln!(out, "Use --page={} to see more.", page.page + 1).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::package_info::Dependent;
+    use pm_lib::test_helpers::{pkg, range, ver};
+
+    fn page(page: usize, total: usize, dependents: &[(&str, &str, &str)]) -> DependentsPage {
+        DependentsPage {
+            total,
+            page,
+            limit: 2,
+            dependents: dependents
+                .iter()
+                .map(|(package, version, constraint)| Dependent {
+                    package: pkg(package),
+                    version: ver(version),
+                    constraint: range(constraint),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn render_pages_of_dependents() {
+        let left_pad = pkg("test/left-pad");
+        assert_eq!(
+            render_dependents(
+                &left_pad,
+                &page(
+                    1,
+                    3,
+                    &[("test/pad-both", "1.0.0", "^1.0"), ("test/x", "10.2.0", "~1.2")]
+                )
+            ),
+            "test/pad-both 1.0.0  requires ^1.0
+test/x 10.2.0        requires ~1.2
+
+Showing 1-2 of 3 dependents of test/left-pad.
+Use --page=2 to see more.
+"
+        );
+        assert_eq!(
+            render_dependents(&left_pad, &page(2, 3, &[("test/y", "1.0.0", "^1.0")])),
+            "test/y 1.0.0  requires ^1.0
+
+Showing 3-3 of 3 dependents of test/left-pad.
+"
+        );
+        assert_eq!(
+            render_dependents(&left_pad, &page(3, 3, &[])),
+            "There is no page 3 of 3 dependents of test/left-pad.\n"
+        );
+        assert_eq!(
+            render_dependents(&left_pad, &page(1, 0, &[])),
+            "No packages depend on test/left-pad.\n"
+        );
+    }
+}
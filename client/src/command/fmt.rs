@@ -0,0 +1,37 @@
+use std::fs;
+
+use crate::manifest_formatter::format_manifest;
+use crate::project::find_project_paths;
+
+pub const USAGE: &str = "Rewrite the manifest in the canonical format.
+
+Usage:
+    pm fmt [options]
+
+Options:
+    --check        Don't write anything; fail if the manifest is not formatted.
+    -h, --help     Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_check: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let project_paths = find_project_paths()?;
+    let source = fs::read_to_string(&project_paths.manifest)?;
+    let formatted = format_manifest(source.clone())?;
+    if formatted == source {
+        return Ok(());
+    }
+    if args.flag_check {
+        bail!(
+            "{} is not formatted; run `pm fmt` to fix it",
+            project_paths.manifest.display()
+        );
+    }
+    fs::write(&project_paths.manifest, formatted)?;
+    println!("Formatted {}", project_paths.manifest.display());
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use std::fmt::Write;
+
+use chrono::NaiveDateTime;
+use console::Style;
+
+use pm_lib::package::PackageName;
+use pm_lib::package_info::{PackageInfo, ReleaseInfo, UserInfo};
+
+use crate::registry::get;
+
+pub const USAGE: &str = "Show what the registry knows about a package.
+
+Usage:
+    pm info [options] <package> [<version>]
+
+Options:
+    --format=<format>  Print the information as text or json [default: text].
+    --readme           Include the readme.
+    -h, --help         Display this message.
+
+Without a version, shows the package's owners and releases, and the details
+of its latest release.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    arg_version: Option<String>,
+    flag_format: String,
+    flag_readme: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    if args.flag_format != "text" && args.flag_format != "json" {
+        bail!(
+            "Invalid format {:?}; expected \"text\" or \"json\"",
+            args.flag_format
+        );
+    }
+    let name = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name {:?}", args.arg_package))?;
+    let path = format!("api/packages/{}/{}", name.namespace, name.name);
+    let json = args.flag_format == "json";
+    match args.arg_version {
+        Some(version) => {
+            let release: ReleaseInfo = get(&format!("{}/{}", path, version), ordmap![])??;
+            if json {
+                println!("{}", ::serde_json::to_string_pretty(&release)?);
+            } else {
+                print!("{}", render_release(&release, args.flag_readme));
+            }
+        }
+        None => {
+            let package: PackageInfo = get(&path, ordmap![])??;
+            if json {
+                println!("{}", ::serde_json::to_string_pretty(&package)?);
+            } else {
+                print!("{}", render_package(&package, args.flag_readme));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_time(time: i64) -> String {
+    NaiveDateTime::from_timestamp(time, 0)
+        .format("%Y-%m-%d %H:%M UTC")
+        .to_string()
+}
+
+fn format_user(user: &UserInfo) -> String {
+    format!("{} <{}> ({})", user.name, user.email, user.id)
+}
+
+fn field(out: &mut String, title: &str, value: &str) {
+    let title = Style::new()
+        .green()
+        .apply_to(format!("{:12}", format!("{}:", title)));
+    writeln!(out, "{}{}", title, value).unwrap();
+}
+
+fn heading(out: &mut String, title: &str) {
+    writeln!(
+        out,
+        "\n{}",
+        Style::new().green().apply_to(format!("{}:", title))
+    )
+    .unwrap();
+}
+
+pub fn render_release(release: &ReleaseInfo, readme: bool) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{} {}",
+        Style::new().bold().apply_to(&release.package),
+        release.version
+    )
+    .unwrap();
+    writeln!(out, "{}\n", release.description).unwrap();
+    if let Some(ref reason) = release.deleted {
+        let deleted_on = release.deleted_on.map(format_time).unwrap_or_default();
+        field(&mut out, "Deleted", &format!("{} {}", deleted_on, reason));
+    }
+    match (&release.license, &release.license_file) {
+        (Some(license), _) => field(&mut out, "License", license),
+        (None, Some(file)) => field(&mut out, "License", &format!("see {}", file.name)),
+        (None, None) => {}
+    }
+    if !release.authors.is_empty() {
+        field(&mut out, "Authors", &release.authors.join(", "));
+    }
+    if !release.keywords.is_empty() {
+        field(&mut out, "Keywords", &release.keywords.join(", "));
+    }
+    if let Some(ref homepage_url) = release.homepage_url {
+        field(&mut out, "Homepage", homepage_url);
+    }
+    if let Some(ref repository) = release.repository {
+        field(
+            &mut out,
+            "Repository",
+            &format!("{} ({})", repository.url, repository.type_),
+        );
+    }
+    if let Some(ref bugs_url) = release.bugs_url {
+        field(&mut out, "Bugs", bugs_url);
+    }
+    field(
+        &mut out,
+        "Published",
+        &format!(
+            "{} by {}",
+            format_time(release.publish_time),
+            format_user(&release.publisher)
+        ),
+    );
+    match release.signature {
+        Some(ref signature) => field(&mut out, "Signed", &signature.public_key),
+        None => field(&mut out, "Signed", "no"),
+    }
+    field(&mut out, "Downloads", &release.downloads.to_string());
+    if !release.dependencies.is_empty() {
+        heading(&mut out, "Dependencies");
+        for dependency in &release.dependencies {
+            writeln!(
+                out,
+                "    {} {}",
+                dependency.package_name, dependency.version_constraint
+            )
+            .unwrap();
+        }
+    }
+    if readme {
+        if let Some(ref file) = release.readme {
+            heading(&mut out, &file.name);
+            writeln!(out, "{}", file.contents.trim_end()).unwrap();
+        }
+    }
+    out
+}
+
+pub fn render_package(package: &PackageInfo, readme: bool) -> String {
+    let mut out = match package.latest {
+        Some(ref latest) => render_release(latest, readme),
+        None => format!(
+            "{}\nNo releases available.\n",
+            Style::new().bold().apply_to(&package.package)
+        ),
+    };
+    if let Some(ref reason) = package.deleted {
+        heading(&mut out, "Deleted");
+        let deleted_on = package.deleted_on.map(format_time).unwrap_or_default();
+        writeln!(out, "    {} {}", deleted_on, reason).unwrap();
+    }
+    heading(&mut out, "Owners");
+    for owner in &package.owners {
+        writeln!(out, "    {}", format_user(&owner.user)).unwrap();
+    }
+    heading(
+        &mut out,
+        &format!("Releases ({} downloads)", package.downloads),
+    );
+    for release in &package.releases {
+        write!(
+            out,
+            "    {:16}{}  {}  {} downloads",
+            release.version.to_string(),
+            format_time(release.publish_time),
+            release.publisher,
+            release.downloads
+        )
+        .unwrap();
+        if release.deleted.is_some() {
+            write!(out, "  (deleted)").unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::dependencies::Dependency;
+    use pm_lib::package_info::{OwnerInfo, ReleaseSummary};
+    use pm_lib::publication_request::NamedTextFile;
+    use pm_lib::test_helpers::{pkg, range, ver};
+
+    fn alice() -> UserInfo {
+        UserInfo {
+            id: "test:alice".to_string(),
+            name: "Alice".to_string(),
+            email: "alice@example.com".to_string(),
+            avatar: None,
+        }
+    }
+
+    fn release() -> ReleaseInfo {
+        ReleaseInfo {
+            package: pkg("test/left-pad"),
+            version: ver("1.1.0"),
+            description: "Pads strings on the left".to_string(),
+            authors: vec!["Alice".to_string()],
+            keywords: vec![],
+            homepage_url: None,
+            repository: None,
+            bugs_url: None,
+            license: Some("MIT".to_string()),
+            license_file: None,
+            manifest: None,
+            readme: Some(NamedTextFile {
+                name: "README.md".to_string(),
+                contents: "# left-pad\n".to_string(),
+            }),
+            dependencies: vec![Dependency {
+                package_name: pkg("test/strings"),
+                version_constraint: range("^2.0"),
+            }],
+            publisher: alice(),
+            publish_time: 1_500_000_000,
+            signature: None,
+            deleted: None,
+            deleted_on: None,
+            downloads: 7,
+            daily_downloads: vec![],
+        }
+    }
+
+    #[test]
+    fn render_release_info() {
+        assert_eq!(
+            render_release(&release(), false),
+            "test/left-pad 1.1.0
+Pads strings on the left
+
+License:    MIT
+Authors:    Alice
+Published:  2017-07-14 02:40 UTC by Alice <alice@example.com> (test:alice)
+Signed:     no
+Downloads:  7
+
+Dependencies:
+    test/strings ^2.0
+"
+        );
+        assert!(render_release(&release(), true).ends_with("\nREADME.md:\n# left-pad\n"));
+    }
+
+    #[test]
+    fn render_package_info() {
+        let summary = |version: &str, deleted: Option<&str>, downloads| ReleaseSummary {
+            version: ver(version),
+            description: "Pads strings on the left".to_string(),
+            publisher: "test:alice".to_string(),
+            publish_time: 1_500_000_000,
+            deleted: deleted.map(str::to_string),
+            downloads,
+        };
+        let package = PackageInfo {
+            package: pkg("test/left-pad"),
+            owners: vec![OwnerInfo {
+                user: alice(),
+                added_time: 1_400_000_000,
+            }],
+            releases: vec![
+                summary("2.0.0", Some("broken"), 3),
+                summary("1.1.0", None, 7),
+            ],
+            latest: Some(release()),
+            deleted: None,
+            deleted_on: None,
+            downloads: 10,
+        };
+        assert!(render_package(&package, false).ends_with(
+            "
+Owners:
+    Alice <alice@example.com> (test:alice)
+
+Releases (10 downloads):
+    2.0.0           2017-07-14 02:40 UTC  test:alice  3 downloads  (deleted)
+    1.1.0           2017-07-14 02:40 UTC  test:alice  7 downloads
+"
+        ));
+
+        let package = PackageInfo {
+            releases: vec![],
+            latest: None,
+            ..package
+        };
+        assert!(
+            render_package(&package, false).starts_with("test/left-pad\nNo releases available.\n")
+        );
+    }
+}
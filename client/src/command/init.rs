@@ -0,0 +1,236 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{stdin, ErrorKind, Write};
+
+use console::Term;
+
+use crate::git::GitScmProvider;
+use crate::manifest_parser::quote_string;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+pub const USAGE: &str = "Create a manifest in the current directory.
+
+Usage:
+    pm init [options]
+
+Options:
+    --namespace=<namespace>      The package namespace.
+    --name=<name>                The package name. Defaults to the directory
+                                 name.
+    --version=<version>          The initial version. Defaults to 0.1.0.
+    --license=<license>          An SPDX license expression. Defaults to MIT.
+    --description=<description>  A short description. Defaults to empty.
+    -y, --yes                    Don't prompt; use the defaults for everything
+                                 not given as a flag. Requires --namespace.
+    -h, --help                   Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_namespace: Option<String>,
+    flag_name: Option<String>,
+    flag_version: Option<String>,
+    flag_license: Option<String>,
+    flag_description: Option<String>,
+    flag_yes: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let root = env::current_dir()?;
+    let manifest_path = root.join("deps");
+    let already_exists = || format_err!("{} already exists", manifest_path.display());
+    // Only to avoid asking questions for nothing; the manifest is created
+    // with `create_new` below, so that one written meanwhile isn't replaced.
+    if manifest_path.exists() {
+        return Err(already_exists());
+    }
+
+    let prompter = Prompter {
+        term: Term::stderr(),
+        yes: args.flag_yes,
+    };
+    let default_name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+    let package_name = loop {
+        let namespace = prompter.ask("Namespace", &args.flag_namespace, None)?;
+        let name = prompter.ask("Package name", &args.flag_name, default_name.as_ref())?;
+        match PackageName::from_str(&format!("{}/{}", namespace, name)) {
+            Some(package_name) => break package_name,
+            None => {
+                let message = format!(
+                    "Invalid package name {}/{}. Namespaces may contain a-z, 0-9, _ and -, \
+                     names may also contain A-Z.",
+                    namespace, name
+                );
+                if prompter.yes || (args.flag_namespace.is_some() && args.flag_name.is_some()) {
+                    bail!("{}", message);
+                }
+                prompter.term.write_line(&message)?;
+            }
+        }
+    };
+    let version = loop {
+        let version = prompter.ask("Version", &args.flag_version, Some(&"0.1.0".to_string()))?;
+        match Version::from_str(&version) {
+            Some(version) => break version,
+            None if prompter.yes || args.flag_version.is_some() => {
+                bail!("Invalid version number: {}", version)
+            }
+            None => prompter
+                .term
+                .write_line(&format!("Invalid version number: {}", version))?,
+        }
+    };
+    let license = prompter.ask("License", &args.flag_license, Some(&"MIT".to_string()))?;
+    let description = prompter.ask("Description", &args.flag_description, Some(&String::new()))?;
+    let in_git_repository = GitScmProvider::new(&root).is_ok();
+
+    let source = render_manifest(
+        &package_name,
+        &version,
+        &license,
+        &description,
+        in_git_repository,
+    );
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&manifest_path)
+    {
+        Ok(file) => file,
+        Err(ref err) if err.kind() == ErrorKind::AlreadyExists => return Err(already_exists()),
+        Err(err) => return Err(err.into()),
+    };
+    file.write_all(source.as_bytes())?;
+    println!("Created {}", manifest_path.display());
+    Ok(())
+}
+
+struct Prompter {
+    term: Term,
+    yes: bool,
+}
+
+impl Prompter {
+    /// Return the flag value if given, else the default with `--yes`, else
+    /// ask until we get an answer.
+    fn ask(
+        &self,
+        question: &str,
+        flag: &Option<String>,
+        default: Option<&String>,
+    ) -> Result<String, failure::Error> {
+        if let Some(value) = flag {
+            return Ok(value.clone());
+        }
+        if self.yes {
+            return default.cloned().ok_or_else(|| {
+                format_err!(
+                    "{} has no default; pass it with --{}",
+                    question,
+                    question.to_lowercase().replace("package ", "")
+                )
+            });
+        }
+        loop {
+            match default {
+                Some(default) if !default.is_empty() => {
+                    self.term.write_str(&format!("{} ({}): ", question, default))?
+                }
+                _ => self.term.write_str(&format!("{}: ", question))?,
+            }
+            // Term::read_line ignores input that isn't from a terminal.
+            let mut answer = String::new();
+            if stdin().read_line(&mut answer)? == 0 {
+                bail!("Unexpected end of input; pass --yes to use the defaults");
+            }
+            let answer = answer.trim().to_string();
+            if !answer.is_empty() {
+                return Ok(answer);
+            }
+            if let Some(default) = default {
+                return Ok(default.clone());
+            }
+        }
+    }
+}
+
+fn render_manifest(
+    package_name: &PackageName,
+    version: &Version,
+    license: &str,
+    description: &str,
+    in_git_repository: bool,
+) -> String {
+    let files = if in_git_repository {
+        "    // Add all files tracked by Git:
+    add_committed \".\"
+"
+    } else {
+        "    // List the files to publish, e.g.:
+    //add_any \"src/**\"
+"
+    };
+    format!(
+        "dependencies {{
+}}
+
+package {{
+  name {}
+  version {}
+
+  description {}
+  license {}
+
+  files {{
+{}  }}
+}}
+",
+        quote_string(&package_name.to_string()),
+        quote_string(&version.to_string()),
+        quote_string(description),
+        quote_string(license),
+        files
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::manifest::{DependencyManifest, Manifest};
+    use pm_lib::test_helpers::{pkg, ver};
+    use std::path::Path;
+
+    #[test]
+    fn rendered_manifest_parses() {
+        let source = render_manifest(
+            &pkg("a"),
+            &ver("0.1.0"),
+            "MIT OR Apache-2.0",
+            "A \"quoted\"\tdescription\\",
+            false,
+        );
+        let manifest = Manifest::from_str(source, Path::new(".")).unwrap();
+        assert_eq!(manifest.name, pkg("a"));
+        assert_eq!(manifest.version, ver("0.1.0"));
+        assert_eq!(manifest.license, Some("MIT OR Apache-2.0".to_string()));
+        assert_eq!(manifest.description, "A \"quoted\"\tdescription\\");
+        assert!(manifest.files.is_empty());
+        assert!(manifest.dependencies.is_empty());
+    }
+
+    #[test]
+    fn rendered_manifest_adds_committed_files() {
+        let source = render_manifest(&pkg("a"), &ver("0.1.0"), "MIT", "", true);
+        assert!(source.contains(
+            "  files {\n    // Add all files tracked by Git:\n    add_committed \".\"\n  }"
+        ));
+        assert!(!source.contains("add_any"));
+        // Evaluating `add_committed` needs a Git repository, so only check
+        // that the rest of the manifest parses.
+        let manifest = DependencyManifest::from_str(source).unwrap();
+        assert!(manifest.dependencies.is_empty());
+    }
+}
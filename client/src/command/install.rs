@@ -1,11 +1,14 @@
 use std::fs;
 
+use crate::config::get_config;
 use crate::lockfile::Lockfile;
-use crate::manifest::Manifest;
-use pm_lib::solver::{solve, Solution};
+use crate::manifest::DependencyManifest;
+use pm_lib::solver::{solve, ResolutionStrategy, Solution};
 use crate::project::find_project_paths;
-use crate::resolve::fetch_index;
+use crate::resolve::{fetch_index, fetch_registry_index, fetch_tar_br};
+use crate::trust::TrustPolicy;
 
+use pm_lib::archive::{validate_archive, ArchiveLimits};
 use pm_lib::index;
 
 pub const USAGE: &str = "Install dependencies.
@@ -14,26 +17,50 @@ Usage:
     pm install [options]
 
 Options:
-    -h, --help     Display this message.
+    --resolve=<strategy>  Pick the highest or lowest version matching each
+                          constraint [default: highest]. The lowest versions
+                          are not written to the lockfile.
+    -h, --help            Display this message.
+
+Releases covered by the trust policy in the config file must be signed with a
+trusted key, and are downloaded to verify their signatures. Downloaded archives
+must stay within the limits in the [archive] section of the config file.
 ";
 
 #[derive(Debug, Deserialize)]
-pub struct Args {}
+pub struct Args {
+    flag_resolve: String,
+}
 
-pub fn execute(_args: Args) -> Result<(), failure::Error> {
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let strategy: ResolutionStrategy = args.flag_resolve.parse()?;
     let project_paths = find_project_paths()?;
-    let manifest = Manifest::from_file(&project_paths)?;
+    let manifest = DependencyManifest::from_file(&project_paths)?;
     let mut maybe_solution: Option<Solution> = None;
     let mut maybe_new_lockfile: Option<Lockfile> = None;
-    if let Some(lockfile) = Lockfile::from_file(&project_paths)? {
-        maybe_solution = lockfile.to_solution_if_up_to_date(&manifest.dependencies)?;
+    // A lockfile produced with a different strategy would still look up to
+    // date, so we only reuse it for the default strategy.
+    if strategy == ResolutionStrategy::Highest {
+        if let Some(lockfile) = Lockfile::from_file(&project_paths)? {
+            maybe_solution = lockfile
+                .to_solution_if_up_to_date(&manifest.dependencies, &manifest.prereleases)?;
+        }
     }
     if maybe_solution.is_none() {
         let index = fetch_index()?;
         let dependencies = index::dependencies_from_slice(&manifest.dependencies);
-        maybe_solution = Some(solve(&index, &dependencies)?);
-        if let Some(ref solution) = maybe_solution {
-            maybe_new_lockfile = Some(Lockfile::from_solution(solution, &index)?);
+        maybe_solution = Some(solve(
+            &index,
+            &dependencies,
+            strategy,
+            manifest.prereleases.clone(),
+        )?);
+        // A lockfile of the lowest versions would look up to date to the
+        // next plain `pm install`, which would then keep them for good.
+        if strategy == ResolutionStrategy::Highest {
+            if let Some(ref solution) = maybe_solution {
+                maybe_new_lockfile = Some(Lockfile::from_solution(solution, &index)?);
+            }
         }
         // TODO use existing lockfile (if any) in resolution
     }
@@ -43,12 +70,45 @@ pub fn execute(_args: Args) -> Result<(), failure::Error> {
         let lockfile_string = format!("{}", new_lockfile);
         fs::write(&project_paths.lockfile, &lockfile_string)?;
         println!("Updating lockfile:\n{}", &lockfile_string);
+    } else if strategy == ResolutionStrategy::Lowest {
+        println!("Leaving the lockfile alone for --resolve=lowest.");
     } else {
         println!("Lockfile is up to date.");
     }
 
-    // install_to_disk()
+    let config = get_config()?;
+    if !config.trust.is_empty() {
+        verify_signatures(&solution, &config.trust, &config.archive)?;
+    }
+
+    Ok(())
+}
 
-    // TODO: read manifest without parsing files section (split up?)
+fn verify_signatures(
+    solution: &Solution,
+    policy: &TrustPolicy,
+    limits: &ArchiveLimits,
+) -> Result<(), failure::Error> {
+    let registry_index = fetch_registry_index()?;
+    let mut verified = 0;
+    for (name, version) in solution {
+        let publisher = registry_index.publisher(name, version);
+        let signed = registry_index.signature(name, version);
+        if !policy.covers(name, publisher) {
+            continue;
+        }
+        policy.check(name, version, publisher, signed)?;
+        if let Some(signed) = signed {
+            let tar_br = fetch_tar_br(name, version)?;
+            validate_archive(&tar_br[..], limits)
+                .map_err(|error| format_err!("{} {}: {}", name, version, error))?;
+            signed
+                .signature
+                .verify(name, version, &tar_br)
+                .map_err(|error| format_err!("{} {}: {}", name, version, error))?;
+            verified += 1;
+        }
+    }
+    println!("Verified signatures of {} package(s).", verified);
     Ok(())
 }
@@ -0,0 +1,63 @@
+use std::io::Cursor;
+
+use pm_lib::signing::SigningKey;
+
+use crate::config::{read_signing_key, write_signing_key};
+use crate::registry::post;
+
+pub const USAGE: &str = "Manage the key used to sign releases.
+
+Usage:
+    pm key generate [options]
+    pm key show
+    pm key register
+
+Options:
+    --force        Replace the existing key.
+    -h, --help     Display this message.
+
+`pm key generate` creates a new ed25519 key in ~/.package-manager/signing_key.
+`pm key register` adds its public key to your registry account, after which
+`pm publish` signs each release with it. `pm key show` prints the public key,
+for others to add to their trust policy.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    cmd_generate: bool,
+    cmd_show: bool,
+    cmd_register: bool,
+    flag_force: bool,
+}
+
+#[derive(Serialize)]
+struct SigningKeyRequest {
+    public_key: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    if args.cmd_generate {
+        if read_signing_key()?.is_some() && !args.flag_force {
+            bail!("A signing key already exists; use --force to replace it");
+        }
+        let key = SigningKey::generate();
+        let path = write_signing_key(&key)?;
+        println!("Wrote a new signing key to {}", path.display());
+        println!("Public key: {}", key.public_key());
+        println!("Run `pm key register` to add it to your registry account.");
+        return Ok(());
+    }
+
+    let key = read_signing_key()?
+        .ok_or_else(|| format_err!("No signing key found; run `pm key generate` first"))?;
+    if args.cmd_show {
+        println!("{}", key.public_key());
+    } else if args.cmd_register {
+        let request = ::serde_json::to_vec(&SigningKeyRequest {
+            public_key: key.public_key(),
+        })?;
+        post::<(), _>("keys", ordmap![], Cursor::new(request))??;
+        println!("Registered {}", key.public_key());
+    }
+    Ok(())
+}
@@ -20,7 +20,8 @@ use tokio::runtime::Runtime;
 use url::{form_urlencoded, Url};
 use webbrowser;
 
-use crate::config::{write_config, Auth, Config};
+use crate::config::{get_config, write_config, Auth};
+use crate::registry::registry_url;
 
 pub const USAGE: &str = "Login.
 
@@ -196,8 +197,7 @@ pub fn execute(_: Args) -> Result<(), failure::Error> {
     };
     let server = Server::bind(&socket).serve(service);
 
-    // TODO This is the URL for the registry server, it should not be localhost.
-    let mut url = Url::parse("http://localhost:8000/login_client").unwrap();
+    let mut url = Url::parse(&format!("{}/login_client", registry_url()))?;
     url.query_pairs_mut().append_pair("token", &secret);
     url.query_pairs_mut()
         .append_pair("callback", &format!("http://{}", server.local_addr()));
@@ -215,10 +215,9 @@ pub fn execute(_: Args) -> Result<(), failure::Error> {
         .get()
         .expect("unable to get auth token from web server");
 
-    // let config = get_config()?;
-    write_config(&Config {
-        auth: Auth { token: Some(token) },
-    })?;
+    let mut config = get_config()?;
+    config.auth = Auth { token: Some(token) };
+    write_config(&config)?;
 
     Ok(())
 }
@@ -1,4 +1,17 @@
+pub mod add;
+pub mod check;
+pub mod dependents;
+pub mod fmt;
+pub mod info;
+pub mod init;
 pub mod install;
+pub mod key;
 pub mod login;
+pub mod outdated;
+pub mod pack;
 pub mod publish;
+pub mod remove;
 pub mod search;
+pub mod tree;
+pub mod verify_log;
+pub mod why;
@@ -0,0 +1,294 @@
+use std::cmp::{max, Ordering};
+use std::collections::BTreeMap;
+
+use console::Style;
+
+use crate::lockfile::Lockfile;
+use crate::manifest::DependencyManifest;
+use crate::project::find_project_paths;
+use crate::resolve::fetch_index;
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::dependencies::Dependency;
+use pm_lib::index::Index;
+use pm_lib::package::PackageName;
+use pm_lib::solver::PrereleasePolicy;
+use pm_lib::version::{caret_bump, Version};
+
+pub const USAGE: &str = "List locked dependencies that have newer versions.
+
+Usage:
+    pm outdated [options]
+
+Options:
+    --format=<format>  Print the report as text or json [default: text].
+    -h, --help         Display this message.
+
+Wanted is the newest version allowed by all constraints on the package.
+Compatible is the newest version that upgrading to is not a breaking change
+according to semver. Latest is the newest version in the registry, and is
+marked as breaking if upgrading to it is.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_format: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct OutdatedPackage {
+    name: PackageName,
+    locked: Version,
+    /// `None` if no version in the registry satisfies all constraints.
+    wanted: Option<Version>,
+    /// The newest version semver-compatible with `locked`, if newer than it.
+    compatible: Option<Version>,
+    latest: Version,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    if args.flag_format != "text" && args.flag_format != "json" {
+        bail!(
+            "Invalid format {:?}; expected \"text\" or \"json\"",
+            args.flag_format
+        );
+    }
+    let project_paths = find_project_paths()?;
+    let lockfile = Lockfile::from_file(&project_paths)?
+        .ok_or_else(|| format_err!("No lockfile found; run `pm install` to create one"))?;
+    let manifest = DependencyManifest::from_file(&project_paths)?;
+    let index = fetch_index()?;
+
+    let outdated = find_outdated(
+        &lockfile,
+        &manifest.dependencies,
+        &index,
+        &manifest.prereleases,
+    )?;
+    if args.flag_format == "json" {
+        println!("{}", ::serde_json::to_string_pretty(&outdated)?);
+    } else if outdated.is_empty() {
+        println!("All dependencies are up to date.");
+    } else {
+        print_outdated(&outdated);
+    }
+    Ok(())
+}
+
+/// Compare each locked version against the registry. The constraints on a
+/// package are those from the manifest plus those of the locked packages
+/// depending on it.
+pub fn find_outdated(
+    lockfile: &Lockfile,
+    dependencies: &[Dependency],
+    index: &Index,
+    prereleases: &PrereleasePolicy,
+) -> Result<Vec<OutdatedPackage>, failure::Error> {
+    let mut constraints: BTreeMap<&PackageName, Vec<VersionConstraint>> = BTreeMap::new();
+    let sub_dependencies = lockfile
+        .locked_dependencies
+        .iter()
+        .flat_map(|locked_dependency| &locked_dependency.dependencies);
+    for dependency in dependencies.iter().chain(sub_dependencies) {
+        constraints
+            .entry(&dependency.package_name)
+            .or_default()
+            .push(dependency.version_constraint.clone());
+    }
+
+    let mut outdated = vec![];
+    for locked_dependency in &lockfile.locked_dependencies {
+        let name = &locked_dependency.package_name;
+        let locked = &locked_dependency.version;
+        let package = index
+            .get(name)
+            .ok_or_else(|| format_err!("package not found in index: {}", name))?;
+        let constraint =
+            VersionConstraint::All(constraints.get(name).cloned().unwrap_or_default());
+        let any = VersionConstraint::Range(None, None);
+        // Index versions are ordered from most to least preferred.
+        let wanted = package
+            .keys()
+            .find(|v| constraint.contains(v) && prereleases.allows(name, &constraint, v))
+            .cloned();
+        let latest = match package.keys().find(|v| prereleases.allows(name, &any, v)) {
+            Some(latest) => latest.clone(),
+            None => continue,
+        };
+        if wanted.as_ref() == Some(locked) && &latest == locked {
+            continue;
+        }
+        let bump = caret_bump(locked);
+        let compatible = package
+            .keys()
+            .find(|v| v.semver_cmp(&bump) == Ordering::Less && prereleases.allows(name, &any, v))
+            .filter(|v| v.semver_cmp(locked) == Ordering::Greater)
+            .cloned();
+        outdated.push(OutdatedPackage {
+            name: name.clone(),
+            locked: locked.clone(),
+            wanted,
+            compatible,
+            latest,
+        });
+    }
+    Ok(outdated)
+}
+
+fn print_outdated(outdated: &[OutdatedPackage]) {
+    let header = Style::new().green();
+    let breaking = Style::new().red();
+
+    let rows: Vec<[String; 5]> = outdated
+        .iter()
+        .map(|o| {
+            [
+                o.name.to_string(),
+                o.locked.to_string(),
+                o.wanted
+                    .as_ref()
+                    .map_or_else(|| "-".to_string(), Version::to_string),
+                o.compatible
+                    .as_ref()
+                    .map_or_else(|| "-".to_string(), Version::to_string),
+                o.latest.to_string(),
+            ]
+        })
+        .collect();
+    let titles = ["Package", "Locked", "Wanted", "Compatible", "Latest"];
+    let mut widths = [0; 5];
+    for (i, title) in titles.iter().enumerate() {
+        widths[i] = rows.iter().map(|row| row[i].len()).fold(title.len(), max);
+    }
+
+    println!(
+        "{}",
+        header.apply_to(format!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}  {}",
+            titles[0],
+            titles[1],
+            titles[2],
+            titles[3],
+            titles[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        ))
+    );
+    for (row, o) in rows.iter().zip(outdated) {
+        let latest = if o.latest.semver_cmp(&caret_bump(&o.locked)) == Ordering::Less {
+            row[4].clone()
+        } else {
+            breaking.apply_to(format!("{} (breaking)", row[4])).to_string()
+        };
+        println!(
+            "{:w0$}  {:w1$}  {:w2$}  {:w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            latest,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lockfile::{LockedDependency, LockfileMeta};
+    use pm_lib::test_helpers::*;
+    use pm_lib::{deps, gen_registry};
+
+    fn dep(name: &str, constraint: &str) -> Dependency {
+        Dependency {
+            package_name: pkg(name),
+            version_constraint: range(constraint),
+        }
+    }
+
+    #[test]
+    fn find_outdated_packages() {
+        let index = gen_registry!(
+            A => ( "1.0.0" => deps!(B => "^1"), "1.1.0" => deps!(B => "^1"),
+                   "2.0.0" => deps!(), "3.0.0-beta" => deps!() ),
+            B => ( "1.0.0" => deps!(), "1.5.0" => deps!(), "2.0.0" => deps!() ),
+            C => ( "1.0.0" => deps!(), "1.2.0" => deps!() ),
+            D => ( "1.2.0" => deps!(), "2.0.0" => deps!() )
+        );
+        let lockfile = Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: vec![
+                LockedDependency {
+                    package_name: pkg("A"),
+                    version: ver("1.0.0"),
+                    dependencies: vec![dep("B", "^1")],
+                },
+                LockedDependency {
+                    package_name: pkg("B"),
+                    version: ver("1.0.0"),
+                    dependencies: vec![],
+                },
+                LockedDependency {
+                    package_name: pkg("C"),
+                    version: ver("1.0.0"),
+                    dependencies: vec![],
+                },
+                LockedDependency {
+                    package_name: pkg("D"),
+                    version: ver("1.2.0"),
+                    dependencies: vec![],
+                },
+            ],
+        };
+        let dependencies = vec![
+            dep("A", "^1"),
+            dep("B", "< 1.5"),
+            dep("C", "1.0.0"),
+            dep("D", "^1"),
+        ];
+
+        assert_eq!(
+            find_outdated(
+                &lockfile,
+                &dependencies,
+                &index,
+                &PrereleasePolicy::default()
+            )
+            .unwrap(),
+            vec![
+                OutdatedPackage {
+                    name: pkg("A"),
+                    locked: ver("1.0.0"),
+                    wanted: Some(ver("1.1.0")),
+                    compatible: Some(ver("1.1.0")),
+                    latest: ver("2.0.0"),
+                },
+                OutdatedPackage {
+                    name: pkg("B"),
+                    locked: ver("1.0.0"),
+                    wanted: Some(ver("1.0.0")),
+                    compatible: Some(ver("1.5.0")),
+                    latest: ver("2.0.0"),
+                },
+                OutdatedPackage {
+                    name: pkg("C"),
+                    locked: ver("1.0.0"),
+                    wanted: Some(ver("1.0.0")),
+                    compatible: Some(ver("1.2.0")),
+                    latest: ver("1.2.0"),
+                },
+                OutdatedPackage {
+                    name: pkg("D"),
+                    locked: ver("1.2.0"),
+                    wanted: Some(ver("1.2.0")),
+                    compatible: None,
+                    latest: ver("2.0.0"),
+                },
+            ]
+        );
+    }
+}
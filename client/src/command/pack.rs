@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::archive::{build_archive, compress};
+use crate::command::publish::{list_file, make_progress};
+use crate::files::PACK_DIR;
+use crate::io::ProgressIO;
+use crate::manifest::Manifest;
+use crate::project::find_project_paths;
+
+pub const USAGE: &str = "Build the release archive without publishing it.
+
+Usage:
+    pm pack [options]
+
+Options:
+    -o, --output=<path>  Where to write the archive.
+    -v, --verbose        List files being added to the archive.
+    -q, --quiet          Don't print any descriptive messages.
+    -h, --help           Display this message.
+
+The archive is written to target/<namespace>-<name>-<version>.tar.br in the
+project root unless --output is given. Archives there are never packed
+themselves. It is built exactly as `pm publish` builds it, and the same source
+always gives the same bytes, so you can check that a published release matches
+a given commit.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_output: Option<String>,
+    flag_verbose: bool,
+    flag_quiet: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let project_paths = find_project_paths()?;
+    let manifest = Manifest::from_file(&project_paths)?;
+
+    let tar = build_archive(&project_paths.root, &manifest.files, |path| {
+        list_file(path, args.flag_verbose, args.flag_quiet)
+    })?;
+    let compress_progress = make_progress("Compressing:", tar.len(), args.flag_quiet);
+    let tar_br = compress(ProgressIO::reader_from(tar, |c, _| {
+        compress_progress.set_position(c as u64)
+    }))?;
+    compress_progress.finish_and_clear();
+
+    let output = match args.flag_output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let pack_dir = project_paths.root.join(PACK_DIR);
+            fs::create_dir_all(&pack_dir)?;
+            pack_dir.join(format!(
+                "{}-{}-{}.tar.br",
+                manifest.name.namespace, manifest.name.name, manifest.version
+            ))
+        }
+    };
+    fs::write(&output, &tar_br)?;
+    if !args.flag_quiet {
+        println!("Wrote {} ({} bytes)", output.display(), tar_br.len());
+    }
+    Ok(())
+}
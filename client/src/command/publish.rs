@@ -1,17 +1,15 @@
-use std::fs::File;
-use std::path::PathBuf;
 use std::sync::Arc;
 
-use brotli;
 use indicatif::{ProgressBar, ProgressStyle};
 use rmp_serde::encode;
-use tar;
 
-use pm_lib::publication_request::PublicationRequest;
+use pm_lib::publication_request::{NamedTextFile, PublicationRequest};
 
 use failure;
+use crate::archive::{build_archive, compress};
+use crate::config::read_signing_key;
 use crate::io::ProgressIO;
-use crate::project::{find_project_paths, ProjectPaths};
+use crate::project::find_project_paths;
 use crate::manifest::Manifest;
 use crate::registry::post;
 
@@ -25,6 +23,8 @@ Options:
     -q, --quiet    Don't print any descriptive messages.
     --dry-run      Run through the procedure, but don't actually publish.
     -h, --help     Display this message.
+
+If you have a signing key (see `pm key`), the release is signed with it.
 ";
 
 #[derive(Debug, Deserialize)]
@@ -34,7 +34,7 @@ pub struct Args {
     flag_quiet: bool,
 }
 
-fn make_progress(msg: &str, len: usize, quiet: bool) -> ProgressBar {
+pub fn make_progress(msg: &str, len: usize, quiet: bool) -> ProgressBar {
     let bar = if quiet {
         ProgressBar::hidden()
     } else {
@@ -56,25 +56,26 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
         println!("Building release {}-{}...", manifest.name, manifest.version);
     }
 
-    let tar = build_archive(
-        manifest.files.iter().map(PathBuf::from).collect(),
-        &project_paths,
-        &args,
-    )?;
+    let tar = build_archive(&project_paths.root, &manifest.files, |path| {
+        list_file(path, args.flag_verbose, args.flag_quiet)
+    })?;
 
-    let mut tar_br = vec![];
     let compress_progress = make_progress("Compressing:", tar.len(), args.flag_quiet);
-
-    let mut brotli_encoder_params = brotli::enc::BrotliEncoderInitParams();
-    brotli_encoder_params.quality = 9;
-    brotli_encoder_params.lgwin = 22; // log2 of window size
-    brotli::BrotliCompress(
-        &mut ProgressIO::reader_from(tar, |c, _| compress_progress.set_position(c as u64)),
-        &mut tar_br,
-        &brotli_encoder_params,
-    )?;
+    let tar_br = compress(ProgressIO::reader_from(tar, |c, _| {
+        compress_progress.set_position(c as u64)
+    }))?;
     compress_progress.finish_and_clear();
 
+    let signature = match read_signing_key()? {
+        Some(key) => {
+            if !args.flag_quiet {
+                println!("Signing with key {}", key.public_key());
+            }
+            Some(key.sign_release(&manifest.name, &manifest.version, &tar_br))
+        }
+        None => None,
+    };
+
     let req = PublicationRequest {
         namespace: manifest.name.namespace.clone(),
         name: manifest.name.name.clone(),
@@ -84,18 +85,26 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
         authors: manifest.authors.clone(),
         keywords: manifest.keywords.clone(),
         homepage_url: manifest.homepage.clone(),
-        repository: None, // TODO
-        bugs_url: None,   // TODO
+        repository: manifest.repository.clone(),
+        bugs_url: manifest.bugs.clone(),
         license: manifest.license.clone(),
-        license_file: None, // TODO
-        manifest: None,     // TODO
-        readme: None,       // TODO
+        license_file: manifest.license_file.clone(),
+        manifest: Some(NamedTextFile {
+            name: "deps".to_string(),
+            contents: manifest.source.clone(),
+        }),
+        readme: manifest.readme.clone(),
 
         dependencies: manifest.dependencies.clone(),
 
         tar_br,
+        signature,
     };
 
+    // The registry checks this too, but we can fail before uploading.
+    req.validate_metadata()
+        .map_err(|error| format_err!("{} [{}]", error, error.code))?;
+
     let payload = encode::to_vec_named(&req)?;
     let upload_progress = Arc::new(make_progress("Uploading:", payload.len(), args.flag_quiet));
     let up = upload_progress.clone(); // lifetime management shenanigans
@@ -120,28 +129,12 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
     Ok(())
 }
 
-fn build_archive(
-    files: Vec<PathBuf>,
-    project_paths: &ProjectPaths,
-    args: &Args,
-) -> Result<Vec<u8>, failure::Error> {
-    let mut tar = tar::Builder::new(Vec::new());
-    for local_path in files {
-        if args.flag_verbose {
-            let repr = local_path
-                .to_str()
-                .unwrap_or_else(|| panic!("non-representable file name: {:?}", local_path));
-            if args.flag_quiet {
-                println!("{}", repr)
-            } else {
-                println!("    {}", repr)
-            }
+pub fn list_file(path: &str, verbose: bool, quiet: bool) {
+    if verbose {
+        if quiet {
+            println!("{}", path)
+        } else {
+            println!("    {}", path)
         }
-        let mut path = project_paths.root.clone();
-        path.push(local_path.clone());
-        let mut file = File::open(path)?;
-        tar.append_file(local_path, &mut file)?;
     }
-    tar.finish()?;
-    Ok(tar.into_inner()?)
 }
@@ -0,0 +1,33 @@
+use std::fs;
+
+use crate::command::add::update_manifest;
+use crate::manifest_editor::remove_dependency;
+use crate::project::find_project_paths;
+use crate::resolve::fetch_index;
+use pm_lib::package::PackageName;
+
+pub const USAGE: &str = "Remove a dependency from the manifest.
+
+Usage:
+    pm remove [options] <package>
+
+Options:
+    -h, --help     Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package_name = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name: {}", args.arg_package))?;
+    let project_paths = find_project_paths()?;
+    let source = fs::read_to_string(&project_paths.manifest)?;
+    let new_source = remove_dependency(&source, &package_name)?;
+    let index = fetch_index()?;
+    update_manifest(&project_paths, &new_source, &index)?;
+    println!("Removed {}", package_name);
+    Ok(())
+}
@@ -3,84 +3,139 @@ use std::cmp::{max, min};
 use console::{Style, Term};
 use failure;
 
+use pm_lib::search::SearchResults;
+
 use crate::registry;
 
-pub const USAGE: &str = "Search for packages by keyword.
+pub const USAGE: &str = "Search the registry for packages.
 
 Usage:
-    pm search [options] <namespace> <keyword>...
+    pm search [options] [<term>...]
 
 Options:
-    -h, --help     Display this message.
+    --namespace=<ns>  Only search these namespaces, separated by commas.
+    --page=<page>     Show this page of the results [default: 1].
+    --limit=<limit>   Show this many results on a page [default: 20].
+    --json            Print the results as JSON.
+    -h, --help        Display this message.
+
+Finds the packages whose name, keywords or description contain every term,
+the most relevant first. A term like keyword:<keyword> only finds packages
+with that keyword, and owner:<user> only packages owned by that user, given
+by ID (like github:octocat) or name. Without terms, lists the packages in
+the namespaces given.
 ";
 
 #[derive(Debug, Deserialize)]
 pub struct Args {
-    arg_namespace: String,
-    arg_keyword: Vec<String>,
-}
-
-#[derive(Deserialize)]
-#[allow(dead_code)]
-pub struct SearchResult {
-    name: String,
-    version: String,
-    publisher: String,
-    description: String,
+    arg_term: Vec<String>,
+    flag_namespace: Option<String>,
+    flag_page: usize,
+    flag_limit: usize,
+    flag_json: bool,
 }
 
 pub fn execute(args: Args) -> Result<(), failure::Error> {
-    match registry::get::<Vec<SearchResult>>(
-        "search",
-        ordmap!{"ns".to_string() => args.arg_namespace, "q".to_string() => args.arg_keyword.join(" ")},
-    )? {
-        Ok(ref results) if results.is_empty() => println!("No results found!"),
+    let mut query = ordmap! {
+        "q".to_string() => args.arg_term.join(" "),
+        "page".to_string() => args.flag_page.to_string(),
+        "limit".to_string() => args.flag_limit.to_string()
+    };
+    if let Some(namespace) = args.flag_namespace {
+        query.insert("ns".to_string(), namespace);
+    }
+    match registry::get::<SearchResults>("search", query)? {
+        Ok(ref results) if args.flag_json => {
+            println!("{}", ::serde_json::to_string_pretty(results)?)
+        }
+        Ok(ref results) if results.total == 0 => println!("No results found!"),
+        Ok(ref results) if results.results.is_empty() => println!(
+            "There is no page {} of the {} results.",
+            results.page, results.total
+        ),
         Ok(ref results) => print_results(results),
         Err(ref msg) => println!("Registry response: {}", msg),
     };
     Ok(())
 }
 
-fn print_results(results: &[SearchResult]) {
+fn print_results(page: &SearchResults) {
+    let results = &page.results;
     let border = Style::new().dim();
     let header = Style::new().green();
     let package = Style::new().bold();
 
     let term = Term::stdout();
     let width = term.size().1 as usize;
-    let max_avail = width - 2;
+    let max_avail = width.saturating_sub(3);
     let bar = border.apply_to("|");
     let w_pkg = max(
         7,
-        min(24, results.iter().map(|r| r.name.len()).max().unwrap()),
+        min(
+            32,
+            results
+                .iter()
+                .map(|r| r.package.to_string().len())
+                .max()
+                .unwrap(),
+        ),
     );
     let h_pkg = header.apply_to(format!("{:1$}", "Package", w_pkg));
     let w_ver = max(
         7,
-        min(16, results.iter().map(|r| r.version.len()).max().unwrap()),
+        min(
+            16,
+            results
+                .iter()
+                .map(|r| r.version.to_string().len())
+                .max()
+                .unwrap(),
+        ),
     );
     let h_ver = header.apply_to(format!("{:1$}", "Version", w_ver));
-    let w_desc = max_avail - w_pkg - w_ver;
+    let w_dl = max(
+        9,
+        results
+            .iter()
+            .map(|r| r.downloads.to_string().len())
+            .max()
+            .unwrap(),
+    );
+    let h_dl = header.apply_to(format!("{:>1$}", "Downloads", w_dl));
+    let w_desc = max_avail.saturating_sub(w_pkg + w_ver + w_dl);
     let h_desc = header.apply_to(format!("{:1$}", "Description", w_desc));
     let sep = border.apply_to(format!(
-        "{}|{}|{}",
+        "{}|{}|{}|{}",
         "-".repeat(w_pkg),
         "-".repeat(w_ver),
+        "-".repeat(w_dl),
         "-".repeat(w_desc)
     ));
     println!("{}", sep);
-    println!("{}{}{}{}{}", h_pkg, bar, h_ver, bar, h_desc);
+    println!("{}{}{}{}{}{}{}", h_pkg, bar, h_ver, bar, h_dl, bar, h_desc);
     println!("{}", sep);
     for result in results {
         println!(
-            "{:4$}{3}{:5$}{3}{}",
-            package.apply_to(&result.name),
-            result.version,
+            "{:5$}{4}{:6$}{4}{:>7$}{4}{}",
+            package.apply_to(result.package.to_string()),
+            result.version.to_string(),
+            result.downloads,
             result.description,
             bar,
             w_pkg,
-            w_ver
+            w_ver,
+            w_dl
         )
     }
     println!("{}", sep);
+    let first = (page.page - 1) * page.limit + 1;
+    println!(
+        "Showing {}-{} of {} results.",
+        first,
+        first + results.len() - 1,
+        page.total
+    );
+    if first + results.len() <= page.total {
+        println!("Use --page={} to see more.", page.page + 1);
+    }
 }
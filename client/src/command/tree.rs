@@ -0,0 +1,77 @@
+use std::env;
+
+use crate::dependency_graph::{render_dot, render_json, render_text, DependencyGraph, Node};
+use crate::lockfile::Lockfile;
+use crate::manifest::DependencyManifest;
+use crate::project::{find_project_paths, ProjectPaths};
+use pm_lib::package::PackageName;
+
+pub const USAGE: &str = "Show the dependency tree recorded in the lockfile.
+
+Usage:
+    pm tree [options]
+
+Options:
+    -i, --invert=<package>  Show the packages depending on <package> instead.
+    -d, --depth=<n>         Only show dependencies up to <n> levels deep.
+    --format=<format>       Print the tree as text, dot or json [default: text].
+    -h, --help              Display this message.
+
+Packages whose dependencies are listed elsewhere in the tree are marked
+with (*). Without a manifest, the tree starts from the locked packages that
+nothing else depends on.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_invert: Option<String>,
+    flag_depth: Option<usize>,
+    flag_format: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let project_paths = match find_project_paths() {
+        Ok(project_paths) => project_paths,
+        // Without a manifest, look for a lockfile in the current directory.
+        Err(_) => {
+            let root = env::current_dir()?;
+            ProjectPaths {
+                manifest: root.join("deps"),
+                lockfile: root.join("deps.lock"),
+                root,
+            }
+        }
+    };
+    let lockfile = Lockfile::from_file(&project_paths)?
+        .ok_or_else(|| format_err!("No lockfile found; run `pm install` to create one"))?;
+    let roots = if project_paths.manifest.exists() {
+        DependencyManifest::from_file(&project_paths)?.dependencies
+    } else {
+        lockfile.root_dependencies()
+    };
+    let graph = DependencyGraph::from_lockfile(&lockfile, &roots)?;
+
+    let trees = match args.flag_invert {
+        None => graph.tree(&Node::Project, args.flag_depth).children,
+        Some(ref package) => {
+            let package_name = PackageName::from_str(package)
+                .ok_or_else(|| format_err!("Invalid package name: {}", package))?;
+            let inverted_graph = graph.inverted();
+            let root = inverted_graph
+                .find(&package_name)
+                .ok_or_else(|| format_err!("{} is not in the lockfile", package_name))?;
+            vec![inverted_graph.tree(&root, args.flag_depth)]
+        }
+    };
+
+    match args.flag_format.as_str() {
+        "text" => print!("{}", render_text(&trees)),
+        "dot" => print!("{}", render_dot(&trees)),
+        "json" => println!("{}", render_json(&trees)),
+        format => bail!(
+            "Invalid format {:?}; expected \"text\", \"dot\" or \"json\"",
+            format
+        ),
+    }
+    Ok(())
+}
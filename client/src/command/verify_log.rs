@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::PathBuf;
+
+use pm_lib::package::PackageName;
+use pm_lib::transparency_log::{
+    digest, hash_from_hex, leaf_hash, verify_consistency, verify_inclusion, ConsistencyProof, Hash,
+    InclusionProof, LogEntry, LogEvent, LoggedEntry, TreeHead,
+};
+use pm_lib::version::Version;
+
+use crate::lockfile::Lockfile;
+use crate::path::config_path;
+use crate::project::find_project_paths;
+use crate::registry::get;
+use crate::resolve::fetch_tar_br;
+
+pub const USAGE: &str = "Audit the locked releases against the registry's transparency log.
+
+Usage:
+    pm verify-log [options]
+
+Options:
+    -h, --help     Display this message.
+
+Checks that the log is consistent with the last version of it seen by this
+machine, that every release in the lockfile was published in the log, and
+that the archives the registry serves have the digests recorded there.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {}
+
+fn parse_hash(hex: &str) -> Result<Hash, failure::Error> {
+    hash_from_hex(hex).ok_or_else(|| format_err!("The registry sent an invalid hash: {:?}", hex))
+}
+
+fn parse_hashes(hexes: &[String]) -> Result<Vec<Hash>, failure::Error> {
+    hexes.iter().map(|hex| parse_hash(hex)).collect()
+}
+
+fn log_head_path() -> Result<PathBuf, failure::Error> {
+    let mut path = config_path()?;
+    path.push("log_head.json");
+    Ok(path)
+}
+
+/// Check the new tree head against the last one we saw, and remember it.
+fn update_log_head(head: &TreeHead) -> Result<(), failure::Error> {
+    let path = log_head_path()?;
+    if path.exists() {
+        let old_head: TreeHead = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        if old_head.tree_size > head.tree_size {
+            bail!(
+                "The log has shrunk from {} to {} entries since it was last checked",
+                old_head.tree_size,
+                head.tree_size
+            );
+        }
+        let proof: ConsistencyProof = get(
+            "log/proof/consistency",
+            ordmap![
+                "first".to_string() => old_head.tree_size.to_string(),
+                "second".to_string() => head.tree_size.to_string()
+            ],
+        )??;
+        if !verify_consistency(
+            old_head.tree_size,
+            head.tree_size,
+            &parse_hash(&old_head.root_hash)?,
+            &parse_hash(&head.root_hash)?,
+            &parse_hashes(&proof.proof)?,
+        ) {
+            bail!(
+                "The log is not consistent with the one seen before: entries have been changed \
+                 or removed"
+            );
+        }
+    }
+    fs::create_dir_all(config_path()?)?;
+    fs::write(&path, serde_json::to_string(head)?)?;
+    Ok(())
+}
+
+/// Pick the publication of a release out of the log entries the registry
+/// returned for it, leaving out those past the tree head. Returns the entry
+/// and the archive digest it records. Entries about other releases are
+/// errors: a registry could otherwise vouch for an archive with another
+/// release's publication.
+fn find_publication(
+    logged_entries: Vec<LoggedEntry>,
+    tree_size: u64,
+    name: &PackageName,
+    version: &Version,
+) -> Result<(LoggedEntry, String), failure::Error> {
+    let mut publication: Option<(LoggedEntry, String)> = None;
+    for logged_entry in logged_entries {
+        if logged_entry.index >= tree_size {
+            continue;
+        }
+        let entry: LogEntry = serde_json::from_str(&logged_entry.entry)?;
+        if let LogEvent::Publish {
+            package,
+            version: published,
+            digest,
+        } = entry.event
+        {
+            if package != *name || published != *version {
+                bail!(
+                    "the log returned the publication of {} {} instead",
+                    package,
+                    published
+                );
+            }
+            match publication {
+                Some((_, ref first)) if *first != digest => bail!(
+                    "the log records several publications, with digests {} and {}",
+                    first,
+                    digest
+                ),
+                Some(_) => {}
+                None => publication = Some((logged_entry, digest)),
+            }
+        }
+    }
+    publication.ok_or_else(|| format_err!("not published in the log"))
+}
+
+/// Whether the log entries the registry returned for a release, up to the
+/// tree head, include its yanking.
+fn is_yanked(
+    logged_entries: &[LoggedEntry],
+    tree_size: u64,
+    name: &PackageName,
+    version: &Version,
+) -> Result<bool, failure::Error> {
+    for logged_entry in logged_entries {
+        if logged_entry.index >= tree_size {
+            continue;
+        }
+        let entry: LogEntry = serde_json::from_str(&logged_entry.entry)?;
+        if let LogEvent::Yank {
+            package,
+            version: yanked,
+        } = entry.event
+        {
+            if package == *name && yanked == *version {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Find the publication of a release in the log, check that it's included in
+/// the tree and that the archive matches it. Returns whether the release has
+/// been yanked.
+fn verify_release(
+    head: &TreeHead,
+    root_hash: &Hash,
+    name: &PackageName,
+    version: &Version,
+) -> Result<bool, failure::Error> {
+    let logged_entries: Vec<LoggedEntry> = get(
+        &format!("log/releases/{}/{}/{}", name.namespace, name.name, version),
+        ordmap![],
+    )??;
+    let yanked = is_yanked(&logged_entries, head.tree_size, name, version)?;
+    let (logged_entry, expected_digest) =
+        find_publication(logged_entries, head.tree_size, name, version)?;
+
+    let proof: InclusionProof = get(
+        "log/proof/inclusion",
+        ordmap![
+            "leaf_index".to_string() => logged_entry.index.to_string(),
+            "tree_size".to_string() => head.tree_size.to_string()
+        ],
+    )??;
+    if !verify_inclusion(
+        &leaf_hash(logged_entry.entry.as_bytes()),
+        logged_entry.index,
+        head.tree_size,
+        &parse_hashes(&proof.audit_path)?,
+        root_hash,
+    ) {
+        bail!("the log entry is not included in the tree");
+    }
+
+    let actual_digest = digest(&fetch_tar_br(name, version)?);
+    if actual_digest != expected_digest {
+        bail!(
+            "the archive has digest {}, but the log says {}",
+            actual_digest,
+            expected_digest
+        );
+    }
+    Ok(yanked)
+}
+
+pub fn execute(_args: Args) -> Result<(), failure::Error> {
+    let project_paths = find_project_paths()?;
+    let lockfile = Lockfile::from_file(&project_paths)?
+        .ok_or_else(|| format_err!("No lockfile found; run `pm install` to create one"))?;
+
+    let head: TreeHead = get("log/head", ordmap![])??;
+    let root_hash = parse_hash(&head.root_hash)?;
+    update_log_head(&head)?;
+
+    let mut failures = 0;
+    for locked_dependency in &lockfile.locked_dependencies {
+        let name = &locked_dependency.package_name;
+        let version = &locked_dependency.version;
+        match verify_release(&head, &root_hash, name, version) {
+            Ok(false) => {}
+            Ok(true) => println!("Warning: {} {} has been yanked", name, version),
+            Err(error) => {
+                println!("{} {}: {}", name, version, error);
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{} release(s) failed verification", failures);
+    }
+    println!(
+        "Verified {} release(s) against a log of {} entries with root {}",
+        lockfile.locked_dependencies.len(),
+        head.tree_size,
+        head.root_hash
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::{pkg, ver};
+
+    fn logged(index: u64, event: LogEvent) -> LoggedEntry {
+        let entry = LogEntry {
+            event,
+            actor: "test:alice".to_string(),
+            timestamp: 0,
+        };
+        LoggedEntry {
+            index,
+            entry: serde_json::to_string(&entry).unwrap(),
+        }
+    }
+
+    fn publish(index: u64, package: &str, version: &str, digest: &str) -> LoggedEntry {
+        logged(
+            index,
+            LogEvent::Publish {
+                package: pkg(package),
+                version: ver(version),
+                digest: digest.to_string(),
+            },
+        )
+    }
+
+    fn yank(index: u64, package: &str, version: &str) -> LoggedEntry {
+        logged(
+            index,
+            LogEvent::Yank {
+                package: pkg(package),
+                version: ver(version),
+            },
+        )
+    }
+
+    #[test]
+    fn find_the_publication_of_a_release() {
+        let find = |entries: Vec<LoggedEntry>| {
+            find_publication(entries, 10, &pkg("test/left-pad"), &ver("1.0.0"))
+                .map(|(entry, digest)| (entry.index, digest))
+                .map_err(|error| error.to_string())
+        };
+        assert_eq!(
+            find(vec![publish(3, "test/left-pad", "1.0.0", "abc")]),
+            Ok((3, "abc".to_string()))
+        );
+        // Entries past the tree head don't count yet.
+        assert_eq!(
+            find(vec![publish(10, "test/left-pad", "1.0.0", "abc")]),
+            Err("not published in the log".to_string())
+        );
+        assert_eq!(
+            find(vec![publish(3, "test/right-pad", "1.0.0", "abc")]),
+            Err("the log returned the publication of test/right-pad 1.0.0 instead".to_string())
+        );
+        assert_eq!(
+            find(vec![publish(3, "test/left-pad", "1.1.0", "abc")]),
+            Err("the log returned the publication of test/left-pad 1.1.0 instead".to_string())
+        );
+        assert_eq!(
+            find(vec![
+                publish(3, "test/left-pad", "1.0.0", "abc"),
+                publish(5, "test/left-pad", "1.0.0", "abc")
+            ]),
+            Ok((3, "abc".to_string()))
+        );
+        assert_eq!(
+            find(vec![
+                publish(3, "test/left-pad", "1.0.0", "abc"),
+                publish(5, "test/left-pad", "1.0.0", "def")
+            ]),
+            Err("the log records several publications, with digests abc and def".to_string())
+        );
+    }
+
+    #[test]
+    fn find_yanked_releases() {
+        let yanked = |entries: Vec<LoggedEntry>| {
+            is_yanked(&entries, 10, &pkg("test/left-pad"), &ver("1.0.0")).unwrap()
+        };
+        assert!(!yanked(vec![publish(3, "test/left-pad", "1.0.0", "abc")]));
+        assert!(yanked(vec![
+            publish(3, "test/left-pad", "1.0.0", "abc"),
+            yank(5, "test/left-pad", "1.0.0")
+        ]));
+        // Entries past the tree head don't count yet.
+        assert!(!yanked(vec![yank(10, "test/left-pad", "1.0.0")]));
+        assert!(!yanked(vec![yank(5, "test/left-pad", "1.1.0")]));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::lockfile::Lockfile;
+use crate::manifest::DependencyManifest;
+use crate::project::find_project_paths;
+use pm_lib::index;
+use pm_lib::package::PackageName;
+use pm_lib::solver::{dependency_chains, solve_justified, ChainLink, ResolutionStrategy};
+
+pub const USAGE: &str = "Explain why a package is installed.
+
+Usage:
+    pm why [options] <package>
+
+Options:
+    -h, --help     Display this message.
+
+Prints the chains of dependencies in the lockfile leading to <package>, with
+the version constraint on each step. Run `pm install` first if the lockfile is
+out of date.
+";
+
+/// Stop listing chains after this many; there can be exponentially many.
+const MAX_CHAINS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package_name = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name: {}", args.arg_package))?;
+    let project_paths = find_project_paths()?;
+    let manifest = DependencyManifest::from_file(&project_paths)?;
+    let dependencies = index::dependencies_from_slice(&manifest.dependencies);
+
+    let lockfile = Lockfile::from_file(&project_paths)?
+        .ok_or_else(|| format_err!("No lockfile found; run `pm install` to create one"))?;
+    let solution = lockfile
+        .to_solution_if_up_to_date(&manifest.dependencies, &manifest.prereleases)?
+        .ok_or_else(|| format_err!("The lockfile is out of date; run `pm install` to update it"))?;
+    let version = solution
+        .get(&package_name)
+        .ok_or_else(|| format_err!("{} is not a dependency of this project", package_name))?;
+
+    // The locked releases are the only ones in this index, so the solver picks
+    // them again, and tells us which chain it picked the version for.
+    let locked_index = lockfile.to_index();
+    let justified_solution = solve_justified(
+        &locked_index,
+        &dependencies,
+        ResolutionStrategy::Highest,
+        manifest.prereleases,
+    )?;
+    let solver_chain: Vec<&PackageName> = justified_solution[&package_name]
+        .path
+        .iter()
+        .map(|(name, _)| &**name)
+        .chain(Some(&package_name))
+        .collect();
+
+    let mut chains = dependency_chains(
+        &locked_index,
+        &dependencies,
+        &solution,
+        &package_name,
+        MAX_CHAINS + 1,
+    );
+    let truncated = chains.len() > MAX_CHAINS;
+    chains.truncate(MAX_CHAINS);
+    let is_solver_chain = |chain: &Vec<ChainLink>| {
+        chain
+            .iter()
+            .map(|link| &link.package)
+            .eq(solver_chain.iter().cloned())
+    };
+    // List the chain that the solver picked the version for first.
+    let has_solver_chain = match chains.iter().position(is_solver_chain) {
+        Some(position) => {
+            let solver_chain = chains.remove(position);
+            chains.insert(0, solver_chain);
+            true
+        }
+        None => false,
+    };
+
+    let total = if truncated {
+        format!("at least {}", chains.len() + 1)
+    } else {
+        chains.len().to_string()
+    };
+    for (i, chain) in chains.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if i == 0 && has_solver_chain {
+            println!(
+                "Chain 1 of {} (used to pick {} {}):",
+                total, package_name, version
+            );
+        } else {
+            println!("Chain {} of {}:", i + 1, total);
+        }
+        print_chain(chain);
+    }
+    if truncated {
+        println!("\nOnly the first {} chains are shown.", MAX_CHAINS);
+    }
+    Ok(())
+}
+
+fn print_chain(chain: &[ChainLink]) {
+    let mut dependent = "(project)".to_string();
+    for link in chain {
+        println!(
+            "  {} requires {} {} -> {}",
+            dependent, link.package, link.constraint, link.version
+        );
+        dependent = format!("{} {}", link.package, link.version);
+    }
+}
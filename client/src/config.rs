@@ -1,19 +1,32 @@
 use failure;
-use std::fs::{create_dir_all, File};
+use std::fs::{self, create_dir_all, File};
 use std::io::{Read, Write};
+use std::path::PathBuf;
 use toml;
 
 use crate::path::config_path;
+use crate::trust::TrustPolicy;
+use pm_lib::archive::ArchiveLimits;
+use pm_lib::signing::SigningKey;
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub auth: Auth,
+    /// Limits for archives downloaded from the registry, set in an
+    /// `[archive]` section.
+    #[serde(default)]
+    pub archive: ArchiveLimits,
+    /// Which releases must be signed, and by whom; see `trust.rs`.
+    #[serde(default)]
+    pub trust: TrustPolicy,
 }
 
 impl Config {
     fn new() -> Config {
         Config {
             auth: Auth { token: None },
+            archive: ArchiveLimits::default(),
+            trust: TrustPolicy::default(),
         }
     }
 }
@@ -50,3 +63,36 @@ pub fn write_config(config: &Config) -> Result<(), failure::Error> {
     file.write_all(data.as_bytes())?;
     Ok(())
 }
+
+fn signing_key_path() -> Result<PathBuf, failure::Error> {
+    let mut path = config_path()?;
+    path.push("signing_key");
+    Ok(path)
+}
+
+/// Read the key used to sign releases, if one has been generated.
+pub fn read_signing_key() -> Result<Option<SigningKey>, failure::Error> {
+    let path = signing_key_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let secret_key = fs::read_to_string(&path)?;
+    let key = SigningKey::from_base64(&secret_key)
+        .map_err(|error| format_err!("{}: {}", path.display(), error))?;
+    Ok(Some(key))
+}
+
+pub fn write_signing_key(key: &SigningKey) -> Result<PathBuf, failure::Error> {
+    create_dir_all(config_path()?)?;
+    let path = signing_key_path()?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    writeln!(file, "{}", key.to_base64())?;
+    Ok(path)
+}
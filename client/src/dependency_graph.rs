@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::lockfile::Lockfile;
+use pm_lib::dependencies::Dependency;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Node {
+    /// The project whose manifest we read the direct dependencies from.
+    Project,
+    Package(PackageName, Version),
+}
+
+impl Node {
+    pub fn label(&self) -> String {
+        match self {
+            Node::Project => "(project)".to_string(),
+            Node::Package(package_name, version) => format!("{} {}", package_name, version),
+        }
+    }
+}
+
+/// The dependency graph recorded in a lockfile. Edges point from dependent
+/// to dependency, unless the graph has been inverted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyGraph {
+    edges: BTreeMap<Node, Vec<Node>>,
+}
+
+impl DependencyGraph {
+    /// Build the graph from the lockfile, with `dependencies` (usually read
+    /// from the manifest) as the edges going out from `Node::Project`.
+    pub fn from_lockfile(
+        lockfile: &Lockfile,
+        dependencies: &[Dependency],
+    ) -> Result<Self, failure::Error> {
+        let solution = lockfile.to_solution()?;
+        let node_for = |dependency: &Dependency| match solution.get(&dependency.package_name) {
+            None => Err(format_err!(
+                "lockfile is missing {}; run `pm install` to update it",
+                dependency.package_name
+            )),
+            Some(version) => Ok(Node::Package(
+                dependency.package_name.clone(),
+                version.clone(),
+            )),
+        };
+        let mut edges = BTreeMap::new();
+        edges.insert(
+            Node::Project,
+            dependencies.iter().map(node_for).collect::<Result<_, _>>()?,
+        );
+        for locked_dependency in &lockfile.locked_dependencies {
+            edges.insert(
+                Node::Package(
+                    locked_dependency.package_name.clone(),
+                    locked_dependency.version.clone(),
+                ),
+                locked_dependency
+                    .dependencies
+                    .iter()
+                    .map(node_for)
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+        Ok(DependencyGraph { edges })
+    }
+
+    /// Return the graph with all edges pointing from dependency to dependent.
+    pub fn inverted(&self) -> Self {
+        let mut edges: BTreeMap<Node, Vec<Node>> =
+            self.edges.keys().map(|node| (node.clone(), vec![])).collect();
+        for (from, tos) in &self.edges {
+            for to in tos {
+                edges.entry(to.clone()).or_default().push(from.clone());
+            }
+        }
+        DependencyGraph { edges }
+    }
+
+    pub fn find(&self, package_name: &PackageName) -> Option<Node> {
+        self.edges
+            .keys()
+            .find(|node| match node {
+                Node::Package(name, _) => name == package_name,
+                Node::Project => false,
+            })
+            .cloned()
+    }
+
+    /// Unfold the graph into a tree starting at `root`, going at most
+    /// `max_depth` edges deep. Nodes whose children have already been listed
+    /// elsewhere in the tree are marked as deduplicated and not expanded
+    /// again, which also takes care of cycles.
+    pub fn tree(&self, root: &Node, max_depth: Option<usize>) -> Tree {
+        let mut expanded = BTreeSet::new();
+        self.subtree(root, 0, max_depth, &mut expanded)
+    }
+
+    fn subtree(
+        &self,
+        node: &Node,
+        depth: usize,
+        max_depth: Option<usize>,
+        expanded: &mut BTreeSet<Node>,
+    ) -> Tree {
+        let children = self.edges.get(node).map_or(&[][..], |c| &c[..]);
+        let mut tree = Tree {
+            node: node.clone(),
+            children: vec![],
+            deduplicated: false,
+        };
+        if children.is_empty() || matches!(max_depth, Some(max) if depth >= max) {
+            return tree;
+        }
+        if !expanded.insert(node.clone()) {
+            tree.deduplicated = true;
+            return tree;
+        }
+        tree.children = children
+            .iter()
+            .map(|child| self.subtree(child, depth + 1, max_depth, expanded))
+            .collect();
+        tree
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    pub node: Node,
+    pub children: Vec<Tree>,
+    /// True if the node has children, but they are listed elsewhere.
+    pub deduplicated: bool,
+}
+
+#[derive(Serialize)]
+struct JsonTree<'a> {
+    /// `None` for the project.
+    name: Option<&'a PackageName>,
+    version: Option<&'a Version>,
+    #[serde(skip_serializing_if = "is_false")]
+    deduplicated: bool,
+    children: Vec<JsonTree<'a>>,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl<'a> From<&'a Tree> for JsonTree<'a> {
+    fn from(tree: &'a Tree) -> Self {
+        let (name, version) = match tree.node {
+            Node::Project => (None, None),
+            Node::Package(ref name, ref version) => (Some(name), Some(version)),
+        };
+        JsonTree {
+            name,
+            version,
+            deduplicated: tree.deduplicated,
+            children: tree.children.iter().map(JsonTree::from).collect(),
+        }
+    }
+}
+
+/// Render the trees as text, like
+///
+/// ```text
+/// test/a 1.0.0
+/// ├── test/b 2.0.0
+/// │   └── test/c 1.0.0
+/// └── test/c 1.0.0 (*)
+/// ```
+pub fn render_text(trees: &[Tree]) -> String {
+    fn render_children(out: &mut String, tree: &Tree, prefix: &str) {
+        for (i, child) in tree.children.iter().enumerate() {
+            let (branch, indent) = if i == tree.children.len() - 1 {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            render_line(out, child, &format!("{}{}", prefix, branch));
+            render_children(out, child, &format!("{}{}", prefix, indent));
+        }
+    }
+    fn render_line(out: &mut String, tree: &Tree, prefix: &str) {
+        let marker = if tree.deduplicated { " (*)" } else { "" };
+        writeln!(out, "{}{}{}", prefix, tree.node.label(), marker).expect("writing to string");
+    }
+
+    let mut out = String::new();
+    for tree in trees {
+        render_line(&mut out, tree, "");
+        render_children(&mut out, tree, "");
+    }
+    out
+}
+
+/// Render the edges contained in the trees in Graphviz format.
+pub fn render_dot(trees: &[Tree]) -> String {
+    fn collect_edges(tree: &Tree, edges: &mut BTreeSet<(String, String)>) {
+        for child in &tree.children {
+            edges.insert((tree.node.label(), child.node.label()));
+            collect_edges(child, edges);
+        }
+    }
+
+    let mut edges = BTreeSet::new();
+    let mut out = String::from("digraph dependencies {\n");
+    for tree in trees {
+        writeln!(out, "    {:?};", tree.node.label()).expect("writing to string");
+        collect_edges(tree, &mut edges);
+    }
+    for (from, to) in edges {
+        writeln!(out, "    {:?} -> {:?};", from, to).expect("writing to string");
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub fn render_json(trees: &[Tree]) -> String {
+    let json_trees: Vec<JsonTree> = trees.iter().map(JsonTree::from).collect();
+    ::serde_json::to_string_pretty(&json_trees).expect("serialization cannot fail")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lockfile::{LockedDependency, LockfileMeta};
+    use pm_lib::test_helpers::*;
+
+    fn dep(name: &str, constraint: &str) -> Dependency {
+        Dependency {
+            package_name: pkg(name),
+            version_constraint: range(constraint),
+        }
+    }
+
+    fn locked(name: &str, version: &str, dependencies: Vec<Dependency>) -> LockedDependency {
+        LockedDependency {
+            package_name: pkg(name),
+            version: ver(version),
+            dependencies,
+        }
+    }
+
+    fn sample_graph() -> DependencyGraph {
+        let lockfile = Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: vec![
+                locked("a", "1.0.0", vec![dep("b", "^2"), dep("c", "^1")]),
+                locked("b", "2.0.0", vec![dep("c", "^1")]),
+                locked("c", "1.0.0", vec![dep("d", "^1")]),
+                locked("d", "1.0.0", vec![]),
+            ],
+        };
+        DependencyGraph::from_lockfile(&lockfile, &[dep("a", "^1"), dep("d", "^1")]).unwrap()
+    }
+
+    #[test]
+    fn render_tree() {
+        let graph = sample_graph();
+        let tree = graph.tree(&Node::Project, None);
+        assert_eq!(
+            render_text(&tree.children),
+            "test/a 1.0.0
+├── test/b 2.0.0
+│   └── test/c 1.0.0
+│       └── test/d 1.0.0
+└── test/c 1.0.0 (*)
+test/d 1.0.0
+"
+        );
+
+        let tree = graph.tree(&Node::Project, Some(2));
+        assert_eq!(
+            render_text(&tree.children),
+            "test/a 1.0.0
+├── test/b 2.0.0
+└── test/c 1.0.0
+test/d 1.0.0
+"
+        );
+    }
+
+    #[test]
+    fn render_inverted_tree() {
+        let graph = sample_graph().inverted();
+        let root = graph.find(&pkg("c")).unwrap();
+        assert_eq!(
+            render_text(&[graph.tree(&root, None)]),
+            "test/c 1.0.0
+├── test/a 1.0.0
+│   └── (project)
+└── test/b 2.0.0
+    └── test/a 1.0.0 (*)
+"
+        );
+        assert_eq!(graph.find(&pkg("x")), None);
+    }
+
+    #[test]
+    fn render_tree_dot() {
+        let graph = sample_graph().inverted();
+        let root = graph.find(&pkg("b")).unwrap();
+        assert_eq!(
+            render_dot(&[graph.tree(&root, None)]),
+            "digraph dependencies {
+    \"test/b 2.0.0\";
+    \"test/a 1.0.0\" -> \"(project)\";
+    \"test/b 2.0.0\" -> \"test/a 1.0.0\";
+}
+"
+        );
+    }
+
+    #[test]
+    fn missing_locked_dependency() {
+        let lockfile = Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: vec![locked("a", "1.0.0", vec![dep("b", "^2")])],
+        };
+        assert!(DependencyGraph::from_lockfile(&lockfile, &[dep("a", "^1")]).is_err());
+    }
+}
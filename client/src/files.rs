@@ -18,6 +18,22 @@ use std::path::PathBuf;
 // behavior due to ordering.
 type FileSet = HashSet<String>;
 
+/// Where `pm pack` writes archives unless told otherwise, relative to the
+/// project root.
+pub const PACK_DIR: &str = "target";
+
+/// Whether `file` is an archive that `pm pack` wrote to `PACK_DIR`. We never
+/// add those to a package, or each archive would contain the one before it.
+fn is_pack_output(file: &str) -> bool {
+    let mut components = file.splitn(2, '/');
+    match (components.next(), components.next()) {
+        (Some(dir), Some(name)) => {
+            dir == PACK_DIR && !name.contains('/') && name.ends_with(".tar.br")
+        }
+        _ => false,
+    }
+}
+
 pub struct FilesSectionInterpreter {
     pub root: PathBuf,
     pub vcs_file_set: VCSFileSet,
@@ -81,7 +97,7 @@ impl FilesSectionInterpreter {
         let mut changed = Vec::<String>::new();
         let mut untracked = Vec::<String>::new();
         for (file, status) in &self.vcs_file_set {
-            if self.pattern_matches_path_or_ancestor(&cglob, file) {
+            if self.pattern_matches_path_or_ancestor(&cglob, file) && !is_pack_output(file) {
                 match status {
                     VCSFileStatus::Tracked => {
                         file_set.insert(file.clone());
@@ -127,7 +143,7 @@ impl FilesSectionInterpreter {
         let cglob = CompiledGlob::new(glob)?;
         let mut did_match = false;
         for file in self.vcs_file_set.keys() {
-            if self.pattern_matches_path_or_ancestor(&cglob, file) {
+            if self.pattern_matches_path_or_ancestor(&cglob, file) && !is_pack_output(file) {
                 did_match = true;
                 // We add files regardless of their VCS status here. One minor
                 // deficiency is that files that have been deleted but whose
@@ -389,5 +405,21 @@ mod test {
             fsi.remove(&mut file_set, "src/").unwrap();
             assert_file_set(&file_set, &[]);
         }
+
+        #[test]
+        fn leave_out_packed_archives() {
+            let mut fsi = make_fsi(&[
+                "src/a.rs",
+                "target/test-a-1.0.0.tar.br",
+                "target/notes.txt",
+                "target/old/test-a-0.1.0.tar.br",
+            ]);
+            let mut file_set = FileSet::new();
+            fsi.add_any(&mut file_set, "**").unwrap();
+            assert_file_set(
+                &file_set,
+                &["src/a.rs", "target/notes.txt", "target/old/test-a-0.1.0.tar.br"],
+            );
+        }
     }
 }
@@ -9,10 +9,11 @@ use failure;
 use serde::de::{self, Deserialize, Deserializer, IgnoredAny, SeqAccess, Visitor};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
 
+use pm_lib::constraint::VersionConstraint;
 use pm_lib::dependencies::Dependency;
-use pm_lib::index::{dependencies_to_vec, Index};
+use pm_lib::index::{dependencies_from_slice, dependencies_to_vec, Index};
 use pm_lib::package::PackageName;
-use pm_lib::solver::Solution;
+use pm_lib::solver::{PrereleasePolicy, Solution};
 use pm_lib::version::Version;
 use crate::project::ProjectPaths;
 
@@ -43,11 +44,66 @@ impl Lockfile {
         Ok(solution)
     }
 
+    /// An index of just the locked releases and their dependencies.
+    pub fn to_index(&self) -> Index {
+        let mut index = Index::new();
+        for dep in &self.locked_dependencies {
+            index
+                .entry(dep.package_name.clone())
+                .or_default()
+                .insert(dep.version.clone(), dependencies_from_slice(&dep.dependencies));
+        }
+        index
+    }
+
+    /// The locked packages that no other locked package depends on, which
+    /// must be what the project depends on. Packages only reachable through a
+    /// dependency cycle are included too, so that every locked package is
+    /// reachable from the result.
+    pub fn root_dependencies(&self) -> Vec<Dependency> {
+        let depended_on: BTreeSet<&PackageName> = self
+            .locked_dependencies
+            .iter()
+            .flat_map(|locked| locked.dependencies.iter().map(|dep| &dep.package_name))
+            .collect();
+        let sub_dependencies: BTreeMap<&PackageName, &[Dependency]> = self
+            .locked_dependencies
+            .iter()
+            .map(|locked| (&locked.package_name, locked.dependencies.as_slice()))
+            .collect();
+        let mut roots = vec![];
+        let mut reached = BTreeSet::new();
+        let candidates = self
+            .locked_dependencies
+            .iter()
+            .filter(|locked| !depended_on.contains(&locked.package_name))
+            .chain(self.locked_dependencies.iter());
+        for locked in candidates {
+            if reached.contains(&locked.package_name) {
+                continue;
+            }
+            roots.push(Dependency {
+                package_name: locked.package_name.clone(),
+                version_constraint: VersionConstraint::Exact(locked.version.clone()),
+            });
+            let mut to_visit = vec![&locked.package_name];
+            while let Some(package_name) = to_visit.pop() {
+                if reached.insert(package_name) {
+                    for dep in sub_dependencies.get(package_name).copied().unwrap_or_default() {
+                        to_visit.push(&dep.package_name);
+                    }
+                }
+            }
+        }
+        roots
+    }
+
     // Return the solution only if the lockfile is consistent with the
     // dependencies provided.
     pub fn to_solution_if_up_to_date(
         &self,
         dependencies: &[Dependency],
+        prereleases: &PrereleasePolicy,
     ) -> Result<Option<Solution>, failure::Error> {
         let solution = self.to_solution()?;
         let mut sub_dependencies: BTreeMap<&PackageName, Vec<&Dependency>> = BTreeMap::new();
@@ -71,7 +127,9 @@ impl Lockfile {
                     return None;
                 }
                 Some(version) => {
-                    if !dep.version_constraint.contains(&version) {
+                    if !dep.version_constraint.contains(&version)
+                        || !prereleases.allows(&dep.package_name, &dep.version_constraint, &version)
+                    {
                         // Package is in solution but has wrong version.
                         return None;
                     }
@@ -382,6 +440,7 @@ impl FromStr for Lockfile {
 mod test {
     use super::*;
     use pm_lib::test_helpers::*;
+    use pm_lib::{deps, gen_registry};
 
     #[test]
     fn roundtrip() {
@@ -396,4 +455,69 @@ mod test {
         let serialized = lockfile.to_string();
         assert_eq!(Lockfile::from_str(&serialized).unwrap(), lockfile);
     }
+
+    #[test]
+    fn index_of_locked_releases() {
+        let lockfile = Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: vec![
+                LockedDependency {
+                    package_name: pkg("x"),
+                    version: ver("1.0.0"),
+                    dependencies: dependencies_to_vec(&deps!(y => "^2")),
+                },
+                LockedDependency {
+                    package_name: pkg("y"),
+                    version: ver("2.1.0"),
+                    dependencies: vec![],
+                },
+            ],
+        };
+        assert_eq!(
+            lockfile.to_index(),
+            gen_registry!(
+                x => ( "1.0.0" => deps!(y => "^2") ),
+                y => ( "2.1.0" => deps!() )
+            )
+        );
+    }
+
+    #[test]
+    fn root_dependencies() {
+        let locked = |name: &str, dependencies: Vec<&str>| LockedDependency {
+            package_name: pkg(name),
+            version: ver("1.0.0"),
+            dependencies: dependencies
+                .into_iter()
+                .map(|dependency| Dependency {
+                    package_name: pkg(dependency),
+                    version_constraint: range("^1"),
+                })
+                .collect(),
+        };
+        let lockfile = Lockfile {
+            meta: LockfileMeta::default(),
+            locked_dependencies: vec![
+                locked("a", vec!["b"]),
+                locked("b", vec!["c"]),
+                locked("c", vec![]),
+                locked("d", vec!["c"]),
+                // Nothing outside the cycle depends on e and f.
+                locked("e", vec!["f"]),
+                locked("f", vec!["e"]),
+            ],
+        };
+        assert_eq!(
+            lockfile
+                .root_dependencies()
+                .into_iter()
+                .map(|dep| (dep.package_name, dep.version_constraint))
+                .collect::<Vec<_>>(),
+            vec![
+                (pkg("a"), range("=1.0.0")),
+                (pkg("d"), range("=1.0.0")),
+                (pkg("e"), range("=1.0.0")),
+            ]
+        );
+    }
 }
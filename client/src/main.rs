@@ -17,26 +17,29 @@ extern crate pest_derive;
 #[macro_use]
 extern crate matches;
 
+mod archive;
 mod config;
+mod dependency_graph;
 mod files;
 mod git;
 mod io;
 mod lockfile;
 mod manifest;
+mod manifest_editor;
+mod manifest_formatter;
 mod manifest_parser;
 mod manifest_parser_error;
 mod path;
 mod project;
 mod registry;
 mod resolve;
+mod trust;
 
 use docopt::Docopt;
 use serde::de::Deserialize;
 use std::env;
 use std::process;
 
-static REGISTRY_URL: &str = "http://localhost:8000";
-
 const USAGE: &str = "Your package manager.
 
 Usage:
@@ -44,10 +47,23 @@ Usage:
     pm [options]
 
 Subcommands:
+    add
+    check
+    dependents
+    fmt
+    info
+    init
     install
+    key
+    outdated
+    pack
     search
     login
     publish
+    remove
+    tree
+    verify-log
+    why
 
 Options:
     -h, --help     Display this message.
@@ -64,10 +80,23 @@ type Result = std::result::Result<(), failure::Error>;
 
 macro_rules! each_subcommand {
     ($mac:ident) => {
+        $mac!(add);
+        $mac!(check);
+        $mac!(dependents);
+        $mac!(fmt);
+        $mac!(info);
+        $mac!(init);
         $mac!(install);
+        $mac!(key);
+        $mac!(outdated);
+        $mac!(pack);
         $mac!(login);
         $mac!(search);
         $mac!(publish);
+        $mac!(remove);
+        $mac!(tree);
+        $mac!(verify_log);
+        $mac!(why);
     };
 }
 
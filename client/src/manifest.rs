@@ -1,20 +1,24 @@
 #![allow(dead_code)]
 
 use std::collections::HashSet;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Component, Path};
 
 use crate::files::FilesSectionInterpreter;
 use crate::manifest_parser::{
-    check_block_fields, get_field, get_fields, get_optional_block_field, get_optional_field,
-    get_optional_list_field, get_optional_string_field, get_string, parse_manifest, Arguments,
-    Pair, Rule,
+    check_block_fields, get_field, get_fields, get_flag_option, get_optional_block_field,
+    get_optional_field, get_optional_list_field, get_optional_string_field, get_string,
+    parse_manifest, Arguments, Pair, Rule,
+};
+use crate::manifest_parser_error::{
+    Diagnostics, ManifestParserError, PestErrorExt, PestResultExt,
 };
-use crate::manifest_parser_error::{PestErrorExt, PestResultExt};
 use pm_lib::constraint::VersionConstraint;
 use pm_lib::dependencies::Dependency;
 use pm_lib::package::PackageName;
+use pm_lib::publication_request::{NamedTextFile, Repository};
+use pm_lib::solver::PrereleasePolicy;
 use pm_lib::version::Version;
 use crate::project::ProjectPaths;
 
@@ -28,19 +32,23 @@ pub struct Manifest {
     pub version: Version,
 
     pub dependencies: Vec<Dependency>,
+    pub prereleases: PrereleasePolicy,
 
     pub authors: Vec<String>,
     pub description: String,
     pub homepage: Option<String>,
-    pub repository: Option<String>,
+    pub repository: Option<Repository>,
     pub bugs: Option<String>,
     pub keywords: Vec<String>,
 
     pub license: Option<String>,
-    pub license_file: Option<String>,
+    pub license_file: Option<NamedTextFile>,
 
-    pub readme: Option<(String, String)>,
+    pub readme: Option<NamedTextFile>,
     pub files: Vec<String>,
+
+    /// The manifest text itself, to be stored with the release.
+    pub source: String,
 }
 
 impl Manifest {
@@ -53,26 +61,29 @@ impl Manifest {
     }
 
     pub fn from_str(manifest_source: String, root: &Path) -> Result<Self, ::failure::Error> {
-        let manifest_pair = parse_and_check_manifest(manifest_source)?;
+        let manifest_pair = parse_manifest(manifest_source)?;
 
         Ok(Self::from_manifest_pair(&manifest_pair, root)?)
     }
 
-    pub fn from_manifest_pair(manifest_pair: &Pair, root: &Path) -> Result<Self, ::failure::Error> {
-        let dependencies = get_dependencies(manifest_pair)?;
-
-        let package_arguments_pair =
-            get_optional_field(&manifest_pair, "package").ok_or_else(|| {
-                // We use get_optional_field and .ok_or_else to produce a
-                // clearer error message.
-                format_err!("A `package {{ ... }}` section is required to publish this package")
-                    .with_pos(&manifest_pair.clone().into_span().end_pos())
-            })?;
-        let block_pair = Arguments::from_pair(package_arguments_pair, 0, 0, &[], Some(true))?
-            .block
-            .expect("validated block presence");
+    /// Check the entire manifest, reporting every problem found rather than
+    /// stopping at the first one.
+    pub fn from_manifest_pair(
+        manifest_pair: &Pair,
+        root: &Path,
+    ) -> Result<Self, ManifestParserError> {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.check(check_manifest_fields(manifest_pair));
+        let dependencies = collect_dependencies(manifest_pair, &mut diagnostics);
+        let prereleases = collect_prerelease_policy(manifest_pair, &dependencies, &mut diagnostics);
+        let dependencies = without_options(dependencies);
+
+        let block_pair = match diagnostics.check(get_package_block(manifest_pair)) {
+            Some(block_pair) => block_pair,
+            None => return Err(diagnostics.into_result().unwrap_err()),
+        };
 
-        check_block_fields(
+        diagnostics.check(check_block_fields(
             &block_pair,
             &[
                 "name",
@@ -85,140 +96,357 @@ impl Manifest {
                 "bugs",
                 "license",
                 "license_file",
+                "readme",
                 "files",
             ],
-        )?;
-
-        let name = {
-            let name_pair = Arguments::get_single(get_field(&block_pair, "name")?)?;
-
-            let name_string = get_string(&name_pair)?;
-            PackageName::from_str(&name_string)
-                .ok_or_else(|| format_err!("Invalid package name").with_pair(&name_pair))?
-        };
-
-        let version = {
-            let version_pair = Arguments::get_single(get_field(&block_pair, "version")?)?;
-            let version_string = get_string(&version_pair)?;
-            Version::from_str(&version_string)
-                .ok_or_else(|| format_err!("Invalid version number").with_pair(&version_pair))?
-        };
+        ));
+
+        let name = diagnostics.check(get_package_name(&block_pair));
+        let version = diagnostics.check(get_package_version(&block_pair));
+        let description = diagnostics.check(
+            get_field(&block_pair, "description")
+                .and_then(Arguments::get_single)
+                .and_then(|description_pair| get_string(&description_pair)),
+        );
 
-        let description = get_string(&Arguments::get_single(get_field(
-            &block_pair,
-            "description",
-        )?)?)?;
+        let homepage = diagnostics.check(get_optional_string_field(&block_pair, "homepage"));
+        let repository = diagnostics.check(get_repository(&block_pair));
+        let bugs = diagnostics.check(get_optional_string_field(&block_pair, "bugs"));
 
-        let homepage = get_optional_string_field(&block_pair, "homepage")?;
-        let repository = get_optional_string_field(&block_pair, "repository")?;
-        let bugs = get_optional_string_field(&block_pair, "bugs")?;
+        let authors = diagnostics.check(get_optional_string_list_field(&block_pair, "authors"));
+        let keywords = diagnostics.check(get_optional_string_list_field(&block_pair, "keywords"));
 
-        let authors = get_optional_list_field(&block_pair, "authors")?
-            .into_iter()
-            .map(|i| get_string(&i))
-            .collect::<Result<_, _>>()?;
-        let keywords = get_optional_list_field(&block_pair, "keywords")?
-            .into_iter()
-            .map(|i| get_string(&i))
-            .collect::<Result<_, _>>()?;
-
-        let license = get_optional_string_field(&block_pair, "license")?;
-        let license_file = get_optional_string_field(&block_pair, "license_file")?;
+        let license = diagnostics.check(get_optional_string_field(&block_pair, "license"));
+        let license_file =
+            diagnostics.check(get_optional_text_file_field(&block_pair, "license_file", root));
+        let readme = diagnostics.check(match get_optional_field(&block_pair, "readme") {
+            Some(_) => get_optional_text_file_field(&block_pair, "readme", root),
+            None => find_readme(root).map_err(ManifestParserError::from),
+        });
 
-        if license.is_none() && license_file.is_none() {
-            return Err(::failure::Error::from(
+        if let (Some(None), Some(None)) = (&license, &license_file) {
+            diagnostics.push(
                 format_err!("package section needs at least one of license or license_file")
-                    .with_pos(&block_pair.into_span().start_pos()),
-            ));
+                    .with_pos(&block_pair.clone().into_span().start_pos()),
+            );
         }
 
-        let files_block = Arguments::get_block(get_field(&block_pair, "files")?)?;
-        let files = evaluate_files_block(&files_block, root)?;
+        let files = diagnostics.check(
+            get_field(&block_pair, "files")
+                .and_then(Arguments::get_block)
+                .and_then(|files_block| evaluate_files_block(&files_block, root)),
+        );
 
+        diagnostics.into_result()?;
+        // Without any errors, every field above was read successfully.
         Ok(Manifest {
-            name,
-            version,
+            name: name.unwrap(),
+            version: version.unwrap(),
 
             dependencies,
+            prereleases,
 
-            authors,
-            description,
-            homepage,
-            repository,
-            bugs,
-            keywords,
+            authors: authors.unwrap(),
+            description: description.unwrap(),
+            homepage: homepage.unwrap(),
+            repository: repository.unwrap(),
+            bugs: bugs.unwrap(),
+            keywords: keywords.unwrap(),
 
-            license,
-            license_file,
+            license: license.unwrap(),
+            license_file: license_file.unwrap(),
 
-            readme: None, // TODO
+            readme: readme.unwrap(),
+            files: files.unwrap(),
 
-            files,
+            source: manifest_pair.as_str().to_string(),
         })
     }
 }
 
+/// The parts of the manifest needed to install dependencies. Unlike
+/// `Manifest`, this doesn't require a `package` section and doesn't evaluate
+/// the `files` block, which needs a clean Git checkout.
+#[derive(Debug)]
+pub struct DependencyManifest {
+    pub dependencies: Vec<Dependency>,
+    pub prereleases: PrereleasePolicy,
+}
+
+impl DependencyManifest {
+    pub fn from_file(project_paths: &ProjectPaths) -> Result<Self, ::failure::Error> {
+        let manifest_source = fs::read_to_string(&project_paths.manifest)?;
+        Self::from_str(manifest_source)
+    }
+
+    pub fn from_str(manifest_source: String) -> Result<Self, ::failure::Error> {
+        let manifest_pair = parse_manifest(manifest_source)?;
+        Ok(Self::from_manifest_pair(&manifest_pair)?)
+    }
+
+    pub fn from_manifest_pair(manifest_pair: &Pair) -> Result<Self, ManifestParserError> {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.check(check_manifest_fields(manifest_pair));
+        let dependencies = collect_dependencies(manifest_pair, &mut diagnostics);
+        let prereleases = collect_prerelease_policy(manifest_pair, &dependencies, &mut diagnostics);
+        let dependencies = without_options(dependencies);
+        diagnostics.into_result()?;
+        Ok(DependencyManifest {
+            dependencies,
+            prereleases,
+        })
+    }
+}
+
+/// Report all problems in the manifest. The `package` section is only checked
+/// if present, since it's only needed to publish.
+pub fn check_manifest(manifest_source: String, root: &Path) -> Result<(), ManifestParserError> {
+    let manifest_pair = parse_manifest(manifest_source)?;
+    if get_optional_field(&manifest_pair, "package").is_some() {
+        Manifest::from_manifest_pair(&manifest_pair, root)?;
+    } else {
+        DependencyManifest::from_manifest_pair(&manifest_pair)?;
+    }
+    Ok(())
+}
+
 pub fn parse_and_check_manifest(manifest_source: String) -> Result<Pair, ::failure::Error> {
     let manifest_pair = parse_manifest(manifest_source)?;
+    check_manifest_fields(&manifest_pair)?;
+    Ok(manifest_pair)
+}
 
+fn check_manifest_fields(manifest_pair: &Pair) -> Result<(), ManifestParserError> {
     check_block_fields(
         &manifest_pair,
         &[
             "pm", // TODO do something with this version tag (if present)
             "dependencies",
+            "prereleases",
             "package",
         ],
-    )?;
+    )
+}
 
-    Ok(manifest_pair)
+fn get_package_block(manifest_pair: &Pair) -> Result<Pair, ManifestParserError> {
+    let package_arguments_pair = get_optional_field(manifest_pair, "package").ok_or_else(|| {
+        // We use get_optional_field and .ok_or_else to produce a clearer
+        // error message.
+        format_err!("A `package {{ ... }}` section is required to publish this package")
+            .with_pos(&manifest_pair.clone().into_span().end_pos())
+    })?;
+    Ok(
+        Arguments::from_pair(package_arguments_pair, 0, 0, &[], Some(true))?
+            .block
+            .expect("validated block presence"),
+    )
 }
 
-pub fn get_dependencies(manifest_pair: &Pair) -> Result<Vec<Dependency>, ::failure::Error> {
-    let mut depset = Vec::<Dependency>::new();
-    for (package_name_pair, arguments_pair) in
-        get_optional_block_field(&manifest_pair, "dependencies")?
-    {
-        let arguments = Arguments::from_pair(arguments_pair, 0, 2, &[], Some(false))?;
-        let (package_name, version_constraint) =
-            make_dependency(&package_name_pair, &arguments.positional_arguments)?;
-        for dep in depset.iter() {
-            if dep.package_name == package_name {
-                return Err(::failure::Error::from(
-                    format_err!("Duplicate dependency").with_pair(&package_name_pair),
-                ));
+fn get_package_name(block_pair: &Pair) -> Result<PackageName, ManifestParserError> {
+    let name_pair = Arguments::get_single(get_field(block_pair, "name")?)?;
+    let name_string = get_string(&name_pair)?;
+    PackageName::from_str(&name_string)
+        .ok_or_else(|| format_err!("Invalid package name").with_pair(&name_pair))
+}
+
+fn get_package_version(block_pair: &Pair) -> Result<Version, ManifestParserError> {
+    let version_pair = Arguments::get_single(get_field(block_pair, "version")?)?;
+    let version_string = get_string(&version_pair)?;
+    Version::from_str(&version_string)
+        .ok_or_else(|| format_err!("Invalid version number").with_pair(&version_pair))
+}
+
+/// Read the `repository { type "git" url "..." }` block.
+fn get_repository(block_pair: &Pair) -> Result<Option<Repository>, ManifestParserError> {
+    let repository_pair = match get_optional_field(block_pair, "repository") {
+        None => return Ok(None),
+        Some(arguments_pair) => Arguments::get_block(arguments_pair)?,
+    };
+    check_block_fields(&repository_pair, &["type", "url"])?;
+    let type_ = get_string(&Arguments::get_single(get_field(&repository_pair, "type")?)?)?;
+    let url = get_string(&Arguments::get_single(get_field(&repository_pair, "url")?)?)?;
+    Ok(Some(Repository { type_, url }))
+}
+
+/// Read a field naming a file in the project, like `license_file`, along with
+/// the file's contents.
+fn get_optional_text_file_field(
+    block_pair: &Pair,
+    field_name: &'static str,
+    root: &Path,
+) -> Result<Option<NamedTextFile>, ManifestParserError> {
+    match get_optional_field(block_pair, field_name) {
+        None => Ok(None),
+        Some(arguments_pair) => {
+            let path_pair = Arguments::get_single(arguments_pair)?;
+            let path = get_string(&path_pair)?;
+            Ok(Some(read_project_file(root, &path).pair_context(&path_pair)?))
+        }
+    }
+}
+
+/// Find a README in the project root, like `README.md` or `readme.txt`, if
+/// none is declared.
+fn find_readme(root: &Path) -> Result<Option<NamedTextFile>, failure::Error> {
+    let mut names = vec![];
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            let lowercase_name = name.to_lowercase();
+            if lowercase_name == "readme" || lowercase_name.starts_with("readme.") {
+                names.push(name.to_string());
             }
         }
-        depset.push(Dependency {
+    }
+    names.sort();
+    match names.first() {
+        None => Ok(None),
+        Some(name) => Ok(Some(read_project_file(root, name)?)),
+    }
+}
+
+fn read_project_file(root: &Path, path: &str) -> Result<NamedTextFile, failure::Error> {
+    let relative_path = Path::new(path);
+    if relative_path.is_absolute()
+        || relative_path
+            .components()
+            .any(|component| component == Component::ParentDir)
+    {
+        bail!("Expected a path inside the project directory");
+    }
+    let contents = fs::read_to_string(root.join(relative_path))
+        .map_err(|error| format_err!("Cannot read {}: {}", path, error))?;
+    Ok(NamedTextFile {
+        name: path.to_string(),
+        contents,
+    })
+}
+
+fn get_optional_string_list_field(
+    block_pair: &Pair,
+    field_name: &'static str,
+) -> Result<Vec<String>, ManifestParserError> {
+    get_optional_list_field(block_pair, field_name)?
+        .into_iter()
+        .map(|i| get_string(&i))
+        .collect()
+}
+
+pub fn get_dependencies(manifest_pair: &Pair) -> Result<Vec<Dependency>, ManifestParserError> {
+    let mut diagnostics = Diagnostics::default();
+    let dependencies = collect_dependencies(manifest_pair, &mut diagnostics);
+    diagnostics.into_result()?;
+    Ok(without_options(dependencies))
+}
+
+/// Read the `dependencies` block. Each dependency comes with its options if
+/// it's marked with the `pre` option, for `collect_prerelease_policy`.
+fn collect_dependencies(
+    manifest_pair: &Pair,
+    diagnostics: &mut Diagnostics,
+) -> Vec<(Dependency, Option<Pair>)> {
+    let mut depset = Vec::<(Dependency, Option<Pair>)>::new();
+    let fields = diagnostics
+        .check(get_optional_block_field(manifest_pair, "dependencies"))
+        .unwrap_or_default();
+    for (package_name_pair, arguments_pair) in fields {
+        let dependency = Arguments::from_pair(arguments_pair, 0, usize::MAX, &["pre"], Some(false))
+            .and_then(|arguments| {
+                let (package_name, version_constraint) =
+                    make_dependency(&package_name_pair, &arguments.positional_arguments)?;
+                let pre = if get_flag_option(arguments.options.clone(), "pre")? {
+                    Some(arguments.options)
+                } else {
+                    None
+                };
+                Ok((package_name, version_constraint, pre))
+            });
+        let (package_name, version_constraint, pre) = match diagnostics.check(dependency) {
+            Some(dependency) => dependency,
+            None => continue,
+        };
+        if depset.iter().any(|(dep, _)| dep.package_name == package_name) {
+            diagnostics.push(format_err!("Duplicate dependency").with_pair(&package_name_pair));
+            continue;
+        }
+        let dependency = Dependency {
             package_name,
             version_constraint,
-        });
+        };
+        depset.push((dependency, pre));
+    }
+    depset
+}
+
+fn without_options(dependencies: Vec<(Dependency, Option<Pair>)>) -> Vec<Dependency> {
+    dependencies.into_iter().map(|(dep, _)| dep).collect()
+}
+
+/// Read the `prereleases "never" | "explicit" | "always"` field, and opt in
+/// dependencies marked with the `pre` option.
+pub fn get_prerelease_policy(
+    manifest_pair: &Pair,
+) -> Result<PrereleasePolicy, ManifestParserError> {
+    let mut diagnostics = Diagnostics::default();
+    let dependencies = collect_dependencies(manifest_pair, &mut diagnostics);
+    let policy = collect_prerelease_policy(manifest_pair, &dependencies, &mut diagnostics);
+    diagnostics.into_result()?;
+    Ok(policy)
+}
+
+fn collect_prerelease_policy(
+    manifest_pair: &Pair,
+    dependencies: &[(Dependency, Option<Pair>)],
+    diagnostics: &mut Diagnostics,
+) -> PrereleasePolicy {
+    let mut policy = match get_optional_field(manifest_pair, "prereleases") {
+        None => PrereleasePolicy::default(),
+        Some(arguments_pair) => diagnostics
+            .check(Arguments::get_single(arguments_pair).and_then(|policy_pair| {
+                get_string(&policy_pair)?
+                    .parse::<PrereleasePolicy>()
+                    .map_err(|error| error.with_pair(&policy_pair))
+            }))
+            .unwrap_or_default(),
+    };
+    for (dependency, pre) in dependencies {
+        let options = match pre {
+            Some(options) => options,
+            None => continue,
+        };
+        if policy == PrereleasePolicy::Never {
+            diagnostics.push(
+                format_err!("`pre` has no effect with `prereleases \"never\"`").with_pair(options),
+            );
+            continue;
+        }
+        policy.allow_package(&dependency.package_name);
     }
-    Ok(depset)
+    policy
 }
 
 pub fn make_dependency(
     package_name_pair: &Pair,
     vcc_pairs: &[Pair],
-) -> Result<(PackageName, VersionConstraint), ::failure::Error> {
+) -> Result<(PackageName, VersionConstraint), ManifestParserError> {
     let package_name = PackageName::from_str(package_name_pair.as_str())
         .ok_or_else(|| format_err!("Invalid package name").with_pair(&package_name_pair))?;
 
-    let version_constraint = match vcc_pairs.len() {
-        0 => VersionConstraint::from_str("*"),
-        1 => {
-            let vc_component = vcc_pairs[0].clone();
-            VersionConstraint::from_str(vc_component.into_span().as_str())
-        }
-        2 => {
-            let vcc1 = vcc_pairs[0].clone(); // e.g. ">=2.0.0"
-            let vcc2 = vcc_pairs[1].clone(); // e.g. "<4.0.0"
-            VersionConstraint::from_str(&format!(
-                "{} {}",
-                vcc1.into_span().as_str(),
-                vcc2.into_span().as_str()
-            ))
-        }
-        _ => unreachable!(),
+    // Components like `>=2.0.0`, `<4.0.0`, `||` are joined back together and
+    // parsed as a whole.
+    let version_constraint = if vcc_pairs.is_empty() {
+        VersionConstraint::from_str("*")
+    } else {
+        VersionConstraint::from_str(
+            &vcc_pairs
+                .iter()
+                .map(|vcc_pair| vcc_pair.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
     }
     .ok_or_else(|| {
         format_err!("Invalid version constraint")
@@ -228,46 +456,36 @@ pub fn make_dependency(
     Ok((package_name, version_constraint))
 }
 
+/// Evaluate the `files` block. Errors in one entry don't stop the following
+/// entries from being checked.
 pub fn evaluate_files_block(
     files_block_pair: &Pair,
     root: &Path,
-) -> Result<Vec<String>, ::failure::Error> {
+) -> Result<Vec<String>, ManifestParserError> {
     let mut file_section_interpreter = FilesSectionInterpreter::new(root.to_path_buf())?;
     let mut file_set = HashSet::<String>::new();
+    let mut diagnostics = Diagnostics::default();
     for (symbol_pair, arguments_pair) in get_fields(&files_block_pair) {
-        match symbol_pair.as_str() {
-            "add_committed" => {
-                let glob_pair = Arguments::get_single(arguments_pair)?;
-                let glob = get_string(&glob_pair)?;
-
-                file_section_interpreter
-                    .add_committed(&mut file_set, &glob)
-                    .pair_context(&glob_pair)?;
-            }
-            "add_any" => {
-                let glob_pair = Arguments::get_single(arguments_pair)?;
-                let glob = get_string(&glob_pair)?;
-
-                file_section_interpreter
-                    .add_any(&mut file_set, &glob)
-                    .pair_context(&glob_pair)?;
-            }
-            "remove" => {
-                let glob_pair = Arguments::get_single(arguments_pair)?;
-                let glob = get_string(&glob_pair)?;
-
-                file_section_interpreter
-                    .remove(&mut file_set, &glob)
-                    .pair_context(&glob_pair)?;
+        let result = match symbol_pair.as_str() {
+            "add_committed" | "add_any" | "remove" => {
+                Arguments::get_single(arguments_pair).and_then(|glob_pair| {
+                    let glob = get_string(&glob_pair)?;
+                    match symbol_pair.as_str() {
+                        "add_committed" => {
+                            file_section_interpreter.add_committed(&mut file_set, &glob)
+                        }
+                        "add_any" => file_section_interpreter.add_any(&mut file_set, &glob),
+                        _ => file_section_interpreter.remove(&mut file_set, &glob),
+                    }
+                    .pair_context(&glob_pair)
+                })
             }
-            _ => {
-                return Err(::failure::Error::from(
-                    format_err!("Expected `add_committed`, `add_any`, or `remove`")
-                        .with_pair(&symbol_pair),
-                ));
-            }
-        }
+            _ => Err(format_err!("Expected `add_committed`, `add_any`, or `remove`")
+                .with_pair(&symbol_pair)),
+        };
+        diagnostics.check(result);
     }
+    diagnostics.into_result()?;
     let mut file_set_vec: Vec<String> = file_set.into_iter().collect();
     file_set_vec.sort_unstable();
     Ok(file_set_vec)
@@ -285,3 +503,200 @@ fn print_pairs(pairs: ::pest::iterators::Pairs<Rule, ::pest::inputs::StringInput
         print_pairs(pair.into_inner(), indent + 2);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::{pkg, range};
+
+    #[test]
+    fn dependency_version_constraints() {
+        let manifest_pair = parse_and_check_manifest(
+            "dependencies {
+  test/a
+  test/b >=1.2 <2
+  test/c ~1.2
+  test/d >=1.2, !=1.3.0
+  test/e ^1.0 || ^2.0
+  test/f 1.x
+}
+"
+            .to_string(),
+        )
+        .unwrap();
+        let dependencies = get_dependencies(&manifest_pair)
+            .unwrap()
+            .into_iter()
+            .map(|dep| (dep.package_name, dep.version_constraint))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dependencies,
+            vec![
+                (pkg("a"), range("*")),
+                (pkg("b"), range(">= 1.2 < 2")),
+                (pkg("c"), range("~1.2")),
+                (pkg("d"), range(">= 1.2, != 1.3.0")),
+                (pkg("e"), range("^1.0 || ^2.0")),
+                (pkg("f"), range("1.x")),
+            ]
+        );
+    }
+
+    #[test]
+    fn prerelease_policy() {
+        let policy = |source: &str| {
+            get_prerelease_policy(&parse_and_check_manifest(source.to_string()).unwrap())
+        };
+
+        assert_eq!(
+            policy("dependencies {\n  test/a ^1.0\n}\n").unwrap(),
+            PrereleasePolicy::default()
+        );
+        let mut opted_in = PrereleasePolicy::default();
+        opted_in.allow_package(&pkg("b"));
+        assert_eq!(
+            policy("dependencies {\n  test/a ^1.0\n  test/b ^1.0 pre\n}\n").unwrap(),
+            opted_in
+        );
+        assert_eq!(
+            policy("prereleases \"always\"\n").unwrap(),
+            PrereleasePolicy::Always
+        );
+        assert!(policy("prereleases \"sometimes\"\n").is_err());
+        assert!(
+            policy("prereleases \"never\"\ndependencies {\n  test/b ^1.0 pre\n}\n").is_err()
+        );
+    }
+
+    #[test]
+    fn report_all_errors() {
+        let error = check_manifest(
+            "dependencies {
+  test/a ^1.0
+  test/b >=1.0 frobnicate
+  test/a ^2.0
+  test/c ^1.0 pre=\"yes\"
+}
+prereleases \"sometimes\"
+frobnicate
+package {
+  name \"test/x\"
+  version \"one\"
+  description \"Test package\"
+  license \"MIT\"
+  colour \"blue\"
+  files {
+    remove \"*\"
+    add_everything \"*\"
+  }
+}
+"
+            .to_string(),
+            Path::new("."),
+        )
+        .unwrap_err();
+        let diagnostics = error
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.unwrap();
+                (location.line, location.column, diagnostic.message)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (3, 16, "Unexpected option".to_string()),
+                (4, 3, "Duplicate dependency".to_string()),
+                (5, 18, "Unexpected value".to_string()),
+                (7, 13, "Expected \"never\", \"explicit\" or \"always\"".to_string()),
+                (8, 1, "Unexpected field".to_string()),
+                (11, 11, "Invalid version number".to_string()),
+                (14, 3, "Unexpected field".to_string()),
+                (17, 5, "Expected `add_committed`, `add_any`, or `remove`".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_dependencies_without_package_section() {
+        assert!(check_manifest("dependencies {\n  test/a ^1.0\n}\n".to_string(), Path::new("."))
+            .is_ok());
+        let error = check_manifest(
+            "dependencies {\n  test/a ^1.0 x\n  test/b ^1.0 y\n}\n".to_string(),
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(error.diagnostics().len(), 2);
+    }
+
+    #[test]
+    fn dependency_manifest_ignores_package_section() {
+        let manifest = DependencyManifest::from_str(
+            "dependencies {
+  test/a ^1.0 pre
+}
+package {
+  name \"test/x\"
+  files {
+    add_everything \"*\"
+  }
+}
+"
+            .to_string(),
+        )
+        .unwrap();
+        assert_eq!(manifest.dependencies.len(), 1);
+        assert!(manifest.prereleases.allows(
+            &pkg("a"),
+            &range("*"),
+            &pm_lib::test_helpers::ver("1.0.0-beta")
+        ));
+
+        let manifest = DependencyManifest::from_str("".to_string()).unwrap();
+        assert!(manifest.dependencies.is_empty());
+    }
+
+    #[test]
+    fn read_package_files() {
+        let root = std::env::temp_dir().join(format!("pm-manifest-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("license")).unwrap();
+        fs::write(root.join("license/GPL"), "GNU GPL").unwrap();
+        fs::write(root.join("Readme.md"), "# Hello").unwrap();
+        let source = |extra_fields: &str| {
+            format!(
+                "package {{
+  name \"test/x\"
+  version \"1.0.0\"
+  description \"Test package\"
+  license_file \"license/GPL\"
+  repository {{
+    type \"git\"
+    url \"https://example.com/x.git\"
+  }}
+{}  files {{
+  }}
+}}
+",
+                extra_fields
+            )
+        };
+
+        let manifest = Manifest::from_str(source(""), &root).unwrap();
+        assert_eq!(manifest.license_file.unwrap().contents, "GNU GPL");
+        let readme = manifest.readme.unwrap();
+        assert_eq!((readme.name.as_str(), readme.contents.as_str()), ("Readme.md", "# Hello"));
+        let repository = manifest.repository.unwrap();
+        assert_eq!(repository.type_, "git");
+        assert_eq!(repository.url, "https://example.com/x.git");
+        assert_eq!(manifest.source, source(""));
+
+        let manifest = Manifest::from_str(source("  readme \"license/GPL\"\n"), &root).unwrap();
+        assert_eq!(manifest.readme.unwrap().contents, "GNU GPL");
+
+        let error = Manifest::from_str(source("  readme \"../README\"\n"), &root).unwrap_err();
+        assert!(error.to_string().contains("inside the project directory"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+// Edit the manifest source in place, using the spans of the parsed manifest so
+// that comments, blank lines and field order survive.
+
+use crate::manifest::parse_and_check_manifest;
+use crate::manifest_parser::{children, find_rule, get_fields, Arguments, Pair, Rule};
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::package::PackageName;
+
+/// Add `package` to the `dependencies` block, or replace its version
+/// constraint if it is already listed. The block is created if it doesn't
+/// exist yet.
+pub fn add_dependency(
+    source: &str,
+    package: &PackageName,
+    constraint: &VersionConstraint,
+) -> Result<String, failure::Error> {
+    let manifest_pair = parse_and_check_manifest(source.to_string())?;
+    let dependency_line = format_dependency(package, constraint);
+
+    let block_pair = match dependencies_block(&manifest_pair)? {
+        None => {
+            let mut new_source = source.to_string();
+            if !new_source.is_empty() && !new_source.ends_with('\n') {
+                new_source.push('\n');
+            }
+            if !new_source.trim().is_empty() {
+                new_source.push('\n');
+            }
+            new_source.push_str(&format!("dependencies {{\n  {}\n}}\n", dependency_line));
+            return Ok(new_source);
+        }
+        Some(block_pair) => block_pair,
+    };
+
+    let fields = dependency_fields(&block_pair);
+    if let Some(field_pair) = fields
+        .iter()
+        .find(|field_pair| symbol_of(field_pair) == package.to_string())
+    {
+        // Only replace the positional arguments, so that options and
+        // trailing comments are kept.
+        let arguments_pair = find_rule(field_pair.clone(), Rule::arguments);
+        let span = find_rule(arguments_pair, Rule::positional_arguments).into_span();
+        let old = &source[span.start()..span.end()];
+        let trailing_ws = &old[old.trim_end().len()..];
+        let mut replacement = constraint_arguments(constraint);
+        let needs_separator = !replacement.is_empty()
+            && !source[..span.start()].ends_with(&[' ', '\t'][..]);
+        if needs_separator {
+            replacement.insert(0, ' ');
+        }
+        let followed_by_more = !source[span.end()..].starts_with(&['\r', '\n'][..]);
+        if trailing_ws.is_empty() && followed_by_more && !replacement.is_empty() {
+            replacement.push(' ');
+        } else {
+            replacement.push_str(trailing_ws);
+        }
+        return Ok(splice(source, span.start(), span.end(), &replacement));
+    }
+
+    // Append after the last dependency, with the same indentation.
+    let (insert_at, indentation) = match fields.last() {
+        Some(field_pair) => {
+            let span = field_pair.clone().into_span();
+            let indentation = find_rule(field_pair.clone(), Rule::maybe_ws).as_str().to_string();
+            (line_end(source, span.end()), indentation)
+        }
+        None => {
+            let fields_pair = find_rule(block_pair, Rule::fields_newline_terminated);
+            (fields_pair.into_span().end(), "  ".to_string())
+        }
+    };
+    Ok(splice(
+        source,
+        insert_at,
+        insert_at,
+        &format!("{}{}\n", indentation, dependency_line),
+    ))
+}
+
+/// Remove the line listing `package` from the `dependencies` block.
+pub fn remove_dependency(source: &str, package: &PackageName) -> Result<String, failure::Error> {
+    let manifest_pair = parse_and_check_manifest(source.to_string())?;
+    let field_pair = dependencies_block(&manifest_pair)?
+        .and_then(|block_pair| {
+            dependency_fields(&block_pair)
+                .into_iter()
+                .find(|field_pair| symbol_of(field_pair) == package.to_string())
+        })
+        .ok_or_else(|| format_err!("{} is not listed in the dependencies", package))?;
+    let span = field_pair.into_span();
+    Ok(splice(source, span.start(), line_end(source, span.end()), ""))
+}
+
+fn dependencies_block(manifest_pair: &Pair) -> Result<Option<Pair>, failure::Error> {
+    for (symbol_pair, arguments_pair) in get_fields(manifest_pair) {
+        if symbol_pair.as_str() == "dependencies" {
+            return Ok(Some(Arguments::get_block(arguments_pair)?));
+        }
+    }
+    Ok(None)
+}
+
+fn dependency_fields(block_pair: &Pair) -> Vec<Pair> {
+    children(
+        find_rule(block_pair.clone(), Rule::fields_newline_terminated),
+        Rule::field,
+    )
+}
+
+fn symbol_of(field_pair: &Pair) -> String {
+    find_rule(field_pair.clone(), Rule::symbol).as_str().to_string()
+}
+
+fn format_dependency(package: &PackageName, constraint: &VersionConstraint) -> String {
+    let arguments = constraint_arguments(constraint);
+    if arguments.is_empty() {
+        package.to_string()
+    } else {
+        format!("{} {}", package, arguments)
+    }
+}
+
+/// A dependency without a version constraint matches any version, and `*`
+/// can't be written in the manifest.
+fn constraint_arguments(constraint: &VersionConstraint) -> String {
+    match constraint {
+        VersionConstraint::Range(None, None) => String::new(),
+        _ => format_constraint(constraint),
+    }
+}
+
+/// Format a version constraint in manifest syntax, which doesn't allow
+/// spaces between an operator and its version (`>=1.2 <2`).
+pub fn format_constraint(constraint: &VersionConstraint) -> String {
+    let join = |constraints: &[VersionConstraint], separator: &str| {
+        constraints
+            .iter()
+            .map(format_constraint)
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    match constraint {
+        VersionConstraint::Range(Some(min), None) => format!(">={}", min),
+        VersionConstraint::Range(None, Some(max)) => format!("<{}", max),
+        VersionConstraint::Range(Some(min), Some(max)) => format!(">={} <{}", min, max),
+        VersionConstraint::Exclude(v) => format!("!={}", v),
+        VersionConstraint::All(constraints) => join(constraints, ", "),
+        VersionConstraint::Any(constraints) => join(constraints, " || "),
+        _ => constraint.to_string(),
+    }
+}
+
+/// Return the index just past the newline ending the line that `pos` is on.
+fn line_end(source: &str, pos: usize) -> usize {
+    source[pos..]
+        .find('\n')
+        .map_or(source.len(), |offset| pos + offset + 1)
+}
+
+fn splice(source: &str, start: usize, end: usize, replacement: &str) -> String {
+    format!("{}{}{}", &source[..start], replacement, &source[end..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::{pkg, range};
+
+    const SOURCE: &str = "pm \"0.1\"
+
+// Runtime dependencies
+dependencies {
+    test/a ^1.0 // keep me
+
+    test/b >=1.2 <2 pre
+}
+";
+
+    #[test]
+    fn add_new_dependency() {
+        assert_eq!(
+            add_dependency(SOURCE, &pkg("c"), &range("^2.1")).unwrap(),
+            "pm \"0.1\"
+
+// Runtime dependencies
+dependencies {
+    test/a ^1.0 // keep me
+
+    test/b >=1.2 <2 pre
+    test/c ^2.1
+}
+"
+        );
+        assert_eq!(
+            add_dependency("dependencies {\n}\n", &pkg("c"), &range("*")).unwrap(),
+            "dependencies {\n  test/c\n}\n"
+        );
+    }
+
+    #[test]
+    fn add_replaces_constraint() {
+        assert_eq!(
+            add_dependency(SOURCE, &pkg("a"), &range("^1.4")).unwrap(),
+            SOURCE.replace("test/a ^1.0 // keep me", "test/a ^1.4 // keep me")
+        );
+        assert_eq!(
+            add_dependency(SOURCE, &pkg("b"), &range("~1.3")).unwrap(),
+            SOURCE.replace("test/b >=1.2 <2 pre", "test/b ~1.3 pre")
+        );
+        assert_eq!(
+            add_dependency("dependencies {\n  test/a\n}\n", &pkg("a"), &range("^1")).unwrap(),
+            "dependencies {\n  test/a ^1\n}\n"
+        );
+    }
+
+    #[test]
+    fn add_creates_dependencies_block() {
+        assert_eq!(
+            add_dependency("pm \"0.1\"", &pkg("a"), &range("^1")).unwrap(),
+            "pm \"0.1\"\n\ndependencies {\n  test/a ^1\n}\n"
+        );
+        assert_eq!(
+            add_dependency("", &pkg("a"), &range("^1")).unwrap(),
+            "dependencies {\n  test/a ^1\n}\n"
+        );
+    }
+
+    #[test]
+    fn remove_existing_dependency() {
+        assert_eq!(
+            remove_dependency(SOURCE, &pkg("a")).unwrap(),
+            SOURCE.replace("    test/a ^1.0 // keep me\n", "")
+        );
+        assert!(remove_dependency(SOURCE, &pkg("c")).is_err());
+        assert!(remove_dependency("", &pkg("a")).is_err());
+    }
+
+    #[test]
+    fn format_constraint_roundtrip() {
+        for constraint in &[
+            "^1.2",
+            "~1.2.3",
+            "1.x",
+            "= 1.0.0",
+            ">= 1.2",
+            "< 2",
+            ">= 1.2 < 2",
+            ">= 1.2, != 1.3.0",
+            "^1.0 || >= 2.1 < 3",
+        ] {
+            let source = add_dependency("", &pkg("a"), &range(constraint)).unwrap();
+            let manifest_pair = parse_and_check_manifest(source).unwrap();
+            let dependencies = crate::manifest::get_dependencies(&manifest_pair).unwrap();
+            assert_eq!(dependencies[0].version_constraint, range(constraint));
+        }
+    }
+}
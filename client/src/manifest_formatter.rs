@@ -0,0 +1,385 @@
+// Canonical formatting for manifests: two-space indentation, one space between
+// arguments, at most one blank line in a row, normalized strings and version
+// constraints, and dependencies sorted by name. Comments are preserved.
+
+use crate::manifest_editor::format_constraint;
+use crate::manifest_parser::{
+    children, find_optional_rule, find_rule, parse_manifest, parse_string, quote_string, Pair,
+    Rule,
+};
+use crate::manifest_parser_error::ManifestParserError;
+use pm_lib::constraint::VersionConstraint;
+
+const INDENT: &str = "  ";
+
+/// Lists that fit within this width and contain no comments or blocks are
+/// kept on one line.
+const MAX_INLINE_LIST_WIDTH: usize = 80;
+
+pub fn format_manifest(source: String) -> Result<String, ManifestParserError> {
+    let manifest_pair = parse_manifest(source)?;
+    let fields_pair = find_rule(manifest_pair, Rule::fields_not_newline_terminated);
+    let mut out = String::new();
+    format_fields(&mut out, fields_pair, 0, false)?;
+    Ok(out)
+}
+
+enum Line {
+    Blank,
+    Comment(String),
+    Field(Pair),
+}
+
+fn format_fields(
+    out: &mut String,
+    fields_pair: Pair,
+    depth: usize,
+    sort: bool,
+) -> Result<(), ManifestParserError> {
+    let mut lines: Vec<Line> = vec![];
+    for pair in fields_pair.into_inner() {
+        let line = match pair.as_rule() {
+            Rule::field => Line::Field(pair),
+            // blank_line is atomic, so the comment has no pair of its own.
+            Rule::blank_line => match pair.as_str().trim() {
+                "" => Line::Blank,
+                comment => Line::Comment(comment.to_string()),
+            },
+            _ => continue,
+        };
+        // Collapse runs of blank lines, and drop leading ones.
+        if let Line::Blank = line {
+            match lines.last() {
+                None | Some(Line::Blank) => continue,
+                _ => {}
+            }
+        }
+        lines.push(line);
+    }
+    while let Some(Line::Blank) = lines.last() {
+        lines.pop();
+    }
+    if sort {
+        lines = sort_fields(lines);
+    }
+
+    let indent = INDENT.repeat(depth);
+    for line in lines {
+        match line {
+            Line::Blank => out.push('\n'),
+            Line::Comment(comment) => {
+                out.push_str(&format!("{}{}\n", indent, comment));
+            }
+            Line::Field(field_pair) => {
+                out.push_str(&indent);
+                format_field(out, field_pair, depth)?;
+                out.push('\n');
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sort fields by name within each group of lines separated by blank lines.
+/// Comments directly above a field move with it; comments at the end of a
+/// group stay there.
+fn sort_fields(lines: Vec<Line>) -> Vec<Line> {
+    let mut sorted = vec![];
+    let mut units: Vec<(String, Vec<Line>)> = vec![];
+    let mut pending: Vec<Line> = vec![];
+    let flush = |sorted: &mut Vec<Line>,
+                 units: &mut Vec<(String, Vec<Line>)>,
+                 pending: &mut Vec<Line>| {
+        units.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, unit) in units.drain(..) {
+            sorted.extend(unit);
+        }
+        sorted.append(pending);
+    };
+    for line in lines {
+        match line {
+            Line::Blank => {
+                flush(&mut sorted, &mut units, &mut pending);
+                sorted.push(Line::Blank);
+            }
+            Line::Comment(_) => pending.push(line),
+            Line::Field(ref field_pair) => {
+                let symbol = find_rule(field_pair.clone(), Rule::symbol).as_str().to_string();
+                pending.push(line);
+                units.push((symbol, std::mem::take(&mut pending)));
+            }
+        }
+    }
+    flush(&mut sorted, &mut units, &mut pending);
+    sorted
+}
+
+fn format_field(
+    out: &mut String,
+    field_pair: Pair,
+    depth: usize,
+) -> Result<(), ManifestParserError> {
+    let symbol = find_rule(field_pair.clone(), Rule::symbol).as_str().to_string();
+    let arguments_pair = find_rule(field_pair.clone(), Rule::arguments);
+    out.push_str(&symbol);
+
+    let positional_arguments = children(
+        find_rule(arguments_pair.clone(), Rule::positional_arguments),
+        Rule::positional_argument,
+    );
+    let mut constraint_components = vec![];
+    for argument_pair in positional_arguments {
+        let inner = argument_pair.into_inner().next().expect("positional argument");
+        if inner.as_rule() == Rule::version_constraint_component {
+            constraint_components.push(inner.as_str().to_string());
+            continue;
+        }
+        push_constraint(out, &mut constraint_components);
+        out.push(' ');
+        format_value(out, inner, depth)?;
+    }
+    push_constraint(out, &mut constraint_components);
+
+    for option_pair in children(find_rule(arguments_pair.clone(), Rule::options), Rule::option) {
+        out.push(' ');
+        out.push_str(find_rule(option_pair.clone(), Rule::option_name).as_str());
+        if let Some(value_pair) = find_optional_rule(option_pair, Rule::option_value) {
+            out.push('=');
+            let inner = value_pair.into_inner().next().expect("option value");
+            format_value(out, inner, depth)?;
+        }
+    }
+
+    if let Some(block_pair) = find_optional_rule(arguments_pair, Rule::block) {
+        out.push(' ');
+        format_block(out, block_pair, depth, depth == 0 && symbol == "dependencies")?;
+    }
+    if let Some(comment_pair) = find_optional_rule(field_pair, Rule::comment_) {
+        out.push(' ');
+        out.push_str(comment_pair.as_str().trim_end());
+    }
+    Ok(())
+}
+
+/// Join consecutive version constraint components and normalize them, if
+/// they form a valid constraint.
+fn push_constraint(out: &mut String, components: &mut Vec<String>) {
+    if components.is_empty() {
+        return;
+    }
+    let joined = components.join(" ");
+    out.push(' ');
+    match VersionConstraint::from_str(&joined) {
+        Some(constraint) => out.push_str(&format_constraint(&constraint)),
+        None => out.push_str(&joined),
+    }
+    components.clear();
+}
+
+fn format_value(out: &mut String, pair: Pair, depth: usize) -> Result<(), ManifestParserError> {
+    match pair.as_rule() {
+        Rule::string => out.push_str(&quote_string(&parse_string(pair)?)),
+        Rule::list => format_list(out, pair, depth)?,
+        Rule::block => format_block(out, pair, depth, false)?,
+        Rule::list_item => {
+            let inner = pair.into_inner().next().expect("list item");
+            format_value(out, inner, depth)?
+        }
+        _ => out.push_str(pair.as_str()),
+    }
+    Ok(())
+}
+
+fn format_block(
+    out: &mut String,
+    block_pair: Pair,
+    depth: usize,
+    sort: bool,
+) -> Result<(), ManifestParserError> {
+    out.push('{');
+    if let Some(comment_pair) = find_optional_rule(block_pair.clone(), Rule::comment_) {
+        out.push(' ');
+        out.push_str(comment_pair.as_str().trim_end());
+    }
+    out.push('\n');
+    let fields_pair = find_rule(block_pair, Rule::fields_newline_terminated);
+    format_fields(out, fields_pair, depth + 1, sort)?;
+    out.push_str(&INDENT.repeat(depth));
+    out.push('}');
+    Ok(())
+}
+
+enum ListEntry {
+    Item(Pair),
+    Comment(String),
+    TrailingComment(String),
+}
+
+fn format_list(
+    out: &mut String,
+    list_pair: Pair,
+    depth: usize,
+) -> Result<(), ManifestParserError> {
+    let mut entries = vec![];
+    let mut newline_since_item = true;
+    let mut multiline = false;
+    for pair in list_pair.into_inner() {
+        match pair.as_rule() {
+            Rule::list_item => {
+                if find_optional_rule(pair.clone(), Rule::block).is_some() {
+                    multiline = true;
+                }
+                entries.push(ListEntry::Item(pair));
+                newline_since_item = false;
+            }
+            Rule::whitespace_with_newline => {
+                if let Some(comment_pair) = find_optional_rule(pair.clone(), Rule::comment_) {
+                    let comment = comment_pair.as_str().trim_end().to_string();
+                    multiline = true;
+                    entries.push(if newline_since_item {
+                        ListEntry::Comment(comment)
+                    } else {
+                        ListEntry::TrailingComment(comment)
+                    });
+                }
+                if pair.as_str().ends_with('\n') {
+                    newline_since_item = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if entries.is_empty() {
+        out.push_str("[]");
+        return Ok(());
+    }
+    if !multiline {
+        let mut inline = "[".to_string();
+        for entry in &entries {
+            if let ListEntry::Item(pair) = entry {
+                inline.push(' ');
+                format_value(&mut inline, pair.clone(), depth)?;
+            }
+        }
+        inline.push_str(" ]");
+        if !inline.contains('\n') && inline.len() <= MAX_INLINE_LIST_WIDTH {
+            out.push_str(&inline);
+            return Ok(());
+        }
+    }
+
+    let indent = INDENT.repeat(depth + 1);
+    out.push('[');
+    for entry in entries {
+        match entry {
+            ListEntry::Item(pair) => {
+                out.push('\n');
+                out.push_str(&indent);
+                format_value(out, pair, depth + 1)?;
+            }
+            ListEntry::Comment(comment) => {
+                out.push('\n');
+                out.push_str(&indent);
+                out.push_str(&comment);
+            }
+            ListEntry::TrailingComment(comment) => {
+                out.push(' ');
+                out.push_str(&comment);
+            }
+        }
+    }
+    out.push('\n');
+    out.push_str(&INDENT.repeat(depth));
+    out.push(']');
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        let formatted = format_manifest(source.to_string()).unwrap();
+        assert_eq!(
+            format_manifest(formatted.clone()).unwrap(),
+            formatted,
+            "formatting is not idempotent"
+        );
+        formatted
+    }
+
+    #[test]
+    fn format_dependencies() {
+        assert_eq!(
+            format(
+                "
+
+dependencies   {   // deps
+      test/b   >=1.2,   !=1.3    pre
+  test/a ^1.0 // first
+
+
+  // test/z ^1
+    // test/y ^1
+  test/d
+  test/c ~1.2
+  // trailing
+}
+"
+            ),
+            "dependencies { // deps
+  test/a ^1.0 // first
+  test/b >=1.2, !=1.3 pre
+
+  test/c ~1.2
+  // test/z ^1
+  // test/y ^1
+  test/d
+  // trailing
+}
+"
+        );
+    }
+
+    #[test]
+    fn format_package_block() {
+        assert_eq!(
+            format(
+                "package {
+name \"test/a\"
+  description \"Say\\u{9}\\\"hi\\\"\"
+   authors [\"A\"   \"B\"]
+ keywords [
+      \"x\" // first
+   // more
+   ]
+  files {
+      add_committed \".\"
+      remove   \"vendor\"
+  }
+}"
+            ),
+            "package {
+  name \"test/a\"
+  description \"Say\\t\\\"hi\\\"\"
+  authors [ \"A\" \"B\" ]
+  keywords [
+    \"x\" // first
+    // more
+  ]
+  files {
+    add_committed \".\"
+    remove \"vendor\"
+  }
+}
+"
+        );
+    }
+
+    #[test]
+    fn format_empty_manifest() {
+        assert_eq!(format(""), "");
+        assert_eq!(format("\n\n// only a comment\n\n"), "// only a comment\n");
+    }
+}
@@ -3,7 +3,7 @@
 use pest;
 use pest::Parser;
 
-use crate::manifest_parser_error::{ManifestParserError, PestErrorExt};
+use crate::manifest_parser_error::{Diagnostics, ManifestParserError, PestErrorExt};
 
 // Ensure this file recompiles when the grammar is modified.
 const _GRAMMAR: &str = include_str!("grammar.pest");
@@ -54,22 +54,24 @@ fn check_keys(
     element_type: &'static str,
     names: &'static [&'static str],
 ) -> Result<(), ManifestParserError> {
+    let mut diagnostics = Diagnostics::default();
     let mut seen = vec![false; names.len()];
     'pair_loop: for name_pair in name_pairs {
         let name = name_pair.as_str();
         for i in 0..names.len() {
             if name == names[i] {
                 if seen[i] {
-                    return Err(format_err!("Duplicate {}", element_type).with_pair(&name_pair));
+                    diagnostics
+                        .push(format_err!("Duplicate {}", element_type).with_pair(name_pair));
                 } else {
                     seen[i] = true;
-                    continue 'pair_loop;
                 }
+                continue 'pair_loop;
             }
         }
-        return Err(format_err!("Unexpected {}", element_type).with_pair(&name_pair));
+        diagnostics.push(format_err!("Unexpected {}", element_type).with_pair(name_pair));
     }
-    Ok(())
+    diagnostics.into_result()
 }
 
 pub fn get_option(options_pair: Pair, name: &'static str) -> Option<Pair> {
@@ -310,6 +312,24 @@ pub fn parse_string(string_pair: Pair) -> Result<String, ManifestParserError> {
     Ok(s)
 }
 
+/// The inverse of `parse_string`: produce a quoted string literal.
+pub fn quote_string(s: &str) -> String {
+    let mut quoted = "\"".to_string();
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            ' '..='~' => quoted.push(c),
+            _ if c >= '\u{00A0}' => quoted.push(c),
+            _ => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 pub fn children(pair: Pair, rule: Rule) -> Vec<Pair> {
     children_of_pairs(pair.into_inner(), rule)
 }
@@ -1,7 +1,7 @@
 use failure;
 use pest;
 use std::convert::From;
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
 use crate::manifest_parser::Rule;
 
@@ -10,11 +10,15 @@ use crate::manifest_parser::Rule;
 // a workaround, we stringify them on instantiation.
 #[derive(Fail, Debug)]
 pub enum ManifestParserError {
-    #[fail(display = "{}", _0)]
-    PestError(String),
+    #[fail(display = "{}", description)]
+    PestError {
+        description: String,
+        location: Location,
+    },
     #[fail(display = "{}", description)]
     ErrorAtSpan {
         description: String,
+        location: Location,
         // We'd like to tag the original_error as #[cause], but failure::Error
         // doesn't implement std::error::Error (yet?).
         original_error: failure::Error,
@@ -22,8 +26,160 @@ pub enum ManifestParserError {
     #[fail(display = "{}", description)]
     ErrorAtPos {
         description: String,
+        location: Location,
         original_error: failure::Error,
     },
+    // An error without a position in the manifest, like an I/O error.
+    #[fail(display = "{}", _0)]
+    Other(failure::Error),
+    #[fail(display = "{}", _0)]
+    Multiple(ManifestErrors),
+}
+
+/// 1-based line and column numbers of an error, as displayed by Pest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: Option<usize>,
+    pub end_column: Option<usize>,
+}
+
+impl Location {
+    fn from_pos<I: pest::inputs::Input>(pos: &pest::inputs::Position<I>) -> Location {
+        let (line, column) = pos.line_col();
+        Location {
+            line,
+            column,
+            end_line: None,
+            end_column: None,
+        }
+    }
+
+    fn from_span<I: pest::inputs::Input>(span: &pest::inputs::Span<I>) -> Location {
+        let (end_line, end_column) = span.end_pos().line_col();
+        Location {
+            end_line: Some(end_line),
+            end_column: Some(end_column),
+            ..Location::from_pos(&span.start_pos())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ManifestErrors(pub Vec<ManifestParserError>);
+
+impl Display for ManifestErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single error in machine-readable form, for `--message-format json`.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub message: String,
+    #[serde(flatten)]
+    pub location: Option<Location>,
+    pub rendered: String,
+}
+
+impl ManifestParserError {
+    /// Where in the manifest this error is, if it has a single location.
+    fn location(&self) -> Option<Location> {
+        match self {
+            ManifestParserError::PestError { location, .. }
+            | ManifestParserError::ErrorAtSpan { location, .. }
+            | ManifestParserError::ErrorAtPos { location, .. } => Some(*location),
+            ManifestParserError::Other(_) | ManifestParserError::Multiple(_) => None,
+        }
+    }
+
+    /// Flatten this error into one diagnostic per problem found.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let (message, location) = match self {
+            ManifestParserError::PestError {
+                description,
+                location,
+            } => {
+                // Pest puts the message on the last line, after "= ".
+                let last_line = description.lines().last().unwrap_or("");
+                let message = last_line.trim_start().trim_start_matches("= ");
+                (message.to_string(), Some(*location))
+            }
+            ManifestParserError::ErrorAtSpan {
+                location,
+                original_error,
+                ..
+            }
+            | ManifestParserError::ErrorAtPos {
+                location,
+                original_error,
+                ..
+            } => (original_error.to_string(), Some(*location)),
+            ManifestParserError::Other(error) => (error.to_string(), None),
+            ManifestParserError::Multiple(ManifestErrors(errors)) => {
+                return errors.iter().flat_map(|error| error.diagnostics()).collect();
+            }
+        };
+        vec![Diagnostic {
+            message,
+            location,
+            rendered: self.to_string(),
+        }]
+    }
+}
+
+/// Collects errors so that we can keep checking a manifest after the first
+/// problem and report everything at once.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    errors: Vec<ManifestParserError>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, error: ManifestParserError) {
+        match error {
+            ManifestParserError::Multiple(ManifestErrors(errors)) => {
+                for error in errors {
+                    self.push(error);
+                }
+            }
+            error => self.errors.push(error),
+        }
+    }
+
+    /// Record the error, if any, and return the value otherwise.
+    pub fn check<T>(&mut self, result: Result<T, ManifestParserError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.push(error);
+                None
+            }
+        }
+    }
+
+    /// Return the errors collected, in the order they appear in the manifest.
+    /// Errors without a location come last.
+    pub fn into_result(mut self) -> Result<(), ManifestParserError> {
+        self.errors.sort_by_key(|error| match error.location() {
+            Some(location) => (false, location.line, location.column),
+            None => (true, 0, 0),
+        });
+        match self.errors.len() {
+            0 => Ok(()),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(ManifestParserError::Multiple(ManifestErrors(self.errors))),
+        }
+    }
 }
 
 pub trait PestErrorExt<E>
@@ -60,6 +216,7 @@ where
         };
         ManifestParserError::ErrorAtSpan {
             description: format!("{}", dummy_pest_error),
+            location: Location::from_span(span),
             original_error: failure::Error::from(self),
         }
     }
@@ -82,6 +239,7 @@ where
         };
         ManifestParserError::ErrorAtPos {
             description: format!("{}", dummy_pest_error),
+            location: Location::from_pos(pos),
             original_error: failure::Error::from(self),
         }
     }
@@ -142,6 +300,20 @@ where
 
 impl From<pest::Error<Rule, pest::inputs::StringInput>> for ManifestParserError {
     fn from(pest_error: pest::Error<Rule, pest::inputs::StringInput>) -> Self {
-        ManifestParserError::PestError(format!("{}", pest_error))
+        let location = match pest_error {
+            pest::Error::ParsingError { ref pos, .. }
+            | pest::Error::CustomErrorPos { ref pos, .. } => Location::from_pos(pos),
+            pest::Error::CustomErrorSpan { ref span, .. } => Location::from_span(span),
+        };
+        ManifestParserError::PestError {
+            description: format!("{}", pest_error),
+            location,
+        }
+    }
+}
+
+impl From<failure::Error> for ManifestParserError {
+    fn from(error: failure::Error) -> Self {
+        ManifestParserError::Other(error)
     }
 }
@@ -4,6 +4,7 @@ use reqwest::Body;
 use reqwest::{self, Method};
 use serde::Deserialize;
 use serde_json;
+use std::env;
 use std::fmt;
 use std::io::Read;
 use url::form_urlencoded::Serializer;
@@ -13,16 +14,30 @@ use crate::config::get_config;
 #[derive(Fail, Deserialize, Debug)]
 pub struct RegistryError {
     message: String,
+    // Machine-readable error code, like "invalid_license".
+    #[serde(default)]
+    code: Option<String>,
 }
 
 impl fmt::Display for RegistryError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.message)
+        match self.code {
+            Some(ref code) => write!(f, "{} [{}]", self.message, code),
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
 pub type Response<A> = Result<A, RegistryError>;
 
+const DEFAULT_REGISTRY_URL: &str = "http://localhost:8000";
+
+/// The registry to talk to: `PM_REGISTRY_URL` if set, or a registry running
+/// on this machine.
+pub fn registry_url() -> String {
+    env::var("PM_REGISTRY_URL").unwrap_or_else(|_| DEFAULT_REGISTRY_URL.to_string())
+}
+
 fn read_auth() -> Result<String, failure::Error> {
     let config = get_config()?;
     config
@@ -51,7 +66,7 @@ where
     let http = reqwest::Client::new();
     let mut req = http.request(
         method,
-        &format!("http://localhost:8000/{}?{}", url, args_str),
+        &format!("{}/{}?{}", registry_url(), url, args_str),
     );
     if auth {
         req = req.header("Authorization", format!("Bearer {}", read_auth()?));
@@ -1,15 +1,21 @@
 use reqwest::{self, Method};
-use pm_lib::index::Index;
+use pm_lib::index::{Index, RegistryIndex};
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
 
-use crate::REGISTRY_URL;
+use crate::registry::registry_url;
 
 // This module should probably be renamed or merged into another module.
 
 pub fn fetch_index() -> Result<Index, ::failure::Error> {
+    Ok(fetch_registry_index()?.packages)
+}
+
+pub fn fetch_registry_index() -> Result<RegistryIndex, ::failure::Error> {
     let http = reqwest::Client::new();
     let req = http.request(
         Method::GET,
-        &format!("{}/index", REGISTRY_URL)
+        &format!("{}/index", registry_url())
     );
     let mut res = req.send()?;
 
@@ -19,3 +25,23 @@ pub fn fetch_index() -> Result<Index, ::failure::Error> {
         bail!("Error: {}", &res.text()?);
     }
 }
+
+pub fn fetch_tar_br(name: &PackageName, version: &Version) -> Result<Vec<u8>, ::failure::Error> {
+    let http = reqwest::Client::new();
+    let req = http.request(
+        Method::GET,
+        &format!(
+            "{}/files/tar-br/{}/{}/{}",
+            registry_url(), name.namespace, name.name, version
+        ),
+    );
+    let mut res = req.send()?;
+
+    if res.status().is_success() {
+        let mut tar_br = vec![];
+        res.copy_to(&mut tar_br)?;
+        Ok(tar_br)
+    } else {
+        bail!("Unable to download {} {}: {}", name, version, res.status());
+    }
+}
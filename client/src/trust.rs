@@ -0,0 +1,154 @@
+// The trust policy decides which releases must be signed, and by which keys.
+// It lives in the `[trust]` section of the config file:
+//
+//     [trust.namespaces]
+//     acme = ["<public key>", "<public key>"]
+//
+//     [trust.owners]
+//     "github:someone" = ["<public key>"]
+//
+// A namespace rule requires every release in the namespace to be signed by
+// one of the listed keys. An owner rule requires every release published by
+// that registry user to be signed by one of their listed keys, including
+// releases that aren't signed at all; since the registry reports who published
+// a release, owner rules protect against a stolen registry login but not
+// against a compromised registry. Releases that no rule covers are not
+// checked.
+
+use std::collections::BTreeMap;
+
+use pm_lib::index::SignedRelease;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustPolicy {
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub owners: BTreeMap<String, Vec<String>>,
+}
+
+/// Errors name the release as "namespace/name version".
+#[derive(Fail, Debug, PartialEq, Eq)]
+pub enum TrustError {
+    #[fail(display = "{} is not signed, but the trust policy requires it", _0)]
+    Unsigned(String),
+    #[fail(display = "{} is signed with untrusted key {}", _0, _1)]
+    UntrustedKey(String, String),
+}
+
+impl TrustPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.namespaces.is_empty() && self.owners.is_empty()
+    }
+
+    /// The rules that apply to a release, given the user ID of its publisher,
+    /// as lists of keys. A release must be signed by a key from every list.
+    fn rules(&self, name: &PackageName, publisher: Option<&str>) -> Vec<&Vec<String>> {
+        let mut rules = vec![];
+        if let Some(keys) = self.namespaces.get(&name.namespace) {
+            rules.push(keys);
+        }
+        if let Some(keys) = publisher.and_then(|publisher| self.owners.get(publisher)) {
+            rules.push(keys);
+        }
+        rules
+    }
+
+    pub fn covers(&self, name: &PackageName, publisher: Option<&str>) -> bool {
+        !self.rules(name, publisher).is_empty()
+    }
+
+    /// Check that a release is signed with a trusted key, if the policy
+    /// covers it. This doesn't check the signature itself, which needs the
+    /// release archive; see `SignedRelease::signature.verify`.
+    pub fn check(
+        &self,
+        name: &PackageName,
+        version: &Version,
+        publisher: Option<&str>,
+        signed: Option<&SignedRelease>,
+    ) -> Result<(), TrustError> {
+        let rules = self.rules(name, publisher);
+        if rules.is_empty() {
+            return Ok(());
+        }
+        let release = format!("{} {}", name, version);
+        let public_key = match signed {
+            Some(signed) => &signed.signature.public_key,
+            None => return Err(TrustError::Unsigned(release)),
+        };
+        if rules.iter().all(|keys| keys.contains(public_key)) {
+            Ok(())
+        } else {
+            Err(TrustError::UntrustedKey(release, public_key.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::signing::ReleaseSignature;
+    use pm_lib::test_helpers::{pkg, ver};
+
+    fn signed(publisher: &str, public_key: &str) -> SignedRelease {
+        SignedRelease {
+            publisher: publisher.to_string(),
+            signature: ReleaseSignature {
+                public_key: public_key.to_string(),
+                signature: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn check_trust_policy() {
+        let policy: TrustPolicy = toml::from_str(
+            r#"
+[namespaces]
+test = ["key1", "key2"]
+
+[owners]
+"github:alice" = ["key2"]
+"#,
+        )
+        .unwrap();
+        let check = |name: &str, signed: Option<&SignedRelease>| {
+            let publisher = signed.map(|signed| signed.publisher.as_str());
+            policy.check(&pkg(name), &ver("1.0.0"), publisher, signed)
+        };
+
+        assert_eq!(check("a", Some(&signed("github:bob", "key1"))), Ok(()));
+        assert_eq!(
+            check("a", Some(&signed("github:bob", "key3"))),
+            Err(TrustError::UntrustedKey(
+                "test/a 1.0.0".to_string(),
+                "key3".to_string()
+            ))
+        );
+        assert_eq!(
+            check("a", None),
+            Err(TrustError::Unsigned("test/a 1.0.0".to_string()))
+        );
+        // Both the namespace and the owner rule apply.
+        assert_eq!(check("a", Some(&signed("github:alice", "key2"))), Ok(()));
+        assert!(check("a", Some(&signed("github:alice", "key1"))).is_err());
+
+        assert_eq!(check("other/a", None), Ok(()));
+        assert_eq!(
+            check("other/a", Some(&signed("github:bob", "key3"))),
+            Ok(())
+        );
+        assert!(check("other/a", Some(&signed("github:alice", "key3"))).is_err());
+        // An owner's releases have to be signed, so that someone with their
+        // registry login can't publish unsigned ones.
+        assert_eq!(
+            policy.check(&pkg("other/a"), &ver("1.0.0"), Some("github:alice"), None),
+            Err(TrustError::Unsigned("other/a 1.0.0".to_string()))
+        );
+        assert!(policy.covers(&pkg("other/a"), Some("github:alice")));
+        assert!(!policy.covers(&pkg("other/a"), Some("github:bob")));
+    }
+}
@@ -0,0 +1,185 @@
+// End to end tests: `pm` talking to a real registry. They need a registry
+// server binary, so they only run when asked for; see `harness/mod.rs`.
+
+#![cfg(unix)]
+
+mod harness;
+
+use std::fs;
+
+use pm_lib::index::RegistryIndex;
+use pm_lib::package_info::{PackageInfo, ReleaseInfo};
+use pm_lib::search::SearchResults;
+use pm_lib::test_helpers::{pkg, range, ver};
+
+use crate::harness::{wait_for, Registry};
+
+fn package_manifest(name: &str, description: &str, dependencies: &str) -> String {
+    format!(
+        "dependencies {{
+{}}}
+package {{
+  name \"e2e/{}\"
+  version \"1.0.0\"
+  description \"{}\"
+  license \"MIT\"
+  keywords [\"strings\"]
+  files {{
+    add_any \"src\"
+  }}
+}}
+",
+        dependencies, name, description
+    )
+}
+
+#[test]
+#[ignore] // needs a registry server binary
+fn publish_search_and_install() {
+    let registry = Registry::start();
+
+    let alice = registry.client();
+    alice.login("alice");
+    let config = fs::read_to_string(alice.home().join(".package-manager/config.toml")).unwrap();
+    assert!(config.contains("token"), "{}", config);
+    alice.pm(alice.home(), &["key", "generate"]);
+    alice.pm(alice.home(), &["key", "register"]);
+    let alice_key = alice.pm(alice.home(), &["key", "show"]).trim().to_string();
+
+    let left_pad = alice.project(
+        "left-pad",
+        &package_manifest("left-pad", "Pads strings on the left", ""),
+        &[("src/left-pad.txt", "left")],
+    );
+    let output = alice.pm(&left_pad, &["publish"]);
+    assert!(output.contains("Package e2e/left-pad version 1.0.0 has been published"));
+    let pad_both = alice.project(
+        "pad-both",
+        &package_manifest("pad-both", "Pads strings on both sides", "  e2e/left-pad ^1.0\n"),
+        &[("src/pad-both.txt", "both")],
+    );
+    alice.pm(&pad_both, &["publish"]);
+
+    // The archive the registry serves is the one `pm pack` builds.
+    alice.pm(&left_pad, &["pack", "--output", "left-pad.tar.br"]);
+    let mut response = registry.get("files/tar-br/e2e/left-pad/1.0.0");
+    assert!(response.status().is_success(), "{:?}", response);
+    let mut served = Vec::new();
+    response.copy_to(&mut served).unwrap();
+    assert_eq!(served, fs::read(left_pad.join("left-pad.tar.br")).unwrap());
+
+    let index: RegistryIndex = registry.get("index").json().unwrap();
+    assert_eq!(index.packages[&pkg("e2e/left-pad")][&ver("1.0.0")].len(), 0);
+    assert_eq!(
+        index.packages[&pkg("e2e/pad-both")][&ver("1.0.0")][&pkg("e2e/left-pad")],
+        range("^1.0")
+    );
+    let signed = &index.signatures[&pkg("e2e/left-pad")][&ver("1.0.0")];
+    assert_eq!(signed.publisher, "test:alice");
+    assert_eq!(signed.signature.public_key, alice_key);
+    assert_eq!(
+        index.publishers[&pkg("e2e/pad-both")][&ver("1.0.0")],
+        "test:alice"
+    );
+
+    let output = alice.pm(alice.home(), &["search", "--namespace", "e2e", "strings"]);
+    assert!(output.contains("e2e/left-pad"), "{}", output);
+    assert!(output.contains("e2e/pad-both"), "{}", output);
+    let output = alice.pm(alice.home(), &["search", "sides"]);
+    assert!(!output.contains("left-pad"), "{}", output);
+    let output = alice.pm(
+        alice.home(),
+        &["search", "--json", "--limit", "1", "keyword:strings", "owner:alice"],
+    );
+    let results: SearchResults = serde_json::from_str(&output).unwrap();
+    assert_eq!(results.total, 2);
+    assert_eq!(results.results[0].package, pkg("e2e/left-pad"));
+    assert_eq!(results.results[0].version, ver("1.0.0"));
+    let output = alice.pm(alice.home(), &["search", "owner:bob"]);
+    assert!(output.contains("No results found!"), "{}", output);
+
+    let output = alice.pm(alice.home(), &["info", "e2e/pad-both"]);
+    assert!(output.contains("Pads strings on both sides"), "{}", output);
+    assert!(output.contains("e2e/left-pad ^1.0"), "{}", output);
+    assert!(output.contains("alice <alice@example.com> (test:alice)"), "{}", output);
+    let output = alice.pm(
+        alice.home(),
+        &["info", "--format", "json", "e2e/left-pad", "1.0.0"],
+    );
+    let release: ReleaseInfo = serde_json::from_str(&output).unwrap();
+    assert_eq!(release.keywords, vec!["strings".to_string()]);
+    assert_eq!(release.signature.unwrap().public_key, alice_key);
+    let output = alice.pm_fails(alice.home(), &["info", "e2e/right-pad"]);
+    assert!(output.contains("No such package: e2e/right-pad"), "{}", output);
+
+    let output = alice.pm(alice.home(), &["dependents", "e2e/left-pad"]);
+    assert!(output.contains("e2e/pad-both 1.0.0  requires ^1.0"), "{}", output);
+    let output = alice.pm(
+        alice.home(),
+        &["dependents", "--range", "^2.0", "e2e/left-pad"],
+    );
+    assert!(output.contains("No packages depend on e2e/left-pad."), "{}", output);
+
+    // Installing doesn't need an account, but Bob only trusts releases that
+    // Alice has signed.
+    let bob = registry.client();
+    fs::create_dir_all(bob.home().join(".package-manager")).unwrap();
+    fs::write(
+        bob.home().join(".package-manager/config.toml"),
+        format!("[auth]\n\n[trust.owners]\n\"test:alice\" = [\"{}\"]\n", alice_key),
+    )
+    .unwrap();
+    let app = bob.project("app", "dependencies {\n  e2e/pad-both ^1.0\n}\n", &[]);
+    let output = bob.pm(&app, &["install"]);
+    assert!(output.contains("Verified signatures of 2 package(s)."), "{}", output);
+    let lockfile = fs::read_to_string(app.join("deps.lock")).unwrap();
+    assert!(lockfile.contains("e2e/left-pad"), "{}", lockfile);
+    assert!(lockfile.contains("e2e/pad-both"), "{}", lockfile);
+    let output = bob.pm(&app, &["verify-log"]);
+    assert!(output.contains("Verified 2 release(s)"), "{}", output);
+
+    // Alice downloaded left-pad above. Bob's install downloaded both packages
+    // to check their signatures, and so did verify-log to check their
+    // digests. Downloads are added up in the background.
+    let results: SearchResults = wait_for("the download counts", || {
+        let results: SearchResults = registry.get("search?q=strings&ns=e2e").json().unwrap();
+        let downloads: Vec<u64> = results.results.iter().map(|r| r.downloads).collect();
+        if downloads == [3, 2] {
+            Some(results)
+        } else {
+            None
+        }
+    });
+    // Both packages are as relevant, so the more downloaded comes first.
+    assert_eq!(results.results[0].package, pkg("e2e/left-pad"));
+    assert_eq!(results.results[1].package, pkg("e2e/pad-both"));
+    let left_pad: PackageInfo = registry.get("api/packages/e2e/left-pad").json().unwrap();
+    assert_eq!(left_pad.downloads, 3);
+    let latest = left_pad.latest.unwrap();
+    assert_eq!(latest.downloads, 3);
+    let daily: u64 = latest.daily_downloads.iter().map(|day| day.downloads).sum();
+    assert_eq!(daily, 3);
+}
+
+#[test]
+#[ignore] // needs a registry server binary
+fn only_owners_can_publish() {
+    let registry = Registry::start();
+    let alice = registry.client();
+    alice.login("alice");
+    let bob = registry.client();
+    bob.login("bob");
+
+    let manifest = package_manifest("left-pad", "Pads strings on the left", "");
+    let files = [("src/left-pad.txt", "left")];
+    alice.pm(&alice.project("left-pad", &manifest, &files), &["publish"]);
+
+    let output = alice.pm_fails(&alice.project("left-pad", &manifest, &files), &["publish"]);
+    assert!(output.contains("This release already exists"), "{}", output);
+
+    let manifest = manifest.replace("1.0.0", "1.1.0");
+    let output = bob.pm_fails(&bob.project("left-pad", &manifest, &files), &["publish"]);
+    assert!(output.contains("is not an owner of"), "{}", output);
+    let index: RegistryIndex = registry.get("index").json().unwrap();
+    assert_eq!(index.packages[&pkg("e2e/left-pad")].len(), 1);
+}
@@ -0,0 +1,271 @@
+// Runs the `pm` binary against a real registry, for end to end tests.
+//
+// The registry is the `pm_server` binary built with the `sqlite` feature, so
+// every test gets its own database and artifact directory in a temporary
+// directory, and logs in through the server's test authentication instead of
+// an identity provider. Build the server before running the tests:
+//
+//     cargo build -p pm_server --no-default-features --features sqlite
+//     cargo test -p pm --test e2e -- --ignored
+//
+// Set `PM_SERVER_BIN` to use a server binary from somewhere else.
+
+use std::env;
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+/// How long to wait for the registry to start, for `pm login` to open its
+/// browser, or for anything else `wait_for` is given.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn wait_for<A, F: FnMut() -> Option<A>>(what: &str, mut f: F) -> A {
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f() {
+            return value;
+        }
+        if start.elapsed() > TIMEOUT {
+            panic!("Timed out waiting for {}", what);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// A child process, killed when dropped.
+struct Process(Child);
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// A temporary directory, deleted when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> TempDir {
+        let path = env::temp_dir().join(format!(
+            "pm-e2e-{}-{}-{}",
+            prefix,
+            process::id(),
+            rand::random::<u32>()
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn server_binary() -> PathBuf {
+    let path = match env::var_os("PM_SERVER_BIN") {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/debug/pm_server"),
+    };
+    if !path.exists() {
+        panic!(
+            "No registry server at {}; build it with \
+             `cargo build -p pm_server --no-default-features --features sqlite`",
+            path.display()
+        );
+    }
+    path
+}
+
+/// A registry server running on a free port, killed when dropped.
+pub struct Registry {
+    pub url: String,
+    server: Process,
+    dir: TempDir,
+}
+
+impl Registry {
+    pub fn start() -> Registry {
+        let dir = TempDir::new("registry");
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        let log = fs::File::create(dir.path().join("server.log")).unwrap();
+        let server = Command::new(server_binary())
+            .current_dir(dir.path())
+            .env("DATABASE_URL", dir.path().join("registry.sqlite"))
+            .env("ARTIFACT_STORAGE", "local")
+            .env("ARTIFACT_DIR", dir.path().join("artifacts"))
+            .env("ADDRESS", "127.0.0.1")
+            .env("PORT", port.to_string())
+            .env("ENABLE_TEST_AUTH", "1")
+            .env("DOWNLOAD_FLUSH_INTERVAL", "1")
+            .stdout(log.try_clone().unwrap())
+            .stderr(log)
+            .spawn()
+            .unwrap();
+        let mut registry = Registry {
+            url: format!("http://127.0.0.1:{}", port),
+            server: Process(server),
+            dir,
+        };
+        wait_for("the registry to start", || {
+            if let Some(status) = registry.server.0.try_wait().unwrap() {
+                panic!("The registry exited with {}:\n{}", status, registry.log());
+            }
+            TcpStream::connect(("127.0.0.1", port)).ok()
+        });
+        registry
+    }
+
+    /// Everything the server has logged so far.
+    pub fn log(&self) -> String {
+        fs::read_to_string(self.dir.path().join("server.log")).unwrap_or_default()
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::Response {
+        reqwest::get(&format!("{}/{}", self.url, path)).unwrap()
+    }
+
+    /// A new user of `pm`, with a home directory of their own.
+    pub fn client(&self) -> Client {
+        let home = TempDir::new("home");
+        let browser = home.path().join("browser");
+        let url_file = home.path().join("login-url");
+        fs::write(
+            &browser,
+            format!(
+                "#!/bin/sh\necho \"$1\" > {0}.partial && mv {0}.partial {0}\n",
+                url_file.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&browser, fs::Permissions::from_mode(0o755)).unwrap();
+        Client {
+            home,
+            registry_url: self.url.clone(),
+        }
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            eprintln!("Registry log:\n{}", self.log());
+        }
+    }
+}
+
+/// Runs `pm` as one user, whose browser writes the URL it's asked to open to
+/// `login-url` in their home directory.
+pub struct Client {
+    home: TempDir,
+    registry_url: String,
+}
+
+impl Client {
+    pub fn home(&self) -> &Path {
+        self.home.path()
+    }
+
+    pub fn command(&self, dir: &Path, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_pm"));
+        command
+            .current_dir(dir)
+            .args(args)
+            .env("HOME", self.home())
+            .env("PM_REGISTRY_URL", &self.registry_url)
+            .env("BROWSER", self.home().join("browser"))
+            .env_remove("RUST_BACKTRACE");
+        command
+    }
+
+    fn run(&self, dir: &Path, args: &[&str], succeed: bool) -> String {
+        let output = self.command(dir, args).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if output.status.success() != succeed {
+            panic!(
+                "`pm {}` exited with {}:\n{}{}",
+                args.join(" "),
+                output.status,
+                stdout,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        stdout
+    }
+
+    /// Run `pm` in `dir`, expecting it to succeed, and return its output.
+    pub fn pm(&self, dir: &Path, args: &[&str]) -> String {
+        self.run(dir, args, true)
+    }
+
+    /// Run `pm` in `dir`, expecting it to fail, and return its output.
+    pub fn pm_fails(&self, dir: &Path, args: &[&str]) -> String {
+        self.run(dir, args, false)
+    }
+
+    /// Log in as the test user `name`, doing what the browser would: open the
+    /// login page, which registers the login with the registry, and return
+    /// from the identity provider to the registry, which sends it on to `pm`.
+    pub fn login(&self, name: &str) {
+        let url_file = self.home().join("login-url");
+        let _ = fs::remove_file(&url_file);
+        let mut login = Process(
+            self.command(self.home(), &["login"])
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap(),
+        );
+        let login_url = wait_for("pm login to open a browser", || {
+            fs::read_to_string(&url_file).ok()
+        });
+        let login_url = Url::parse(login_url.trim()).unwrap();
+        let (_, state) = login_url
+            .query_pairs()
+            .find(|(key, _)| key == "token")
+            .expect("the login URL has a token");
+
+        let http = reqwest::Client::new();
+        let response = http.get(login_url.as_str()).send().unwrap();
+        assert!(response.status().is_success(), "{:?}", response);
+        let mut callback = Url::parse(&format!("{}/test/login", self.registry_url)).unwrap();
+        callback
+            .query_pairs_mut()
+            .append_pair("state", &state)
+            .append_pair("user", name);
+        let response = http.get(callback.as_str()).send().unwrap();
+        assert!(response.status().is_success(), "{:?}", response);
+
+        assert!(login.0.wait().unwrap().success(), "pm login failed");
+    }
+
+    /// Create a project directory containing a `deps` manifest and `files`,
+    /// given as (path, contents) pairs. The project is a Git repository, since
+    /// `pm publish` asks Git which files there are.
+    pub fn project(&self, name: &str, manifest: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = self.home().join("projects").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        git2::Repository::init(&dir).unwrap();
+        fs::write(dir.join("deps"), manifest).unwrap();
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+}
@@ -26,7 +26,12 @@ fn resolve_something_real(b: &mut Bencher) {
 
     b.iter(|| {
         assert_eq!(
-            solve(&reg, &problem),
+            solve(
+                &reg,
+                &problem,
+                ResolutionStrategy::Highest,
+                PrereleasePolicy::default(),
+            ),
             Ok(solution! {
                 base64 => "0.6.0",
                 byteorder => "1.1.0",
@@ -84,7 +89,12 @@ fn deep_conflict(b: &mut Bencher) {
 
     b.iter(|| {
         assert_eq!(
-            solve(&reg, &problem),
+            solve(
+                &reg,
+                &problem,
+                ResolutionStrategy::Highest,
+                PrereleasePolicy::default(),
+            ),
             Err(Error::Conflict(Box::new(Conflict {
                 package: Arc::new(pkg("hyper")),
                 existing: range("^0.11"),
@@ -0,0 +1,402 @@
+// Validation of release archives (Brotli compressed TAR files). The registry
+// runs it on upload, and the client runs it again before extracting anything,
+// so that neither has to trust the other.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use brotli::Decompressor;
+use tar::{Archive, EntryType};
+
+/// Limits on the contents of an archive. Both the registry and the client let
+/// users override these.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ArchiveLimits {
+    /// The maximum number of entries, including directories.
+    pub max_entries: usize,
+    /// The maximum total size of the archive after decompression, in bytes.
+    /// This guards against decompression bombs.
+    pub max_unpacked_size: u64,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        ArchiveLimits {
+            max_entries: 10_000,
+            max_unpacked_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum ArchiveError {
+    #[fail(display = "not a Brotli compressed TAR archive: {}", _0)]
+    Malformed(String),
+    #[fail(display = "archive is larger than {} bytes when decompressed", _0)]
+    TooLarge(u64),
+    #[fail(display = "archive has more than {} entries", _0)]
+    TooManyEntries(usize),
+    #[fail(display = "path is not inside the package: {}", _0)]
+    UnsafePath(String),
+    #[fail(display = "link points outside the package: {} -> {}", _0, _1)]
+    UnsafeLink(String, String),
+    #[fail(display = "path goes through a symlink in the package: {}", _0)]
+    ThroughSymlink(String),
+    #[fail(display = "device files are not allowed: {}", _0)]
+    DeviceFile(String),
+    #[fail(display = "unsupported entry type {:?}: {}", _0, _1)]
+    UnsupportedEntryType(EntryType, String),
+    #[fail(display = "duplicate entry: {}", _0)]
+    DuplicateEntry(String),
+}
+
+/// A reader that fails once more than `remaining` bytes have been read. The
+/// TAR reader wraps our errors, so we flag `exceeded` to tell them apart.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    exceeded: Rc<Cell<bool>>,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n as u64 > self.remaining {
+            self.exceeded.set(true);
+            return Err(io::Error::other("size limit exceeded"));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Resolve `.` and `..` in a relative path, failing if the path is absolute
+/// or leaves the directory it's relative to.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+/// Whether any directory on the way to `path` is one of `symlinks`. Such a
+/// path can leave the package even if `normalize` says it doesn't, since `..`
+/// after a symlink goes up from wherever the symlink points.
+fn goes_through_symlink(path: &Path, symlinks: &HashSet<PathBuf>) -> bool {
+    let components: Vec<Component> = path.components().collect();
+    let mut prefix = PathBuf::new();
+    for component in &components[..components.len().saturating_sub(1)] {
+        prefix.push(component);
+        match normalize(&prefix) {
+            Some(ref normalized) if symlinks.contains(normalized) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// Check every entry in a Brotli compressed TAR archive against `limits`,
+/// and return the paths of all entries. No entry, and no link target, may go
+/// through a symlink in the archive, wherever the symlink appears in it.
+pub fn validate_archive<R: Read>(
+    tar_br: R,
+    limits: &ArchiveLimits,
+) -> Result<Vec<PathBuf>, ArchiveError> {
+    let exceeded = Rc::new(Cell::new(false));
+    let reader = LimitedReader {
+        inner: Decompressor::new(tar_br, 4096),
+        remaining: limits.max_unpacked_size,
+        exceeded: exceeded.clone(),
+    };
+    let malformed = |error: io::Error| {
+        if exceeded.get() {
+            ArchiveError::TooLarge(limits.max_unpacked_size)
+        } else {
+            ArchiveError::Malformed(error.to_string())
+        }
+    };
+
+    let mut archive = Archive::new(reader);
+    let mut paths = vec![];
+    let mut seen = HashSet::new();
+    let mut symlinks = HashSet::new();
+    // Each entry's path, and the paths its link target goes through.
+    let mut checks = vec![];
+    for entry in archive.entries().map_err(malformed)? {
+        let entry = entry.map_err(malformed)?;
+        if paths.len() == limits.max_entries {
+            return Err(ArchiveError::TooManyEntries(limits.max_entries));
+        }
+        if entry.header().size().map_err(malformed)? > limits.max_unpacked_size {
+            return Err(ArchiveError::TooLarge(limits.max_unpacked_size));
+        }
+
+        let raw_path = entry.path().map_err(malformed)?.to_path_buf();
+        // We don't allow `..` at all, even where it would stay inside the
+        // package, so that every entry has exactly one name.
+        let path = match normalize(&raw_path) {
+            Some(ref path)
+                if !path.as_os_str().is_empty()
+                    && !raw_path
+                        .components()
+                        .any(|component| component == Component::ParentDir) =>
+            {
+                path.clone()
+            }
+            _ => return Err(ArchiveError::UnsafePath(display(&raw_path))),
+        };
+
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous | EntryType::Directory => {}
+            EntryType::Symlink => {
+                let target = entry.link_name().map_err(malformed)?.unwrap_or_default();
+                // Symlinks are relative to the directory containing them.
+                let resolved = path.parent().unwrap_or_else(|| Path::new("")).join(&target);
+                if target.as_os_str().is_empty() || normalize(&resolved).is_none() {
+                    return Err(ArchiveError::UnsafeLink(display(&path), display(&target)));
+                }
+                symlinks.insert(path.clone());
+                checks.push((path.clone(), resolved));
+            }
+            EntryType::Link => {
+                let target = entry.link_name().map_err(malformed)?.unwrap_or_default();
+                // Hard links are relative to the archive root.
+                match normalize(&target) {
+                    Some(ref normalized) if !normalized.as_os_str().is_empty() => {
+                        checks.push((path.clone(), target.to_path_buf()));
+                    }
+                    _ => return Err(ArchiveError::UnsafeLink(display(&path), display(&target))),
+                }
+            }
+            EntryType::Char | EntryType::Block | EntryType::Fifo => {
+                return Err(ArchiveError::DeviceFile(display(&path)));
+            }
+            entry_type => {
+                return Err(ArchiveError::UnsupportedEntryType(entry_type, display(&path)));
+            }
+        }
+
+        if !seen.insert(path.clone()) {
+            return Err(ArchiveError::DuplicateEntry(display(&path)));
+        }
+        checks.push((path.clone(), path.clone()));
+        paths.push(path);
+    }
+    for (path, checked) in &checks {
+        if goes_through_symlink(checked, &symlinks) {
+            return Err(ArchiveError::ThroughSymlink(display(path)));
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tar::{Builder, Header};
+
+    fn header(entry_type: EntryType, size: u64) -> Header {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mode(0o644);
+        header
+    }
+
+    fn compress(tar: &[u8]) -> Vec<u8> {
+        let mut tar_br = vec![];
+        brotli::BrotliCompress(
+            &mut &tar[..],
+            &mut tar_br,
+            &brotli::enc::BrotliEncoderInitParams(),
+        )
+        .unwrap();
+        tar_br
+    }
+
+    // Write raw headers, since tar::Builder refuses some of the unsafe paths
+    // we want to test.
+    fn archive(entries: &[(&str, EntryType, &str)]) -> Vec<u8> {
+        let mut builder = Builder::new(vec![]);
+        for &(path, entry_type, contents) in entries {
+            let mut header = header(entry_type, contents.len() as u64);
+            {
+                let name = &mut header.as_old_mut().name;
+                name[..path.len()].copy_from_slice(path.as_bytes());
+            }
+            if entry_type == EntryType::Symlink || entry_type == EntryType::Link {
+                header.set_size(0);
+                header.set_link_name(contents).unwrap();
+                header.set_cksum();
+                builder.append(&header, io::empty()).unwrap();
+            } else {
+                header.set_cksum();
+                builder.append(&header, contents.as_bytes()).unwrap();
+            }
+        }
+        compress(&builder.into_inner().unwrap())
+    }
+
+    fn validate(entries: &[(&str, EntryType, &str)]) -> Result<Vec<PathBuf>, ArchiveError> {
+        validate_archive(&archive(entries)[..], &ArchiveLimits::default())
+    }
+
+    #[test]
+    fn accept_valid_archive() {
+        assert_eq!(
+            validate(&[
+                ("lib", EntryType::Directory, ""),
+                ("lib/a.txt", EntryType::Regular, "hello"),
+                ("lib/b.txt", EntryType::Symlink, "a.txt"),
+                ("lib/c/d.txt", EntryType::Symlink, "../a.txt"),
+                ("lib/e.txt", EntryType::Link, "lib/a.txt"),
+            ])
+            .unwrap(),
+            vec![
+                PathBuf::from("lib"),
+                PathBuf::from("lib/a.txt"),
+                PathBuf::from("lib/b.txt"),
+                PathBuf::from("lib/c/d.txt"),
+                PathBuf::from("lib/e.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_unsafe_entries() {
+        let error = |entries: &[(&str, EntryType, &str)]| validate(entries).unwrap_err();
+        assert!(matches!(
+            error(&[("../evil", EntryType::Regular, "")]),
+            ArchiveError::UnsafePath(_)
+        ));
+        assert!(matches!(
+            error(&[("lib/../../evil", EntryType::Regular, "")]),
+            ArchiveError::UnsafePath(_)
+        ));
+        assert!(matches!(
+            error(&[("/etc/passwd", EntryType::Regular, "")]),
+            ArchiveError::UnsafePath(_)
+        ));
+        assert!(matches!(
+            error(&[("lib/a", EntryType::Symlink, "../../etc/passwd")]),
+            ArchiveError::UnsafeLink(..)
+        ));
+        assert!(matches!(
+            error(&[("lib/a", EntryType::Symlink, "/etc/passwd")]),
+            ArchiveError::UnsafeLink(..)
+        ));
+        assert!(matches!(
+            error(&[("lib/a", EntryType::Link, "../etc/passwd")]),
+            ArchiveError::UnsafeLink(..)
+        ));
+        // Each link stays inside the package, but together they don't.
+        assert!(matches!(
+            error(&[
+                ("a/b/l", EntryType::Symlink, "../.."),
+                ("a/b/l/m", EntryType::Symlink, ".."),
+            ]),
+            ArchiveError::ThroughSymlink(_)
+        ));
+        assert!(matches!(
+            error(&[
+                ("a/b/l", EntryType::Symlink, "../.."),
+                ("a/b/l/evil", EntryType::Regular, "evil"),
+            ]),
+            ArchiveError::ThroughSymlink(_)
+        ));
+        // However the entries are ordered.
+        assert!(matches!(
+            error(&[
+                ("a/b/l/evil", EntryType::Regular, "evil"),
+                ("a/b/l", EntryType::Symlink, "../.."),
+            ]),
+            ArchiveError::ThroughSymlink(_)
+        ));
+        assert!(matches!(
+            error(&[
+                ("a/b/l", EntryType::Symlink, "../.."),
+                ("x", EntryType::Symlink, "a/b/l/../.."),
+            ]),
+            ArchiveError::ThroughSymlink(_)
+        ));
+        assert!(matches!(
+            error(&[
+                ("a/b/l", EntryType::Symlink, "../.."),
+                ("x", EntryType::Link, "a/b/l/m"),
+            ]),
+            ArchiveError::ThroughSymlink(_)
+        ));
+        assert!(matches!(
+            error(&[("dev", EntryType::Char, "")]),
+            ArchiveError::DeviceFile(_)
+        ));
+        assert!(matches!(
+            error(&[
+                ("a.txt", EntryType::Regular, "1"),
+                ("./a.txt", EntryType::Regular, "2"),
+            ]),
+            ArchiveError::DuplicateEntry(_)
+        ));
+        assert!(matches!(
+            validate_archive(&b"not brotli"[..], &ArchiveLimits::default()).unwrap_err(),
+            ArchiveError::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn enforce_limits() {
+        let tar_br = archive(&[
+            ("a.txt", EntryType::Regular, "hello"),
+            ("b.txt", EntryType::Regular, "world"),
+        ]);
+        assert!(matches!(
+            validate_archive(
+                &tar_br[..],
+                &ArchiveLimits {
+                    max_entries: 1,
+                    ..ArchiveLimits::default()
+                }
+            )
+            .unwrap_err(),
+            ArchiveError::TooManyEntries(1)
+        ));
+        // A 1 MiB file of zeros compresses to a few bytes.
+        let mut builder = Builder::new(vec![]);
+        let mut header = header(EntryType::Regular, 1024 * 1024);
+        header.set_path("zeros").unwrap();
+        header.set_cksum();
+        builder.append(&header, io::repeat(0).take(1024 * 1024)).unwrap();
+        let bomb = compress(&builder.into_inner().unwrap());
+        assert!(bomb.len() < 1024);
+        assert!(matches!(
+            validate_archive(
+                &bomb[..],
+                &ArchiveLimits {
+                    max_unpacked_size: 64 * 1024,
+                    ..ArchiveLimits::default()
+                }
+            )
+            .unwrap_err(),
+            ArchiveError::TooLarge(_)
+        ));
+        assert!(validate_archive(&bomb[..], &ArchiveLimits::default()).is_ok());
+    }
+}
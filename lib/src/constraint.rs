@@ -1,8 +1,8 @@
 use nom::IResult::Done;
-use self::VersionConstraint::{Exact, Range, Caret};
+use self::VersionConstraint::{All, Any, Caret, Exact, Exclude, Range, Tilde, Wildcard};
 use serde::de::Error;
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
-use crate::version::{Version, version, caret_bump};
+use crate::version::{Version, version, nat, caret_bump, tilde_bump};
 use std::cmp::Ordering;
 use std::fmt;
 use nom;
@@ -12,6 +12,15 @@ pub enum VersionConstraint {
     Exact(Version),
     Range(Option<Version>, Option<Version>),
     Caret(Version),
+    Tilde(Version),
+    /// `1.2.x`, holding the fields before the wildcard.
+    Wildcard(Vec<u64>),
+    /// `!= 1.2.3`
+    Exclude(Version),
+    /// Comma-separated constraints that must all match.
+    All(Vec<VersionConstraint>),
+    /// `||`-separated constraints of which at least one must match.
+    Any(Vec<VersionConstraint>),
 }
 
 impl Serialize for VersionConstraint {
@@ -59,6 +68,18 @@ impl VersionConstraint {
             Range(None, Some(ref v)) => format!("< {}", v),
             Range(Some(ref v1), Some(ref v2)) => format!(">= {} < {}", v1, v2),
             Caret(ref v) => format!("^{}", v),
+            Tilde(ref v) => format!("~{}", v),
+            Wildcard(ref fields) => {
+                let mut s = String::new();
+                for field in fields {
+                    s.push_str(&format!("{}.", field));
+                }
+                s.push('x');
+                s
+            }
+            Exclude(ref v) => format!("!= {}", v),
+            All(ref constraints) => join_constraints(constraints, ", "),
+            Any(ref constraints) => join_constraints(constraints, " || "),
         }
     }
 
@@ -66,11 +87,57 @@ impl VersionConstraint {
         match self {
             Exact(ref v) => version == v,
             Caret(ref v) => contained_in_range(&version, Some(&v), Some(&caret_bump(&v))),
+            Tilde(ref v) => contained_in_range(version, Some(v), Some(&tilde_bump(v))),
+            Wildcard(ref fields) => {
+                let (min, max) = wildcard_bounds(fields);
+                contained_in_range(version, Some(&min), Some(&max))
+            }
             Range(ref v1, ref v2) => contained_in_range(&version, v1.as_ref(), v2.as_ref()),
+            Exclude(ref v) => version != v,
+            All(ref constraints) => constraints.iter().all(|c| c.contains(version)),
+            Any(ref constraints) => constraints.iter().any(|c| c.contains(version)),
+        }
+    }
+
+    /// Return true if the constraint names a pre-release version with the
+    /// same base version as `version`, as in `^1.2-beta` for `1.2-rc.1`.
+    /// Exclusions don't count.
+    pub fn names_prerelease_of(&self, version: &Version) -> bool {
+        let same_base = |v: &Version| {
+            v.has_pre() && v.normalized_fields() == version.normalized_fields()
+        };
+        match self {
+            Exact(ref v) | Caret(ref v) | Tilde(ref v) => same_base(v),
+            Range(ref v1, ref v2) => v1.iter().chain(v2.iter()).any(same_base),
+            Wildcard(_) | Exclude(_) => false,
+            All(ref constraints) | Any(ref constraints) => {
+                constraints.iter().any(|c| c.names_prerelease_of(version))
+            }
         }
     }
 }
 
+fn join_constraints(constraints: &[VersionConstraint], separator: &str) -> String {
+    constraints
+        .iter()
+        .map(VersionConstraint::as_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// `1.2.x` is equivalent to `>= 1.2 < 1.3`.
+fn wildcard_bounds(fields: &[u64]) -> (Version, Version) {
+    let mut max_fields = fields.to_vec();
+    if let Some(last) = max_fields.last_mut() {
+        // Same overflow shortcut as in caret_bump.
+        *last = last.saturating_add(1);
+    }
+    (
+        Version::new(fields.to_vec(), vec![], vec![]),
+        Version::new(max_fields, vec![], vec![]),
+    )
+}
+
 fn contained_in_range(version: &Version, min: Option<&Version>, max: Option<&Version>) -> bool {
     match (min, max) {
         (None, None) => true,
@@ -110,6 +177,12 @@ impl fmt::Debug for VersionConstraint {
 
 named!(exact_version_constraint<VersionConstraint>, map!(version, VersionConstraint::Exact));
 
+named!(equal_constraint<VersionConstraint>, ws!(do_parse!(
+    tag!(b"=") >>
+        v: version >>
+        (Exact(v))
+)));
+
 named!(min_constraint<VersionConstraint>, ws!(do_parse!(
     tag!(b">=") >>
         v: version >>
@@ -122,16 +195,8 @@ named!(max_constraint<VersionConstraint>, ws!(do_parse!(
         (Range(None, Some(v)))
 )));
 
-named!(closed_constraint<VersionConstraint>, ws!(do_parse!(
-    tag!(b">=") >>
-        v1: version >>
-        tag!(b"<") >>
-        v2: version >>
-        (Range(Some(v1), Some(v2)))
-)));
-
 named!(open_constraint<VersionConstraint>, ws!(do_parse!(
-    tag!(b"*") >>
+    one_of!("*xX") >>
         (Range(None, None))
 )));
 
@@ -141,23 +206,92 @@ named!(caret_constraint<VersionConstraint>, ws!(do_parse!(
         (Caret(v))
 )));
 
-named!(pub version_constraint_unchecked<VersionConstraint>,
-       alt_complete!(open_constraint
-                     | caret_constraint
-                     | closed_constraint
+named!(tilde_constraint<VersionConstraint>, ws!(do_parse!(
+    tag!(b"~") >>
+        v: version >>
+        (Tilde(v))
+)));
+
+named!(exclude_constraint<VersionConstraint>, ws!(do_parse!(
+    tag!(b"!=") >>
+        v: version >>
+        (Exclude(v))
+)));
+
+// Not wrapped in ws! as a whole, since we don't want to allow whitespace
+// inside `1.2.x`.
+named!(wildcard_fields<Vec<u64>>, do_parse!(
+    fields: many1!(terminated!(nat, char!('.'))) >>
+        one_of!("*xX") >>
+        // Allow `1.x.x`, which means the same as `1.x`.
+        many0!(complete!(preceded!(char!('.'), one_of!("*xX")))) >>
+        (fields)
+));
+
+named!(wildcard_constraint<VersionConstraint>, ws!(do_parse!(
+    fields: wildcard_fields >>
+        (Wildcard(fields))
+)));
+
+named!(single_constraint<VersionConstraint>,
+       alt_complete!(caret_constraint
+                     | tilde_constraint
+                     | exclude_constraint
                      | max_constraint
                      | min_constraint
+                     | equal_constraint
+                     | wildcard_constraint
+                     | open_constraint
                      | exact_version_constraint));
 
+// A list of constraints separated by commas or whitespace, e.g.
+// `>= 1.2, != 1.3.0`. `>= 1 < 2` remains a plain range.
+named!(all_constraint<VersionConstraint>, map!(
+    many1!(terminated!(single_constraint, opt!(complete!(ws!(char!(',')))))),
+    |mut constraints: Vec<VersionConstraint>| {
+        if constraints.len() == 1 {
+            constraints.remove(0)
+        } else {
+            match (&constraints[0], &constraints[1]) {
+                (Range(Some(ref v1), None), Range(None, Some(ref v2))) if constraints.len() == 2 => {
+                    Range(Some(v1.clone()), Some(v2.clone()))
+                }
+                _ => All(constraints),
+            }
+        }
+    }
+));
+
+named!(pub version_constraint_unchecked<VersionConstraint>, map!(
+    separated_nonempty_list!(ws!(tag!(b"||")), all_constraint),
+    |mut constraints: Vec<VersionConstraint>| {
+        if constraints.len() == 1 {
+            constraints.remove(0)
+        } else {
+            Any(constraints)
+        }
+    }
+));
+
+/// Return the error code for the first invalid constraint in `vc`, if any.
+fn check_constraint(vc: &VersionConstraint) -> Option<u32> {
+    match vc {
+        Range(Some(ref v1), Some(ref v2)) if v1.semver_cmp(v2) != Ordering::Less => Some(1),
+        Caret(ref v) if v.base_version_is_zero() => Some(2),
+        All(ref constraints) | Any(ref constraints) => {
+            constraints.iter().filter_map(check_constraint).next()
+        }
+        _ => None,
+    }
+}
+
 fn version_constraint(input: &[u8]) -> nom::IResult<&[u8], VersionConstraint> {
     match version_constraint_unchecked(input) {
         Done(i, _) if !i.is_empty() => nom::IResult::Error(nom::ErrorKind::Eof),
-        Done(_, Range(Some(ref v1), Some(ref v2))) if v1.semver_cmp(v2) != Ordering::Less => {
-            nom::IResult::Error(nom::ErrorKind::Custom(1))
-        }
-        Done(_, Caret(ref v)) if v.base_version_is_zero() => {
-            nom::IResult::Error(nom::ErrorKind::Custom(2))
-        }
+        Done(i, vc) => match check_constraint(&vc) {
+            Some(code) => nom::IResult::Error(nom::ErrorKind::Custom(code)),
+            None => Done(i, vc),
+        },
         r => r,
     }
 }
@@ -224,6 +358,60 @@ mod test {
                    nom::IResult::Error(nom::ErrorKind::Custom(2)));
     }
 
+    #[test]
+    fn parse_tilde_constraint() {
+        assert_eq!(version_constraint(b"~1.2"), Done(&b""[..], Tilde(ver!(1, 2))));
+        assert_eq!(version_constraint(b" ~ 1.2.3 "), Done(&b""[..], Tilde(ver!(1, 2, 3))));
+    }
+
+    #[test]
+    fn parse_wildcard_constraint() {
+        assert_eq!(version_constraint(b"1.x"), Done(&b""[..], Wildcard(vec![1])));
+        assert_eq!(version_constraint(b"1.2.X"), Done(&b""[..], Wildcard(vec![1, 2])));
+        assert_eq!(version_constraint(b"1.2.*"), Done(&b""[..], Wildcard(vec![1, 2])));
+        assert_eq!(version_constraint(b"1.x.x"), Done(&b""[..], Wildcard(vec![1])));
+        assert_eq!(version_constraint(b"x"), Done(&b""[..], Range(None, None)));
+        assert_eq!(version_constraint(b"1. x"), nom::IResult::Error(nom::ErrorKind::Eof));
+    }
+
+    #[test]
+    fn parse_exclude_constraint() {
+        assert_eq!(version_constraint(b"!= 1.3.0"), Done(&b""[..], Exclude(ver!(1, 3, 0))));
+        assert_eq!(version_constraint(b"=1.3.0"), Done(&b""[..], Exact(ver!(1, 3, 0))));
+    }
+
+    #[test]
+    fn parse_compound_constraint() {
+        assert_eq!(version_constraint(b"^1.0 || ^2.0"),
+                   Done(&b""[..], Any(vec![Caret(ver!(1, 0)), Caret(ver!(2, 0))])));
+        assert_eq!(version_constraint(b">= 1.2, != 1.3.0"),
+                   Done(&b""[..], All(vec![Range(Some(ver!(1, 2)), None), Exclude(ver!(1, 3, 0))])));
+        assert_eq!(version_constraint(b">=1.2,<2"),
+                   Done(&b""[..], Range(Some(ver!(1, 2)), Some(ver!(2)))));
+        assert_eq!(version_constraint(b"1.x != 1.3 || >= 3 < 4"),
+                   Done(&b""[..], Any(vec![
+                       All(vec![Wildcard(vec![1]), Exclude(ver!(1, 3))]),
+                       Range(Some(ver!(3)), Some(ver!(4))),
+                   ])));
+        assert_eq!(version_constraint(b"^1.0 ||"), nom::IResult::Error(nom::ErrorKind::Eof));
+        assert_eq!(version_constraint(b"^1.0 || >= 2 < 1"),
+                   nom::IResult::Error(nom::ErrorKind::Custom(1)));
+        assert_eq!(version_constraint(b"1.x, ^0.0"),
+                   nom::IResult::Error(nom::ErrorKind::Custom(2)));
+    }
+
+    #[test]
+    fn constraint_as_string_roundtrip() {
+        for s in &[
+            "1.2.3", "*", ">= 1.2.3", "< 2", ">= 1 < 2", "^1.2", "~1.2", "1.2.x", "!= 1.3.0",
+            ">= 1.2, != 1.3.0", "^1.0 || ^2.0", "1.x, != 1.3 || ~3.1",
+        ] {
+            let vc = range(s);
+            assert_eq!(vc.as_string(), *s);
+            assert_eq!(range(&vc.as_string()), vc);
+        }
+    }
+
     #[test]
     fn constraint_as_string() {
         assert_eq!(Exact(ver("1.2.3.0-beta.0+foo")).as_string(), "1.2.3.0-beta.0+foo");
@@ -269,5 +457,28 @@ mod test {
         assert!(!range("^1.2.0-pre.1").contains(&ver("2-beta")));
         assert!(range("^0.0.0.1.2.3").contains(&ver("0.0.0.1.3")));
         assert!(!range("^0.0.0.1.2.3").contains(&ver("0.0.0.2")));
+
+        assert!(range("~1.2").contains(&ver("1.2.9")));
+        assert!(!range("~1.2").contains(&ver("1.3")));
+        assert!(!range("~1.2.3").contains(&ver("1.2.2")));
+        assert!(range("~1").contains(&ver("1.9")));
+        assert!(!range("~1").contains(&ver("2")));
+
+        assert!(range("1.x").contains(&ver("1")));
+        assert!(range("1.x").contains(&ver("1.9.9")));
+        assert!(!range("1.x").contains(&ver("2-beta")));
+        assert!(range("1.2.x").contains(&ver("1.2.7")));
+        assert!(!range("1.2.x").contains(&ver("1.3")));
+
+        assert!(range("!= 1.3.0").contains(&ver("1.3.1")));
+        assert!(!range("!= 1.3.0").contains(&ver("1.3")));
+
+        assert!(range("^1.0, != 1.3.0").contains(&ver("1.4")));
+        assert!(!range("^1.0, != 1.3.0").contains(&ver("1.3")));
+        assert!(!range("^1.0, != 1.3.0").contains(&ver("2")));
+
+        assert!(range("^1.0 || ^3.0").contains(&ver("1.5")));
+        assert!(range("^1.0 || ^3.0").contains(&ver("3.1")));
+        assert!(!range("^1.0 || ^3.0").contains(&ver("2.1")));
     }
 }
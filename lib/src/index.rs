@@ -10,6 +10,7 @@ use std::sync::Arc;
 use crate::constraint::VersionConstraint;
 use crate::dependencies::Dependency;
 use crate::package::PackageName;
+use crate::signing::ReleaseSignature;
 use crate::version::Version;
 
 quick_error! {
@@ -41,6 +42,49 @@ pub type Index = BTreeMap<PackageName, Package>;
 pub type Package = BTreeMap<Version, Dependencies>;
 pub type Dependencies = BTreeMap<PackageName, VersionConstraint>;
 
+/// The signature of a release as listed in the registry index, together with
+/// the registry user who published it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SignedRelease {
+    pub publisher: String,
+    pub signature: ReleaseSignature,
+}
+
+pub type Signatures = BTreeMap<PackageName, BTreeMap<Version, SignedRelease>>;
+
+/// The user ID of the registry user who published each release.
+pub type Publishers = BTreeMap<PackageName, BTreeMap<Version, String>>;
+
+/// The index as served by the registry: the dependency information that the
+/// solver works with, who published every release, and signatures for the
+/// releases that have them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RegistryIndex {
+    pub packages: Index,
+    #[serde(default)]
+    pub publishers: Publishers,
+    #[serde(default)]
+    pub signatures: Signatures,
+}
+
+impl RegistryIndex {
+    /// Who published a release. Registries which predate `publishers` only
+    /// report it for signed releases.
+    pub fn publisher(&self, name: &PackageName, version: &Version) -> Option<&str> {
+        self.publishers
+            .get(name)
+            .and_then(|releases| releases.get(version))
+            .or_else(|| self.signature(name, version).map(|signed| &signed.publisher))
+            .map(String::as_str)
+    }
+
+    pub fn signature(&self, name: &PackageName, version: &Version) -> Option<&SignedRelease> {
+        self.signatures
+            .get(name)
+            .and_then(|releases| releases.get(version))
+    }
+}
+
 // Note that this throws away duplicate dependencies.
 pub fn dependencies_from_slice(dependency_slice: &[Dependency]) -> Dependencies {
     let mut dependencies = Dependencies::new();
@@ -15,11 +15,17 @@ extern crate failure_derive;
 
 #[macro_use]
 pub mod test_helpers;
+pub mod archive;
 pub mod constraint;
 pub mod dependencies;
 pub mod index;
 pub mod package;
+pub mod package_info;
 pub mod publication_request;
+pub mod search;
+pub mod signing;
 #[macro_use]
 pub mod solver;
+pub mod spdx;
+pub mod transparency_log;
 pub mod version;
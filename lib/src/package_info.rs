@@ -0,0 +1,232 @@
+// What the registry's package API returns: `/api/packages/<namespace>/<name>`
+// describes a package as a `PackageInfo`, and
+// `/api/packages/<namespace>/<name>/<version>` one of its releases as a
+// `ReleaseInfo`. `/api/packages/<namespace>/<name>/dependents` lists the
+// packages which depend on a package as a `DependentsPage`. Times are in
+// seconds since the Unix epoch. Download counts lag a minute or so behind,
+// as the registry adds them up in the background.
+
+use crate::constraint::VersionConstraint;
+use crate::dependencies::Dependency;
+use crate::package::PackageName;
+use crate::publication_request::{NamedTextFile, Repository};
+use crate::signing::ReleaseSignature;
+use crate::version::Version;
+
+/// A registry user.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UserInfo {
+    /// `<provider>:<id>`, like `github:octocat`.
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub avatar: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OwnerInfo {
+    pub user: UserInfo,
+    pub added_time: i64,
+}
+
+/// Everything the registry stores about a release.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseInfo {
+    pub package: PackageName,
+    pub version: Version,
+
+    pub description: String,
+    pub authors: Vec<String>,
+    pub keywords: Vec<String>,
+    pub homepage_url: Option<String>,
+    pub repository: Option<Repository>,
+    pub bugs_url: Option<String>,
+    pub license: Option<String>,
+    pub license_file: Option<NamedTextFile>,
+    pub manifest: Option<NamedTextFile>,
+    pub readme: Option<NamedTextFile>,
+
+    pub dependencies: Vec<Dependency>,
+    pub publisher: UserInfo,
+    pub publish_time: i64,
+    pub signature: Option<ReleaseSignature>,
+    pub deleted: Option<String>,
+    pub deleted_on: Option<i64>,
+
+    pub downloads: u64,
+    /// The downloads on each of the last 90 days that had any, oldest first.
+    pub daily_downloads: Vec<DailyDownloads>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DailyDownloads {
+    /// In UTC, as `YYYY-MM-DD`.
+    pub day: String,
+    pub downloads: u64,
+}
+
+/// A release in a package's list of releases.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseSummary {
+    pub version: Version,
+    pub description: String,
+    /// The publisher's user ID.
+    pub publisher: String,
+    pub publish_time: i64,
+    pub deleted: Option<String>,
+    pub downloads: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PackageInfo {
+    pub package: PackageName,
+    /// In the order they were added.
+    pub owners: Vec<OwnerInfo>,
+    /// Highest version first.
+    pub releases: Vec<ReleaseSummary>,
+    /// The release picked by `latest_release`, in full.
+    pub latest: Option<ReleaseInfo>,
+    pub deleted: Option<String>,
+    pub deleted_on: Option<i64>,
+    /// The downloads of all its releases.
+    pub downloads: u64,
+}
+
+/// A release that depends on a package.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Dependent {
+    pub package: PackageName,
+    pub version: Version,
+    /// The versions of the package the release accepts.
+    pub constraint: VersionConstraint,
+}
+
+/// One page of the packages which depend on a package.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DependentsPage {
+    /// How many dependents there are on all pages.
+    pub total: usize,
+    /// Counting from 1.
+    pub page: usize,
+    /// How many dependents a page holds.
+    pub limit: usize,
+    /// The highest release of each dependent package, sorted by package name.
+    pub dependents: Vec<Dependent>,
+}
+
+/// The highest release of each package out of `dependents`, which have to be
+/// sorted by package and then from the highest version down.
+pub fn highest_dependents<'a, I>(dependents: I) -> Vec<&'a Dependent>
+where
+    I: IntoIterator<Item = &'a Dependent>,
+{
+    let mut highest: Vec<&Dependent> = Vec::new();
+    for dependent in dependents {
+        if highest.last().map(|last| &last.package) != Some(&dependent.package) {
+            highest.push(dependent);
+        }
+    }
+    highest
+}
+
+/// The release to show for a package: the highest one that isn't deleted,
+/// preferring stable releases over prereleases.
+pub fn latest_release(releases: &[ReleaseSummary]) -> Option<&ReleaseSummary> {
+    // Versions are ordered by preference: stable releases from the highest
+    // down, then prereleases.
+    releases
+        .iter()
+        .filter(|release| release.deleted.is_none())
+        .min_by(|a, b| a.version.cmp(&b.version))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helpers::{pkg, range, ver};
+
+    fn release(version: &str, deleted: bool) -> ReleaseSummary {
+        ReleaseSummary {
+            version: ver(version),
+            description: "A package".to_string(),
+            publisher: "test:alice".to_string(),
+            publish_time: 0,
+            deleted: if deleted {
+                Some("mistake".to_string())
+            } else {
+                None
+            },
+            downloads: 0,
+        }
+    }
+
+    fn latest(releases: &[ReleaseSummary]) -> Option<String> {
+        latest_release(releases).map(|release| release.version.to_string())
+    }
+
+    #[test]
+    fn latest_release_prefers_stable_releases() {
+        assert_eq!(latest(&[]), None);
+        assert_eq!(
+            latest(&[
+                release("1.0.0", false),
+                release("2.0.0-beta", false),
+                release("1.2.0", false),
+            ]),
+            Some("1.2.0".to_string())
+        );
+        assert_eq!(
+            latest(&[release("1.0.0-alpha", false), release("1.0.0-beta", false)]),
+            Some("1.0.0-beta".to_string())
+        );
+    }
+
+    #[test]
+    fn latest_release_skips_deleted_releases() {
+        assert_eq!(
+            latest(&[
+                release("1.0.0", false),
+                release("2.0.0", true),
+                release("2.1.0-beta", false),
+            ]),
+            Some("1.0.0".to_string())
+        );
+        assert_eq!(
+            latest(&[release("1.0.0", true), release("1.1.0-rc", false)]),
+            Some("1.1.0-rc".to_string())
+        );
+        assert_eq!(latest(&[release("1.0.0", true)]), None);
+    }
+
+    #[test]
+    fn highest_dependents_keeps_one_release_per_package() {
+        let dependent = |package: &str, version: &str, constraint: &str| Dependent {
+            package: pkg(package),
+            version: ver(version),
+            constraint: range(constraint),
+        };
+        let dependents = vec![
+            dependent("test/a", "2.0.0", "^2.0"),
+            dependent("test/a", "1.0.0", "^1.0"),
+            dependent("test/b", "1.0.0", "^1.0"),
+        ];
+        let highest = |dependents: Vec<&Dependent>| {
+            dependents
+                .iter()
+                .map(|dependent| format!("{} {}", dependent.package, dependent.version))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            highest(highest_dependents(&dependents)),
+            vec!["test/a 2.0.0", "test/b 1.0.0"]
+        );
+        assert_eq!(
+            highest(highest_dependents(
+                dependents
+                    .iter()
+                    .filter(|dependent| dependent.constraint.contains(&ver("1.2.0")))
+            )),
+            vec!["test/a 1.0.0", "test/b 1.0.0"]
+        );
+    }
+}
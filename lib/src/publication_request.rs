@@ -1,13 +1,23 @@
 use crate::version::Version;
 use crate::dependencies::Dependency;
+use crate::package::PackageName;
+use crate::signing::ReleaseSignature;
+use crate::spdx::is_valid_license_expression;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+pub const MAX_LICENSE_LENGTH: usize = 256;
+pub const MAX_README_SIZE: usize = 1024 * 1024;
+pub const MAX_LICENSE_FILE_SIZE: usize = 256 * 1024;
+pub const MAX_KEYWORDS: usize = 20;
+pub const MAX_KEYWORD_LENGTH: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct NamedTextFile {
     pub name: String,
     pub contents: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Repository {
     pub type_: String,
     pub url: String,
@@ -34,4 +44,263 @@ pub struct PublicationRequest {
 
     pub dependencies: Vec<Dependency>,
     pub tar_br: Vec<u8>,
+    /// The publisher's signature over `tar_br`, if they have a signing key.
+    #[serde(default)]
+    pub signature: Option<ReleaseSignature>,
+}
+
+/// A reason to reject a publication request. The `code` is stable and meant
+/// for programs; the message is meant for people.
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+#[fail(display = "{}", message)]
+pub struct MetadataError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+fn metadata_error(code: &'static str, message: String) -> Result<(), MetadataError> {
+    Err(MetadataError { code, message })
+}
+
+fn validate_url(field_name: &str, url: &str) -> Result<(), MetadataError> {
+    let lowercase_url = url.to_lowercase();
+    let rest = if lowercase_url.starts_with("https://") {
+        &url["https://".len()..]
+    } else if lowercase_url.starts_with("http://") {
+        &url["http://".len()..]
+    } else {
+        return metadata_error(
+            "invalid_url",
+            format!("{} must be an http:// or https:// URL: {}", field_name, url),
+        );
+    };
+    if rest.is_empty()
+        || rest.starts_with('/')
+        || rest.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        return metadata_error("invalid_url", format!("{} is not a valid URL: {}", field_name, url));
+    }
+    Ok(())
+}
+
+impl PublicationRequest {
+    /// Check the metadata that we can check without access to the registry.
+    pub fn validate_metadata(&self) -> Result<(), MetadataError> {
+        let package_name =
+            match PackageName::from_str(&format!("{}/{}", self.namespace, self.name)) {
+                Some(package_name) => package_name,
+                None => {
+                    return metadata_error(
+                        "invalid_package_name",
+                        format!("Invalid package name: {}/{}", self.namespace, self.name),
+                    );
+                }
+            };
+
+        match self.license {
+            Some(ref license) if license.len() > MAX_LICENSE_LENGTH => {
+                return metadata_error(
+                    "invalid_license",
+                    format!(
+                        "The license expression must be at most {} bytes long",
+                        MAX_LICENSE_LENGTH
+                    ),
+                );
+            }
+            Some(ref license) if !is_valid_license_expression(license) => {
+                return metadata_error(
+                    "invalid_license",
+                    format!("Not a valid SPDX license expression: {}", license),
+                );
+            }
+            None if self.license_file.is_none() => {
+                return metadata_error(
+                    "missing_license",
+                    "Either license or license_file is required".to_string(),
+                );
+            }
+            _ => {}
+        }
+
+        if self.description.chars().count() > MAX_DESCRIPTION_LENGTH {
+            return metadata_error(
+                "description_too_long",
+                format!(
+                    "The description must be at most {} characters long",
+                    MAX_DESCRIPTION_LENGTH
+                ),
+            );
+        }
+        if let Some(ref readme) = self.readme {
+            if readme.contents.len() > MAX_README_SIZE {
+                return metadata_error(
+                    "readme_too_large",
+                    format!("{} must be at most {} bytes", readme.name, MAX_README_SIZE),
+                );
+            }
+        }
+        if let Some(ref license_file) = self.license_file {
+            if license_file.contents.len() > MAX_LICENSE_FILE_SIZE {
+                return metadata_error(
+                    "license_file_too_large",
+                    format!(
+                        "{} must be at most {} bytes",
+                        license_file.name, MAX_LICENSE_FILE_SIZE
+                    ),
+                );
+            }
+        }
+
+        if self.keywords.len() > MAX_KEYWORDS {
+            return metadata_error(
+                "too_many_keywords",
+                format!("A package can have at most {} keywords", MAX_KEYWORDS),
+            );
+        }
+        for keyword in &self.keywords {
+            if keyword.is_empty() || keyword.chars().count() > MAX_KEYWORD_LENGTH {
+                return metadata_error(
+                    "invalid_keyword",
+                    format!(
+                        "Keywords must be between 1 and {} characters long: {:?}",
+                        MAX_KEYWORD_LENGTH, keyword
+                    ),
+                );
+            }
+        }
+
+        if let Some(ref url) = self.homepage_url {
+            validate_url("homepage", url)?;
+        }
+        if let Some(ref url) = self.bugs_url {
+            validate_url("bugs", url)?;
+        }
+        if let Some(ref repository) = self.repository {
+            validate_url("repository url", &repository.url)?;
+        }
+
+        if let Some(ref signature) = self.signature {
+            if let Err(error) = signature.verify(&package_name, &self.version, &self.tar_br) {
+                return metadata_error("invalid_signature", format!("Invalid signature: {}", error));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signing::SigningKey;
+    use crate::test_helpers::{pkg, ver};
+
+    fn request() -> PublicationRequest {
+        PublicationRequest {
+            namespace: "test".to_string(),
+            name: "a".to_string(),
+            version: ver("1.0.0"),
+            description: "A package".to_string(),
+            authors: vec![],
+            keywords: vec!["testing".to_string()],
+            homepage_url: Some("https://example.com/a".to_string()),
+            repository: Some(Repository {
+                type_: "git".to_string(),
+                url: "https://example.com/a.git".to_string(),
+            }),
+            bugs_url: None,
+            license: Some("MIT OR Apache-2.0".to_string()),
+            license_file: None,
+            manifest: None,
+            readme: None,
+            dependencies: vec![],
+            tar_br: vec![],
+            signature: None,
+        }
+    }
+
+    fn error_code(request: PublicationRequest) -> Option<&'static str> {
+        request.validate_metadata().err().map(|error| error.code)
+    }
+
+    #[test]
+    fn validate_metadata() {
+        assert_eq!(error_code(request()), None);
+        assert_eq!(
+            error_code(PublicationRequest {
+                namespace: "Test".to_string(),
+                ..request()
+            }),
+            Some("invalid_package_name")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                license: Some("MIT/Apache-2.0".to_string()),
+                ..request()
+            }),
+            Some("invalid_license")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                license: Some(format!("{}MIT{}", "(".repeat(200), ")".repeat(200))),
+                ..request()
+            }),
+            Some("invalid_license")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                license: None,
+                ..request()
+            }),
+            Some("missing_license")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                description: "x".repeat(MAX_DESCRIPTION_LENGTH + 1),
+                ..request()
+            }),
+            Some("description_too_long")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                readme: Some(NamedTextFile {
+                    name: "README.md".to_string(),
+                    contents: "x".repeat(MAX_README_SIZE + 1),
+                }),
+                ..request()
+            }),
+            Some("readme_too_large")
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                keywords: vec!["x".to_string(); MAX_KEYWORDS + 1],
+                ..request()
+            }),
+            Some("too_many_keywords")
+        );
+        for url in &["ftp://example.com", "javascript:alert(1)", "https://", "http://a b"] {
+            assert_eq!(
+                error_code(PublicationRequest {
+                    bugs_url: Some(url.to_string()),
+                    ..request()
+                }),
+                Some("invalid_url")
+            );
+        }
+
+        let key = SigningKey::generate();
+        assert_eq!(
+            error_code(PublicationRequest {
+                signature: Some(key.sign_release(&pkg("a"), &ver("1.0.0"), &[])),
+                ..request()
+            }),
+            None
+        );
+        assert_eq!(
+            error_code(PublicationRequest {
+                signature: Some(key.sign_release(&pkg("a"), &ver("1.0.0"), b"other")),
+                ..request()
+            }),
+            Some("invalid_signature")
+        );
+    }
 }
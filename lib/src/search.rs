@@ -0,0 +1,191 @@
+// Searching the registry. `/search?q=<query>` returns a page of
+// `SearchResults`, and ranks packages with `relevance`.
+//
+// A query is a list of search terms, each of which has to appear in a
+// package's name, keywords or description, and qualifiers which filter the
+// results: `keyword:<keyword>` only finds packages with that keyword, and
+// `owner:<user>` only packages owned by that user, given by ID (like
+// `github:octocat`) or name. Everything is matched case insensitively.
+// Packages which are equally relevant are ranked by how often they've been
+// downloaded.
+
+use crate::package::PackageName;
+use crate::version::Version;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub keywords: Vec<String>,
+    pub owners: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> SearchQuery {
+        let mut parsed = SearchQuery::default();
+        for word in query.split_whitespace() {
+            let word = word.to_lowercase();
+            if word.starts_with("keyword:") && word.len() > "keyword:".len() {
+                parsed.keywords.push(word["keyword:".len()..].to_string());
+            } else if word.starts_with("owner:") && word.len() > "owner:".len() {
+                parsed.owners.push(word["owner:".len()..].to_string());
+            } else {
+                parsed.terms.push(word);
+            }
+        }
+        parsed
+    }
+
+    /// Whether a package passes the query's `keyword:` qualifiers.
+    pub fn matches_keywords(&self, keywords: &[String]) -> bool {
+        self.keywords.iter().all(|wanted| {
+            keywords
+                .iter()
+                .any(|keyword| keyword.to_lowercase() == *wanted)
+        })
+    }
+
+    /// Whether a package passes the query's `owner:` qualifiers, given its
+    /// owners' IDs and names.
+    pub fn matches_owners(&self, owners: &[(String, String)]) -> bool {
+        self.owners.iter().all(|wanted| {
+            owners
+                .iter()
+                .any(|(id, name)| id.to_lowercase() == *wanted || name.to_lowercase() == *wanted)
+        })
+    }
+}
+
+/// How well a package's name, keywords and description match the search
+/// terms, or `None` if one of the terms doesn't appear in them at all. A term
+/// counts most when it's the name, then a keyword, and least when it's only
+/// part of the description.
+pub fn relevance(
+    terms: &[String],
+    name: &str,
+    keywords: &[String],
+    description: &str,
+) -> Option<u32> {
+    let name = name.to_lowercase();
+    let keywords: Vec<String> = keywords
+        .iter()
+        .map(|keyword| keyword.to_lowercase())
+        .collect();
+    let description = description.to_lowercase();
+    let mut total = 0;
+    for term in terms {
+        let mut score = 0;
+        if name == *term {
+            score += 16;
+        } else if name.starts_with(term.as_str()) {
+            score += 8;
+        } else if name.contains(term.as_str()) {
+            score += 4;
+        }
+        if keywords.iter().any(|keyword| keyword == term) {
+            score += 4;
+        } else if keywords
+            .iter()
+            .any(|keyword| keyword.contains(term.as_str()))
+        {
+            score += 2;
+        }
+        if description.contains(term.as_str()) {
+            score += 1;
+        }
+        if score == 0 {
+            return None;
+        }
+        total += score;
+    }
+    Some(total)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub package: PackageName,
+    /// The latest release which isn't yanked, and isn't a prerelease unless
+    /// the package has nothing but prereleases.
+    pub version: Version,
+    pub description: String,
+    pub keywords: Vec<String>,
+    /// The user ID of the release's publisher.
+    pub publisher: String,
+    /// The downloads of all the package's releases.
+    pub downloads: u64,
+}
+
+/// One page of search results, the most relevant first, and the most
+/// downloaded first among equally relevant ones.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchResults {
+    /// How many results there are on all pages.
+    pub total: usize,
+    /// Counting from 1.
+    pub page: usize,
+    /// How many results a page holds.
+    pub limit: usize,
+    pub results: Vec<SearchResult>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(strings: &[&str]) -> Vec<String> {
+        strings.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_qualifiers() {
+        assert_eq!(
+            SearchQuery::parse("Pad keyword:Strings owner:github:octocat  left keyword:"),
+            SearchQuery {
+                terms: strings(&["pad", "left", "keyword:"]),
+                keywords: strings(&["strings"]),
+                owners: strings(&["github:octocat"]),
+            }
+        );
+        assert_eq!(SearchQuery::parse("  "), SearchQuery::default());
+    }
+
+    #[test]
+    fn match_qualifiers() {
+        let query = SearchQuery::parse("keyword:strings owner:alice");
+        let owners = vec![("test:alice".to_string(), "Alice".to_string())];
+        assert!(query.matches_keywords(&strings(&["Strings", "padding"])));
+        assert!(!query.matches_keywords(&strings(&["stringsy"])));
+        assert!(query.matches_owners(&owners));
+        assert!(SearchQuery::parse("owner:test:alice").matches_owners(&owners));
+        assert!(!SearchQuery::parse("owner:bob").matches_owners(&owners));
+        assert!(SearchQuery::parse("pad").matches_owners(&[]));
+    }
+
+    #[test]
+    fn rank_names_over_keywords_over_descriptions() {
+        let relevance = |query: &str, name: &str, keywords: &[&str], description: &str| {
+            relevance(
+                &SearchQuery::parse(query).terms,
+                name,
+                &strings(keywords),
+                description,
+            )
+        };
+        assert_eq!(relevance("pad", "pad", &[], ""), Some(16));
+        assert_eq!(relevance("pad", "Pad-left", &[], ""), Some(8));
+        assert_eq!(
+            relevance("pad", "left-pad", &["pad"], "Pads strings"),
+            Some(9)
+        );
+        assert_eq!(relevance("pad", "strings", &["padding"], ""), Some(2));
+        assert_eq!(relevance("pad", "strings", &[], "Pads strings"), Some(1));
+        assert_eq!(
+            relevance("pad left", "left-pad", &[], "Pads strings"),
+            Some(13)
+        );
+        assert_eq!(
+            relevance("pad right", "left-pad", &[], "Pads strings"),
+            None
+        );
+        assert_eq!(relevance("", "left-pad", &[], ""), Some(0));
+    }
+}
@@ -0,0 +1,149 @@
+// Ed25519 signatures over releases. Publishers register public keys with the
+// registry and sign each release they publish; clients check the signatures
+// against a trust policy of their own, so a compromised registry account or
+// registry database alone can't ship a release that they will accept.
+//
+// Keys and signatures are exchanged as standard Base64 strings.
+
+use std::convert::TryFrom;
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+
+use crate::package::PackageName;
+use crate::version::Version;
+
+#[derive(Fail, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    #[fail(display = "invalid ed25519 public key: {:?}", _0)]
+    InvalidPublicKey(String),
+    #[fail(display = "invalid ed25519 secret key")]
+    InvalidSecretKey,
+    #[fail(display = "malformed signature: {:?}", _0)]
+    MalformedSignature(String),
+    #[fail(display = "signature does not match the release")]
+    Mismatch,
+}
+
+/// The signature of a release, together with the public key that made it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// What gets signed: the release's name and version, so that a signature
+/// can't be replayed for another release, followed by a digest of the
+/// archive.
+fn release_message(name: &PackageName, version: &Version, tar_br: &[u8]) -> Vec<u8> {
+    let mut message = format!("pm release v1\n{}\n{}\n", name, version).into_bytes();
+    message.extend_from_slice(&Sha512::digest(tar_br));
+    message
+}
+
+pub fn parse_public_key(public_key: &str) -> Result<PublicKey, SignatureError> {
+    base64::decode(public_key)
+        .ok()
+        .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| SignatureError::InvalidPublicKey(public_key.to_string()))
+}
+
+pub struct SigningKey(Keypair);
+
+impl SigningKey {
+    pub fn generate() -> SigningKey {
+        SigningKey(Keypair::generate(&mut OsRng))
+    }
+
+    /// Read a secret key as written by `to_base64`.
+    pub fn from_base64(secret_key: &str) -> Result<SigningKey, SignatureError> {
+        let secret = base64::decode(secret_key.trim())
+            .ok()
+            .and_then(|bytes| SecretKey::from_bytes(&bytes).ok())
+            .ok_or(SignatureError::InvalidSecretKey)?;
+        let public = PublicKey::from(&secret);
+        Ok(SigningKey(Keypair { secret, public }))
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.0.secret.as_bytes())
+    }
+
+    pub fn public_key(&self) -> String {
+        base64::encode(self.0.public.as_bytes())
+    }
+
+    pub fn sign_release(
+        &self,
+        name: &PackageName,
+        version: &Version,
+        tar_br: &[u8],
+    ) -> ReleaseSignature {
+        let signature = self.0.sign(&release_message(name, version, tar_br));
+        ReleaseSignature {
+            public_key: self.public_key(),
+            signature: base64::encode(&signature.to_bytes()[..]),
+        }
+    }
+}
+
+impl ReleaseSignature {
+    /// Check that this signature was made by `public_key` over the given
+    /// release. This says nothing about whether the key should be trusted.
+    pub fn verify(
+        &self,
+        name: &PackageName,
+        version: &Version,
+        tar_br: &[u8],
+    ) -> Result<(), SignatureError> {
+        let public_key = parse_public_key(&self.public_key)?;
+        let signature = base64::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| Signature::try_from(&bytes[..]).ok())
+            .ok_or_else(|| SignatureError::MalformedSignature(self.signature.clone()))?;
+        public_key
+            .verify(&release_message(name, version, tar_br), &signature)
+            .map_err(|_| SignatureError::Mismatch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helpers::{pkg, ver};
+
+    #[test]
+    fn sign_and_verify_releases() {
+        let key = SigningKey::generate();
+        let key = SigningKey::from_base64(&key.to_base64()).unwrap();
+        let signature = key.sign_release(&pkg("a"), &ver("1.0.0"), b"archive");
+        assert_eq!(signature.public_key, key.public_key());
+        assert_eq!(
+            signature.verify(&pkg("a"), &ver("1.0.0"), b"archive"),
+            Ok(())
+        );
+
+        for (name, version, tar_br) in &[
+            ("a", "1.0.0", &b"tampered"[..]),
+            ("a", "1.0.1", b"archive"),
+            ("b", "1.0.0", b"archive"),
+        ] {
+            assert_eq!(
+                signature.verify(&pkg(name), &ver(version), tar_br),
+                Err(SignatureError::Mismatch)
+            );
+        }
+
+        let other = ReleaseSignature {
+            public_key: SigningKey::generate().public_key(),
+            ..signature.clone()
+        };
+        assert_eq!(
+            other.verify(&pkg("a"), &ver("1.0.0"), b"archive"),
+            Err(SignatureError::Mismatch)
+        );
+        assert!(parse_public_key("not a key").is_err());
+        assert!(SigningKey::from_base64("AAAA").is_err());
+    }
+}
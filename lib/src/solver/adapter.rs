@@ -6,6 +6,7 @@ use crate::solver::constraints::{Constraint, ConstraintSet};
 use crate::solver::failure::Failure;
 use crate::solver::mappable::Mappable;
 use crate::solver::path::Path;
+use crate::solver::prerelease::PrereleasePolicy;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -13,20 +14,30 @@ use std::vec::Vec;
 
 pub struct RegistryAdapter<'r> {
     registry: &'r Index,
+    prereleases: PrereleasePolicy,
     cache: RefCell<HashMap<(PackageName, VersionConstraint), Option<Vec<Version>>>>,
 }
 
 impl<'r> RegistryAdapter<'r> {
     pub fn new(registry: &Index) -> RegistryAdapter {
+        RegistryAdapter::with_prereleases(registry, PrereleasePolicy::default())
+    }
+
+    pub fn with_prereleases(
+        registry: &Index,
+        prereleases: PrereleasePolicy,
+    ) -> RegistryAdapter<'_> {
         RegistryAdapter {
             registry,
+            prereleases,
             cache: RefCell::new(HashMap::new()),
         }
     }
 
     /// Return a vector of all versions of `package` matching `constraint`, or
     /// `None` if the `package` was not found in the registry. The vector can be
-    /// empty if no versions match.
+    /// empty if no versions match. Pre-release versions are left out unless the
+    /// `PrereleasePolicy` allows them.
     pub fn versions_for(
         &self,
         package: &PackageName,
@@ -41,7 +52,9 @@ impl<'r> RegistryAdapter<'r> {
             None => None,
             Some(pkg) => Some(
                 pkg.keys()
-                    .filter(|v| constraint.contains(v))
+                    .filter(|v| {
+                        constraint.contains(v) && self.prereleases.allows(package, constraint, v)
+                    })
                     .cloned()
                     .collect(),
             ),
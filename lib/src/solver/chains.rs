@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+
+use crate::constraint::VersionConstraint;
+use crate::index::{Dependencies, Index};
+use crate::package::PackageName;
+use crate::solver::solution::Solution;
+use crate::version::Version;
+
+/// One edge in a dependency chain: `package` is required with `constraint`,
+/// and was resolved to `version`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainLink {
+    pub package: PackageName,
+    pub constraint: VersionConstraint,
+    pub version: Version,
+}
+
+/// Return the chains of dependencies in `solution` that lead from the
+/// top-level `deps` to `package`, at most `limit` of them: there can be
+/// exponentially many. A chain never visits the same package twice, so
+/// cyclic dependencies don't produce infinitely many chains.
+pub fn dependency_chains(
+    reg: &Index,
+    deps: &Dependencies,
+    solution: &Solution,
+    package: &PackageName,
+    limit: usize,
+) -> Vec<Vec<ChainLink>> {
+    let leads_to_target = packages_leading_to(reg, solution, package);
+    let mut chains = vec![];
+    let mut search = Search {
+        reg,
+        solution,
+        target: package,
+        leads_to_target: &leads_to_target,
+        limit,
+        chain: vec![],
+    };
+    search.visit(deps, &mut chains);
+    chains
+}
+
+/// The packages in `solution` that depend on `target`, directly or
+/// indirectly, and `target` itself.
+fn packages_leading_to<'a>(
+    reg: &Index,
+    solution: &'a Solution,
+    target: &PackageName,
+) -> BTreeSet<&'a PackageName> {
+    let mut leading = BTreeSet::new();
+    leading.extend(solution.get_key_value(target).map(|(name, _)| name));
+    loop {
+        let more: Vec<&PackageName> = solution
+            .iter()
+            .filter(|(package, version)| {
+                if leading.contains(package) {
+                    return false;
+                }
+                match reg.get(package).and_then(|p| p.get(version)) {
+                    Some(deps) => deps.keys().any(|dep| leading.contains(dep)),
+                    None => false,
+                }
+            })
+            .map(|(package, _)| package)
+            .collect();
+        if more.is_empty() {
+            return leading;
+        }
+        leading.extend(more);
+    }
+}
+
+struct Search<'a> {
+    reg: &'a Index,
+    solution: &'a Solution,
+    target: &'a PackageName,
+    /// We only follow dependencies that can get us to `target`, so that
+    /// nearly every step finds a chain.
+    leads_to_target: &'a BTreeSet<&'a PackageName>,
+    limit: usize,
+    chain: Vec<ChainLink>,
+}
+
+impl<'a> Search<'a> {
+    fn visit(&mut self, deps: &Dependencies, chains: &mut Vec<Vec<ChainLink>>) {
+        for (package, constraint) in deps {
+            if chains.len() >= self.limit {
+                return;
+            }
+            if !self.leads_to_target.contains(package)
+                || self.chain.iter().any(|link| &link.package == package)
+            {
+                continue;
+            }
+            let version = match self.solution.get(package) {
+                Some(version) => version,
+                None => continue,
+            };
+            self.chain.push(ChainLink {
+                package: package.clone(),
+                constraint: constraint.clone(),
+                version: version.clone(),
+            });
+            if package == self.target {
+                chains.push(self.chain.clone());
+            } else if let Some(release_deps) = self.reg.get(package).and_then(|p| p.get(version)) {
+                self.visit(release_deps, chains);
+            }
+            self.chain.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helpers::{pkg, range, ver};
+
+    fn link(package: &str, constraint: &str, version: &str) -> ChainLink {
+        ChainLink {
+            package: pkg(package),
+            constraint: range(constraint),
+            version: ver(version),
+        }
+    }
+
+    #[test]
+    fn find_dependency_chains() {
+        let reg = gen_registry!(
+            A => ( "1.0.0" => deps!(B => "^2", C => "^1") ),
+            B => ( "2.0.0" => deps!(C => ">= 1.0.1") ),
+            C => ( "1.0.1" => deps!(A => "^1") )
+        );
+        let deps = deps!(A => "^1", B => "*");
+        let solution = solution!(A => "1.0.0", B => "2.0.0", C => "1.0.1");
+
+        assert_eq!(
+            dependency_chains(&reg, &deps, &solution, &pkg("C"), 10),
+            vec![
+                vec![
+                    link("A", "^1", "1.0.0"),
+                    link("B", "^2", "2.0.0"),
+                    link("C", ">= 1.0.1", "1.0.1"),
+                ],
+                vec![link("A", "^1", "1.0.0"), link("C", "^1", "1.0.1")],
+                vec![link("B", "*", "2.0.0"), link("C", ">= 1.0.1", "1.0.1")],
+            ]
+        );
+        assert_eq!(
+            dependency_chains(&reg, &deps, &solution, &pkg("X"), 10),
+            Vec::<Vec<ChainLink>>::new()
+        );
+        assert_eq!(
+            dependency_chains(&reg, &deps, &solution, &pkg("C"), 1),
+            vec![vec![
+                link("A", "^1", "1.0.0"),
+                link("B", "^2", "2.0.0"),
+                link("C", ">= 1.0.1", "1.0.1"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn bound_dependency_chains() {
+        // Each package depends on the next two, so there are over a hundred
+        // million chains from P0 to P40, and as many from D0 to D40, which
+        // doesn't lead to P40 at all.
+        let mut reg = Index::new();
+        let mut solution = Solution::new();
+        for prefix in &["D", "P"] {
+            for i in 0..=40 {
+                let mut deps = Dependencies::new();
+                for next in &[i + 1, i + 2] {
+                    if *next <= 40 {
+                        deps.insert(pkg(&format!("{}{}", prefix, next)), range("^1"));
+                    }
+                }
+                let name = pkg(&format!("{}{}", prefix, i));
+                reg.insert(name.clone(), Some((ver("1.0.0"), deps)).into_iter().collect());
+                solution.insert(name, ver("1.0.0"));
+            }
+        }
+        let deps = deps!(D0 => "^1", P0 => "^1");
+        let chains = dependency_chains(&reg, &deps, &solution, &pkg("P40"), 100);
+        assert_eq!(chains.len(), 100);
+        assert!(chains.iter().all(|chain| chain.last().unwrap().package == pkg("P40")));
+    }
+}
@@ -5,6 +5,7 @@ use crate::solver::failure::Failure;
 use crate::solver::mappable::Mappable;
 use crate::solver::path::Path;
 use crate::solver::solution::{JustifiedVersion, PartialSolution};
+use crate::solver::strategy::ResolutionStrategy;
 use std::fmt;
 use std::sync::Arc;
 
@@ -67,6 +68,24 @@ impl Constraint {
         }
     }
 
+    /// Iterate over the versions in the order in which `strategy` wants us to
+    /// try them. Either way, pre-release versions come last.
+    pub fn iter_preferred<'a>(
+        &'a self,
+        strategy: ResolutionStrategy,
+    ) -> Box<dyn Iterator<Item = &'a (Arc<Version>, Path)> + 'a> {
+        match strategy {
+            // The map is already ordered highest-first with pre-releases last.
+            ResolutionStrategy::Highest => Box::new(self.iter()),
+            ResolutionStrategy::Lowest => Box::new(
+                self.iter()
+                    .rev()
+                    .filter(|(version, _)| !version.has_pre())
+                    .chain(self.iter().rev().filter(|(version, _)| version.has_pre())),
+            ),
+        }
+    }
+
     pub fn or(&self, other: &Constraint) -> Constraint {
         let mut out = self.clone();
         for (version, other_path) in other.iter() {
@@ -317,6 +336,30 @@ mod test {
         assert_eq!(merged, Err(expected_failure));
     }
 
+    #[test]
+    fn constraint_iter_preferred() {
+        let c = constraint(&[
+            ("1", &[]),
+            ("2-beta", &[]),
+            ("2", &[]),
+            ("1.5", &[]),
+            ("1.5-rc", &[]),
+        ]);
+        let versions = |strategy| {
+            c.iter_preferred(strategy)
+                .map(|(version, _)| version.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            versions(ResolutionStrategy::Highest),
+            vec!["2", "1.5", "1", "2-beta", "1.5-rc"]
+        );
+        assert_eq!(
+            versions(ResolutionStrategy::Lowest),
+            vec!["1", "1.5", "2", "1.5-rc", "2-beta"]
+        );
+    }
+
     #[test]
     fn constraint_set_merge() {
         let existing = constraint_set(&[("A", &[("1", &[])]), ("B", &[("1", &[]), ("2", &[])])]);
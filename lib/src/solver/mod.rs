@@ -3,29 +3,36 @@ use crate::index::{Dependencies, Index};
 #[macro_use]
 pub mod test_helpers;
 mod adapter;
+mod chains;
 mod constraints;
 mod error;
 mod failure;
 mod mappable;
 mod path;
+mod prerelease;
 mod solution;
+mod strategy;
 
 pub use crate::solver::adapter::RegistryAdapter;
+pub use crate::solver::chains::{dependency_chains, ChainLink};
 pub use crate::solver::constraints::{Constraint, ConstraintSet};
 pub use crate::solver::error::{Conflict, Error};
 pub use crate::solver::failure::Failure;
 use crate::solver::mappable::Mappable;
 pub use crate::solver::path::Path;
-pub use crate::solver::solution::{JustifiedVersion, PartialSolution, Solution};
+pub use crate::solver::prerelease::{InvalidPrereleasePolicy, PrereleasePolicy};
+pub use crate::solver::solution::{JustifiedSolution, JustifiedVersion, PartialSolution, Solution};
+pub use crate::solver::strategy::{InvalidStrategy, ResolutionStrategy};
 
 fn search(
     ra: &RegistryAdapter,
+    strategy: ResolutionStrategy,
     mut stack: ConstraintSet,
     solution: &PartialSolution,
 ) -> Result<PartialSolution, Failure> {
     let mut cheap_failure;
     loop {
-        match cheap_attempt(ra, &stack, solution) {
+        match cheap_attempt(ra, strategy, &stack, solution) {
             Ok(sln) => {
                 return Ok(sln);
             }
@@ -44,7 +51,7 @@ fn search(
         None => Ok(solution.clone()),
         Some((stack_tail, package, constraint)) => {
             let mut first_failure = None;
-            for (version, path) in constraint.iter() {
+            for (version, path) in constraint.iter_preferred(strategy) {
                 let new_solution = solution.insert(
                     package.clone(),
                     JustifiedVersion {
@@ -55,7 +62,7 @@ fn search(
                 let try_version = || {
                     let constraint_set = ra.constraint_set_for(&package, &version, path)?;
                     let (new_deps, _) = stack_tail.and(&constraint_set, &new_solution)?;
-                    Ok(search(ra, new_deps, &new_solution)?)
+                    Ok(search(ra, strategy, new_deps, &new_solution)?)
                 };
                 match try_version() {
                     Err(failure) => {
@@ -72,11 +79,12 @@ fn search(
     }
 }
 
-// Try naively picking the highest version of each package without any
-// backtracking, to see if we're done. If this doesn't work, return the first
-// conflict.
+// Try naively picking the preferred (usually the highest) version of each
+// package without any backtracking, to see if we're done. If this doesn't work,
+// return the first conflict.
 fn cheap_attempt(
     ra: &RegistryAdapter,
+    strategy: ResolutionStrategy,
     stack_ref: &ConstraintSet,
     solution_ref: &PartialSolution,
 ) -> Result<PartialSolution, Failure> {
@@ -87,7 +95,8 @@ fn cheap_attempt(
             None => return Ok(solution),
             Some((stack_tail, package, constraint)) => {
                 let (version, path) = constraint
-                    .get_min()
+                    .iter_preferred(strategy)
+                    .next()
                     .expect("unreachable: constraints should never be empty");
                 solution = solution.insert(
                     package.clone(),
@@ -103,15 +112,46 @@ fn cheap_attempt(
     }
 }
 
-pub fn solve(reg: &Index, deps: &Dependencies) -> Result<Solution, Error> {
-    let ra = RegistryAdapter::new(reg);
-    solve_inner(&ra, &deps).map_err(|failure| Error::from_failure(&reg, &deps, &ra, failure))
+pub fn solve(
+    reg: &Index,
+    deps: &Dependencies,
+    strategy: ResolutionStrategy,
+    prereleases: PrereleasePolicy,
+) -> Result<Solution, Error> {
+    let ra = RegistryAdapter::with_prereleases(reg, prereleases);
+    solve_inner(&ra, &deps, strategy)
+        .map_err(|failure| Error::from_failure(&reg, &deps, &ra, failure))
 }
 
-fn solve_inner(ra: &RegistryAdapter, deps: &Dependencies) -> Result<Solution, Failure> {
+/// Like `solve`, but keep the dependency chain that each version was picked
+/// for.
+pub fn solve_justified(
+    reg: &Index,
+    deps: &Dependencies,
+    strategy: ResolutionStrategy,
+    prereleases: PrereleasePolicy,
+) -> Result<JustifiedSolution, Error> {
+    let ra = RegistryAdapter::with_prereleases(reg, prereleases);
+    solve_partial(&ra, deps, strategy)
+        .map(JustifiedSolution::from)
+        .map_err(|failure| Error::from_failure(reg, deps, &ra, failure))
+}
+
+fn solve_inner(
+    ra: &RegistryAdapter,
+    deps: &Dependencies,
+    strategy: ResolutionStrategy,
+) -> Result<Solution, Failure> {
+    Ok(Solution::from(solve_partial(ra, deps, strategy)?))
+}
+
+fn solve_partial(
+    ra: &RegistryAdapter,
+    deps: &Dependencies,
+    strategy: ResolutionStrategy,
+) -> Result<PartialSolution, Failure> {
     let constraint_set = ra.constraint_set_from(deps)?;
-    let partial_solution = search(&ra, constraint_set, &PartialSolution::new())?;
-    Ok(Solution::from(partial_solution))
+    search(ra, strategy, constraint_set, &PartialSolution::new())
 }
 
 fn infer_indirect_dependencies(
@@ -191,7 +231,7 @@ mod unit_test {
         );
 
         assert_eq!(
-            solve_inner(&sample_ra, &problem),
+            solve_inner(&sample_ra, &problem, ResolutionStrategy::Highest),
             Ok(solution!(
                 left_pad => "2.0.0",
                 down_pad => "1.2.0",
@@ -202,6 +242,89 @@ mod unit_test {
         );
     }
 
+    #[test]
+    fn find_lowest_solution_set() {
+        let sample_reg = sample_registry();
+        let sample_ra = RegistryAdapter::new(&sample_reg);
+
+        let problem = deps!(
+            down_pad => "^1.0.0",
+            left_pad => "^2.0.0"
+        );
+
+        assert_eq!(
+            solve_inner(&sample_ra, &problem, ResolutionStrategy::Lowest),
+            Ok(solution!(
+                left_pad => "2.0.0",
+                down_pad => "1.0.0",
+                right_pad => "2.0.0",
+                up_pad => "2.0.0"
+            ))
+        );
+    }
+
+    #[test]
+    fn justified_solution_keeps_paths() {
+        let reg = gen_registry!(
+            A => ( "1.0.0" => deps!(B => "^1") ),
+            B => ( "1.0.0" => deps!() )
+        );
+        let solution = solve_justified(
+            &reg,
+            &deps!(A => "^1"),
+            ResolutionStrategy::Highest,
+            PrereleasePolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(solution[&pkg("A")].path, Path::new());
+        assert_eq!(solution[&pkg("B")].path, path(&[("A", "1.0.0")]));
+        assert_eq!(*solution[&pkg("B")].version, ver("1.0.0"));
+    }
+
+    #[test]
+    fn prereleases_need_opt_in() {
+        let reg = gen_registry!(
+            A => (
+                "1.0.0" => deps!(),
+                "1.1.0-beta" => deps!(),
+                "2.0.0-rc.1" => deps!()
+            )
+        );
+        let solve_with = |constraint: &str, policy: PrereleasePolicy| {
+            let mut problem = Dependencies::new();
+            problem.insert(pkg("A"), range(constraint));
+            solve_inner(
+                &RegistryAdapter::with_prereleases(&reg, policy),
+                &problem,
+                ResolutionStrategy::Highest,
+            )
+        };
+
+        assert_eq!(
+            solve_with(">= 1.0.1", PrereleasePolicy::default()),
+            Err(Failure::uninhabited_constraint(
+                Arc::new(pkg("A")),
+                Arc::new(range(">= 1.0.1")),
+                Path::new(),
+            ))
+        );
+        assert_eq!(
+            solve_with("^2.0.0-rc", PrereleasePolicy::default()),
+            Ok(solution!(A => "2.0.0-rc.1"))
+        );
+        let mut opted_in = PrereleasePolicy::default();
+        opted_in.allow_package(&pkg("A"));
+        assert_eq!(
+            solve_with(">= 1.0.1 < 2", opted_in),
+            Ok(solution!(A => "1.1.0-beta"))
+        );
+        assert!(solve_with("^2.0.0-rc", PrereleasePolicy::Never).is_err());
+        assert_eq!(
+            solve_with(">= 1.0.1 < 2", PrereleasePolicy::Always),
+            Ok(solution!(A => "1.1.0-beta"))
+        );
+    }
+
     #[test]
     fn conflicting_subdependencies() {
         let sample_reg = sample_registry();
@@ -215,7 +338,7 @@ mod unit_test {
         );
 
         assert_eq!(
-            solve_inner(&sample_ra, &problem),
+            solve_inner(&sample_ra, &problem, ResolutionStrategy::Highest),
             Err(Failure::conflict(
                 Arc::new(pkg("right_pad")),
                 Constraint::new()
@@ -246,7 +369,7 @@ mod unit_test {
         let problem = deps! {
             P0 => "^1"
         };
-        solve(&reg, &problem).unwrap();
+        solve(&reg, &problem, ResolutionStrategy::Highest, PrereleasePolicy::default()).unwrap();
     }
 
     #[test]
@@ -0,0 +1,111 @@
+use crate::constraint::VersionConstraint;
+use crate::package::PackageName;
+use crate::version::Version;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// Determines whether the solver may pick pre-release versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrereleasePolicy {
+    /// Never pick a pre-release version.
+    Never,
+    /// Only pick a pre-release version if the version constraint names a
+    /// pre-release of the same base version (so `^1.0-beta` allows `1.0-rc`,
+    /// but not `1.1-beta`), or if the package was opted in, e.g. with the
+    /// `pre` option in the manifest.
+    Explicit(BTreeSet<PackageName>),
+    /// Pick pre-release versions whenever they match the version constraint.
+    Always,
+}
+
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+#[fail(display = "Expected \"never\", \"explicit\" or \"always\"")]
+pub struct InvalidPrereleasePolicy(pub String);
+
+/// Parse a policy name. Use `allow_package` to opt packages in to
+/// pre-releases under the `explicit` policy.
+impl FromStr for PrereleasePolicy {
+    type Err = InvalidPrereleasePolicy;
+
+    fn from_str(s: &str) -> Result<PrereleasePolicy, InvalidPrereleasePolicy> {
+        match s {
+            "never" => Ok(PrereleasePolicy::Never),
+            "explicit" => Ok(PrereleasePolicy::Explicit(BTreeSet::new())),
+            "always" => Ok(PrereleasePolicy::Always),
+            _ => Err(InvalidPrereleasePolicy(s.to_string())),
+        }
+    }
+}
+
+impl PrereleasePolicy {
+    pub fn allow_package(&mut self, package: &PackageName) {
+        if let PrereleasePolicy::Explicit(ref mut packages) = self {
+            packages.insert(package.clone());
+        }
+    }
+
+    /// Return true if `version` of `package` may be picked to satisfy
+    /// `constraint`. This does not check whether `constraint` contains
+    /// `version`.
+    pub fn allows(
+        &self,
+        package: &PackageName,
+        constraint: &VersionConstraint,
+        version: &Version,
+    ) -> bool {
+        if !version.has_pre() {
+            return true;
+        }
+        match self {
+            PrereleasePolicy::Never => false,
+            PrereleasePolicy::Explicit(ref packages) => {
+                packages.contains(package) || constraint.names_prerelease_of(version)
+            }
+            PrereleasePolicy::Always => true,
+        }
+    }
+}
+
+impl Default for PrereleasePolicy {
+    fn default() -> Self {
+        PrereleasePolicy::Explicit(BTreeSet::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helpers::{pkg, range, ver};
+
+    #[test]
+    fn prerelease_policy_allows() {
+        let explicit = PrereleasePolicy::default();
+        assert!(explicit.allows(&pkg("a"), &range("^1.0"), &ver("1.1")));
+        assert!(!explicit.allows(&pkg("a"), &range("^1.0"), &ver("1.1-beta")));
+        assert!(explicit.allows(&pkg("a"), &range("^1.1-alpha"), &ver("1.1-beta")));
+        assert!(!explicit.allows(&pkg("a"), &range("^1.0-alpha"), &ver("1.1-beta")));
+        assert!(explicit.allows(&pkg("a"), &range("^2 || 1.1-beta"), &ver("1.1-beta")));
+        assert!(!explicit.allows(&pkg("a"), &range("!= 1.1-beta"), &ver("1.1-beta")));
+
+        let mut opted_in = PrereleasePolicy::default();
+        opted_in.allow_package(&pkg("a"));
+        assert!(opted_in.allows(&pkg("a"), &range("^1.0"), &ver("1.1-beta")));
+        assert!(!opted_in.allows(&pkg("b"), &range("^1.0"), &ver("1.1-beta")));
+
+        let never = PrereleasePolicy::Never;
+        assert!(!never.allows(&pkg("a"), &range("1.1-beta"), &ver("1.1-beta")));
+        let always = PrereleasePolicy::Always;
+        assert!(always.allows(&pkg("a"), &range("^1.0"), &ver("1.1-beta")));
+    }
+
+    #[test]
+    fn parse_prerelease_policy() {
+        assert_eq!("never".parse(), Ok(PrereleasePolicy::Never));
+        assert_eq!("explicit".parse(), Ok(PrereleasePolicy::default()));
+        assert_eq!("always".parse(), Ok(PrereleasePolicy::Always));
+        assert_eq!(
+            "sometimes".parse::<PrereleasePolicy>(),
+            Err(InvalidPrereleasePolicy("sometimes".to_string()))
+        );
+    }
+}
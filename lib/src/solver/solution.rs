@@ -57,3 +57,18 @@ impl From<PartialSolution> for Solution {
             .collect()
     }
 }
+
+/// Like `Solution`, but remembering for each package the dependency chain that
+/// the solver picked its version for.
+pub type JustifiedSolution = BTreeMap<PackageName, JustifiedVersion>;
+
+impl From<PartialSolution> for JustifiedSolution {
+    fn from(partial_solution: PartialSolution) -> JustifiedSolution {
+        partial_solution
+            .iter()
+            .map(|(package_name, justified_version)| {
+                ((**package_name).clone(), justified_version.clone())
+            })
+            .collect()
+    }
+}
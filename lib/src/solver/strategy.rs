@@ -0,0 +1,32 @@
+use std::str::FromStr;
+
+/// Determines which of the versions matching a constraint the solver tries
+/// first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResolutionStrategy {
+    /// Prefer the newest version. This is what you want for installing.
+    #[default]
+    Highest,
+    /// Prefer the oldest version. This is useful for checking that a package's
+    /// declared lower bounds actually work.
+    Lowest,
+}
+
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+#[fail(
+    display = "Invalid resolution strategy {:?}; expected \"highest\" or \"lowest\"",
+    _0
+)]
+pub struct InvalidStrategy(pub String);
+
+impl FromStr for ResolutionStrategy {
+    type Err = InvalidStrategy;
+
+    fn from_str(s: &str) -> Result<ResolutionStrategy, InvalidStrategy> {
+        match s {
+            "highest" => Ok(ResolutionStrategy::Highest),
+            "lowest" => Ok(ResolutionStrategy::Lowest),
+            _ => Err(InvalidStrategy(s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+// Syntax checking for SPDX license expressions, like `MIT`, `GPL-2.0+` or
+// `(Apache-2.0 OR MIT) AND BSD-3-Clause`. See
+// https://spdx.github.io/spdx-spec/appendix-IV-SPDX-license-expressions/
+//
+// We don't check identifiers against the SPDX license list, which changes over
+// time; we only check that the expression is well-formed.
+
+/// How deeply parentheses may nest. The parser recurses once per level, and
+/// expressions come from untrusted publication requests.
+const MAX_NESTING: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    With,
+    Id(String),
+}
+
+fn tokenize(expression: &str) -> Vec<Token> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(|word| match word {
+            "(" => Token::Open,
+            ")" => Token::Close,
+            "AND" | "and" => Token::And,
+            "OR" | "or" => Token::Or,
+            "WITH" | "with" => Token::With,
+            _ => Token::Id(word.to_string()),
+        })
+        .collect()
+}
+
+fn is_idstring(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+fn is_license_id(s: &str) -> bool {
+    if let Some(document_ref) = s.strip_prefix("DocumentRef-") {
+        return match document_ref.find(":LicenseRef-") {
+            Some(i) => {
+                is_idstring(&document_ref[..i])
+                    && is_idstring(&document_ref[i + ":LicenseRef-".len()..])
+            }
+            None => false,
+        };
+    }
+    is_idstring(s.strip_suffix('+').unwrap_or(s))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// How many parentheses are open.
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    // expression = and-expression ("OR" and-expression)*
+    fn expression(&mut self) -> bool {
+        if !self.and_expression() {
+            return false;
+        }
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            if !self.and_expression() {
+                return false;
+            }
+        }
+        true
+    }
+
+    // and-expression = with-expression ("AND" with-expression)*
+    fn and_expression(&mut self) -> bool {
+        if !self.with_expression() {
+            return false;
+        }
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            if !self.with_expression() {
+                return false;
+            }
+        }
+        true
+    }
+
+    // with-expression = simple-expression ("WITH" exception-id)?
+    //                 | "(" expression ")"
+    fn with_expression(&mut self) -> bool {
+        match self.next() {
+            Some(Token::Open) => {
+                if self.depth == MAX_NESTING {
+                    return false;
+                }
+                self.depth += 1;
+                let valid = self.expression() && self.next() == Some(&Token::Close);
+                self.depth -= 1;
+                valid
+            }
+            Some(Token::Id(id)) if is_license_id(id) => {
+                if self.peek() == Some(&Token::With) {
+                    self.position += 1;
+                    match self.next() {
+                        Some(Token::Id(exception)) => is_idstring(exception),
+                        _ => false,
+                    }
+                } else {
+                    true
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Check that `expression` is a well-formed SPDX license expression.
+pub fn is_valid_license_expression(expression: &str) -> bool {
+    let tokens = tokenize(expression);
+    let token_count = tokens.len();
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+    parser.expression() && parser.position == token_count
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn license_expressions() {
+        for valid in &[
+            "MIT",
+            "GPL-2.0+",
+            "MIT OR Apache-2.0",
+            "(MIT OR Apache-2.0) AND BSD-3-Clause",
+            "GPL-2.0-or-later WITH Classpath-exception-2.0",
+            "LicenseRef-Proprietary",
+            "DocumentRef-spdx-tool-1.2:LicenseRef-MIT-Style-2",
+            "mit or apache-2.0",
+        ] {
+            assert!(is_valid_license_expression(valid), "{:?}", valid);
+        }
+        for invalid in &[
+            "",
+            "MIT OR",
+            "AND MIT",
+            "MIT Apache-2.0",
+            "(MIT OR Apache-2.0",
+            "MIT)",
+            "MIT WITH",
+            "GPL-2.0 WITH (Classpath-exception-2.0)",
+            "MIT/Apache-2.0",
+            "DocumentRef-tool:MIT",
+            "Mit Or Apache",
+        ] {
+            assert!(!is_valid_license_expression(invalid), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn limit_nesting() {
+        let nested = |depth| format!("{}MIT{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(is_valid_license_expression(&nested(MAX_NESTING)));
+        assert!(!is_valid_license_expression(&nested(MAX_NESTING + 1)));
+        // Deep enough to overflow the stack without the limit.
+        assert!(!is_valid_license_expression(&nested(1_000_000)));
+    }
+}
@@ -0,0 +1,362 @@
+// The registry's transparency log: an append-only Merkle tree of everything
+// that changes what a package name refers to. Clients remember the tree heads
+// they have seen and ask for consistency proofs between them, so the registry
+// can't rewrite history without being caught, and they check that the releases
+// they use are in the log with the archive digests they downloaded.
+//
+// The tree is the one from Certificate Transparency (RFC 9162, section 2.1),
+// with SHA-256. Hashes are exchanged as lowercase hex strings.
+
+use sha2::{Digest, Sha256};
+
+use crate::package::PackageName;
+use crate::version::Version;
+
+pub type Hash = [u8; 32];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogEvent {
+    Publish {
+        package: PackageName,
+        version: Version,
+        /// `digest` of the release archive.
+        digest: String,
+    },
+    /// The registry has no way to yank a release yet; whatever sets a
+    /// release's `deleted` should append this in the same transaction.
+    Yank {
+        package: PackageName,
+        version: Version,
+    },
+    AddOwner {
+        package: PackageName,
+        owner: String,
+    },
+    RemoveOwner {
+        package: PackageName,
+        owner: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    #[serde(flatten)]
+    pub event: LogEvent,
+    /// The registry user who caused the event.
+    pub actor: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// A log entry as served by the registry. The leaf is the hash of `entry`
+/// exactly as sent, so clients must hash the string before parsing it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LoggedEntry {
+    pub index: u64,
+    pub entry: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TreeHead {
+    pub tree_size: u64,
+    pub root_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    pub audit_path: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    pub proof: Vec<String>,
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn hash_from_hex(hex: &str) -> Option<Hash> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut hash = [0; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(hash)
+}
+
+/// The digest of a release archive, as recorded in `Publish` events.
+pub fn digest(tar_br: &[u8]) -> String {
+    format!("sha256:{}", to_hex(&Sha256::digest(tar_br)))
+}
+
+pub fn leaf_hash(entry: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    hasher.update(entry);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The largest power of two smaller than `n`, for `n > 1`.
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+pub fn root_hash(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest(&[]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&root_hash(&leaves[..k]), &root_hash(&leaves[k..]))
+        }
+    }
+}
+
+/// The audit path for the leaf at `index`, which must be in `leaves`.
+pub fn inclusion_proof(leaves: &[Hash], index: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return vec![];
+    }
+    let k = split(n);
+    let (mut path, sibling) = if index < k {
+        (
+            inclusion_proof(&leaves[..k], index),
+            root_hash(&leaves[k..]),
+        )
+    } else {
+        (
+            inclusion_proof(&leaves[k..], index - k),
+            root_hash(&leaves[..k]),
+        )
+    };
+    path.push(sibling);
+    path
+}
+
+/// A proof that the tree made of the first `first` leaves is a prefix of the
+/// tree made of all `leaves`.
+pub fn consistency_proof(leaves: &[Hash], first: usize) -> Vec<Hash> {
+    fn subproof(first: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+        let n = leaves.len();
+        if first == n {
+            return if complete {
+                vec![]
+            } else {
+                vec![root_hash(leaves)]
+            };
+        }
+        let k = split(n);
+        let (mut proof, sibling) = if first <= k {
+            (
+                subproof(first, &leaves[..k], complete),
+                root_hash(&leaves[k..]),
+            )
+        } else {
+            (
+                subproof(first - k, &leaves[k..], false),
+                root_hash(&leaves[..k]),
+            )
+        };
+        proof.push(sibling);
+        proof
+    }
+    if first == 0 || first > leaves.len() {
+        return vec![];
+    }
+    subproof(first, leaves, true)
+}
+
+/// Shift `a` and `b` right until the lowest bit of `a` is set or `a` is 0.
+fn shift_while_even(a: &mut u64, b: &mut u64) {
+    while *a != 0 && *a & 1 == 0 {
+        *a >>= 1;
+        *b >>= 1;
+    }
+}
+
+pub fn verify_inclusion(
+    leaf_hash: &Hash,
+    leaf_index: u64,
+    tree_size: u64,
+    audit_path: &[Hash],
+    root_hash: &Hash,
+) -> bool {
+    if leaf_index >= tree_size {
+        return false;
+    }
+    let (mut f, mut s) = (leaf_index, tree_size - 1);
+    let mut hash = *leaf_hash;
+    for sibling in audit_path {
+        if s == 0 {
+            return false;
+        }
+        if f & 1 == 1 || f == s {
+            hash = node_hash(sibling, &hash);
+            shift_while_even(&mut f, &mut s);
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    s == 0 && hash == *root_hash
+}
+
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first > second {
+        return false;
+    }
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if first == 0 {
+        // The empty tree is a prefix of every tree.
+        return proof.is_empty();
+    }
+    let mut path = proof.to_vec();
+    if first.is_power_of_two() {
+        path.insert(0, *first_root);
+    }
+    if path.is_empty() {
+        return false;
+    }
+    let (mut f, mut s) = (first - 1, second - 1);
+    while f & 1 == 1 {
+        f >>= 1;
+        s >>= 1;
+    }
+    let (mut first_hash, mut second_hash) = (path[0], path[0]);
+    for node in &path[1..] {
+        if s == 0 {
+            return false;
+        }
+        if f & 1 == 1 || f == s {
+            first_hash = node_hash(node, &first_hash);
+            second_hash = node_hash(node, &second_hash);
+            shift_while_even(&mut f, &mut s);
+        } else {
+            second_hash = node_hash(&second_hash, node);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    s == 0 && first_hash == *first_root && second_hash == *second_root
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helpers::{pkg, ver};
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n)
+            .map(|i| leaf_hash(format!("entry {}", i).as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn verify_inclusion_proofs() {
+        for n in 1..20 {
+            let leaves = leaves(n);
+            let root = root_hash(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = inclusion_proof(&leaves, i);
+                assert!(verify_inclusion(leaf, i as u64, n as u64, &proof, &root));
+                let larger_root = root_hash(&self::leaves(n + 1));
+                assert!(!verify_inclusion(
+                    leaf,
+                    i as u64,
+                    n as u64,
+                    &proof,
+                    &larger_root
+                ));
+                let other = (i + 1) % n;
+                if other != i {
+                    assert!(!verify_inclusion(
+                        &leaves[other],
+                        i as u64,
+                        n as u64,
+                        &proof,
+                        &root
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify_consistency_proofs() {
+        let all = leaves(20);
+        for n in 1..20 {
+            let root = root_hash(&all[..n]);
+            for m in 1..=n {
+                let old_root = root_hash(&all[..m]);
+                let proof = consistency_proof(&all[..n], m);
+                assert!(verify_consistency(
+                    m as u64, n as u64, &old_root, &root, &proof
+                ));
+                if m < n {
+                    // A log that changed an old entry can't prove consistency.
+                    let mut rewritten = all[..n].to_vec();
+                    rewritten[m - 1] = leaf_hash(b"rewritten");
+                    let proof = consistency_proof(&rewritten, m);
+                    let new_root = root_hash(&rewritten);
+                    assert!(!verify_consistency(
+                        m as u64, n as u64, &old_root, &new_root, &proof
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serialize_entries() {
+        let entry = LogEntry {
+            event: LogEvent::Publish {
+                package: pkg("a"),
+                version: ver("1.0.0"),
+                digest: digest(b""),
+            },
+            actor: "github:alice".to_string(),
+            timestamp: 1_500_000_000,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            json,
+            "{\"event\":\"publish\",\"package\":\"test/a\",\"version\":\"1.0.0\",\
+             \"digest\":\"sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\",\
+             \"actor\":\"github:alice\",\"timestamp\":1500000000}"
+        );
+        assert_eq!(serde_json::from_str::<LogEntry>(&json).unwrap(), entry);
+        let hash = leaf_hash(json.as_bytes());
+        assert_eq!(hash_from_hex(&to_hex(&hash)), Some(hash));
+        assert_eq!(hash_from_hex("xyz"), None);
+    }
+}
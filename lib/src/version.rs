@@ -246,7 +246,25 @@ pub fn caret_bump(v: &Version) -> Version {
     Version::new(vec![1], vec![], vec![]) // version 0
 }
 
-named!(nat<u64>, map_res!(map_res!(digit, str::from_utf8), to_u64));
+/// Increment the second component (or the first, if there is only one), drop
+/// the rest.
+///
+/// This is the effect of the tilde operator `~`.
+///
+/// Eg. `tilde_bump(1.2.3)` yields `1.3`, `tilde_bump(0.0.2)` yields `0.1`, and
+/// `tilde_bump(1)` yields `2`.
+///
+/// This drops all tags from the version.
+pub fn tilde_bump(v: &Version) -> Version {
+    let keep = if v.fields.len() >= 2 { 2 } else { 1 };
+    let mut parts = v.fields[..keep].to_vec();
+    let last = parts.len() - 1;
+    // Same overflow shortcut as in caret_bump.
+    parts[last] = parts[last].saturating_add(1);
+    Version::new(parts, vec![], vec![])
+}
+
+named!(pub nat<u64>, map_res!(map_res!(digit, str::from_utf8), to_u64));
 
 named!(pub base_version<Vec<u64>>, separated_nonempty_list!(char!('.'), nat));
 
@@ -428,6 +446,16 @@ mod unit_test {
         caret_bump(&ver("18446744073709551615"));
     }
 
+    #[test]
+    fn test_tilde_bump() {
+        assert_eq!(tilde_bump(&ver("1.2.3")), ver("1.3"));
+        assert_eq!(tilde_bump(&ver("1.2")), ver("1.3"));
+        assert_eq!(tilde_bump(&ver("1")), ver("2"));
+        assert_eq!(tilde_bump(&ver("0.0.2")), ver("0.1"));
+        assert_eq!(tilde_bump(&ver("1.2.3-beta2+lol")), ver("1.3"));
+        tilde_bump(&ver("1.18446744073709551615"));
+    }
+
     #[test]
     fn parse_nat() {
         assert_eq!(nat(b"1"), Done(&b""[..], 1));
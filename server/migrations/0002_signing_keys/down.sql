@@ -0,0 +1,5 @@
+ALTER TABLE files
+  DROP COLUMN signature,
+  DROP COLUMN signing_key;
+
+DROP TABLE signing_keys;
@@ -0,0 +1,17 @@
+-- Release signing
+
+CREATE TABLE signing_keys (
+  -- Base64 encoded ed25519 public key. A key belongs to a single user.
+  public_key TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  added_time TIMESTAMP NOT NULL DEFAULT NOW(),
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX signing_keys_by_user_id ON signing_keys (user_id);
+
+-- The signature covers the data, so it lives next to it. Recompressing a file
+-- invalidates its signature, so new revisions must leave these NULL.
+ALTER TABLE files
+  ADD COLUMN signature TEXT,
+  ADD COLUMN signing_key TEXT;
@@ -0,0 +1,2 @@
+DROP TABLE transparency_log;
+DROP FUNCTION transparency_log_append_only();
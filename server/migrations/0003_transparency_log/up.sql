@@ -0,0 +1,28 @@
+-- Transparency log
+
+CREATE TABLE transparency_log (
+  -- The leaf index in the Merkle tree, counting from 0 without gaps.
+  id BIGINT PRIMARY KEY,
+  -- The JSON entry, exactly as it was hashed.
+  entry TEXT NOT NULL,
+  leaf_hash BYTEA NOT NULL,
+  -- For looking up the publication of a release.
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT
+);
+
+CREATE INDEX transparency_log_by_release ON transparency_log (namespace, name, version);
+
+CREATE FUNCTION transparency_log_append_only() RETURNS trigger
+  LANGUAGE plpgsql
+  AS $$
+BEGIN
+  RAISE EXCEPTION 'transparency_log is append-only';
+END;
+$$;
+
+CREATE TRIGGER transparency_log_append_only_trigger
+  BEFORE UPDATE OR DELETE ON transparency_log
+  FOR EACH ROW
+  EXECUTE PROCEDURE transparency_log_append_only();
@@ -0,0 +1,5 @@
+-- This fails if any archives have been moved out of the database.
+ALTER TABLE files
+  DROP CONSTRAINT files_data_or_storage_key,
+  ALTER COLUMN data SET NOT NULL,
+  DROP COLUMN storage_key;
@@ -0,0 +1,10 @@
+-- Artifact storage
+
+-- Archives are now kept in artifact storage (see src/storage.rs) under
+-- storage_key. data is only set for archives from before this migration that
+-- `pm_server migrate-artifacts` hasn't moved yet.
+ALTER TABLE files
+  ADD COLUMN storage_key TEXT,
+  ALTER COLUMN data DROP NOT NULL,
+  ADD CONSTRAINT files_data_or_storage_key
+    CHECK (data IS NOT NULL OR storage_key IS NOT NULL);
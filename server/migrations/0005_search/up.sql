@@ -0,0 +1,4 @@
+-- Search
+
+-- Packages are ranked by search.rs now, the same way for both backends.
+DROP FUNCTION package_search(TEXT, TEXT[]);
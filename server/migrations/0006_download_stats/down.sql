@@ -0,0 +1,3 @@
+ALTER TABLE package_releases DROP COLUMN downloads;
+
+DROP TABLE release_downloads;
@@ -0,0 +1,18 @@
+-- Download statistics
+
+-- How many times each release was downloaded on each day (in UTC). The server
+-- adds downloads up in memory and writes them out every so often; see
+-- src/downloads.rs.
+CREATE TABLE release_downloads (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  day DATE NOT NULL,
+  downloads BIGINT NOT NULL,
+  PRIMARY KEY (namespace, name, version, day),
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases (namespace, name, version)
+);
+
+-- The total of release_downloads for each release, so that search doesn't
+-- have to add up every day.
+ALTER TABLE package_releases ADD COLUMN downloads BIGINT NOT NULL DEFAULT 0;
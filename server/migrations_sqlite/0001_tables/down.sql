@@ -0,0 +1,7 @@
+DROP TABLE files;
+DROP TABLE release_dependencies;
+DROP TABLE package_releases;
+DROP TABLE package_owners;
+DROP TABLE packages;
+DROP TABLE users;
+DROP TABLE login_sessions;
@@ -0,0 +1,112 @@
+-- The SQLite counterpart of migrations/0001_tables. Timestamps are stored as
+-- text in UTC, the way Diesel writes them.
+
+-- Sessions
+
+CREATE TABLE login_sessions (
+  token TEXT PRIMARY KEY,
+  callback TEXT NOT NULL,
+  stamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER expire_login_sessions_trigger
+  AFTER INSERT ON login_sessions
+BEGIN
+  DELETE FROM login_sessions WHERE stamp < datetime('now', '-30 minutes');
+END;
+
+-- Packages
+
+CREATE TABLE users (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL,
+  email TEXT NOT NULL,
+  avatar TEXT
+);
+
+CREATE TABLE packages (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  deleted TEXT,
+  deleted_on TIMESTAMP,
+  PRIMARY KEY (namespace, name)
+);
+
+CREATE TABLE package_owners (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  added_time TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (namespace, name, user_id),
+  FOREIGN KEY (namespace, name) REFERENCES packages (namespace, name),
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX package_owners_by_package_id ON package_owners (namespace, name);
+CREATE INDEX package_owners_by_user_id ON package_owners (user_id);
+
+CREATE TABLE package_releases (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+
+  -- Metadata
+  description TEXT NOT NULL,
+  -- JSON arrays of strings
+  authors TEXT NOT NULL DEFAULT '[]',
+  keywords TEXT NOT NULL DEFAULT '[]',
+  homepage_url TEXT,
+  repository_type TEXT,
+  repository_url TEXT,
+  bugs_url TEXT,
+
+  license TEXT,
+  license_file_name TEXT,
+  license_file_contents TEXT,
+
+  manifest_file_name TEXT,
+  manifest_file_contents TEXT,
+
+  readme_name TEXT,
+  readme_contents TEXT,
+
+  -- Bookkeeping
+  publisher TEXT NOT NULL,
+  publish_time TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  deleted TEXT,
+  deleted_on TIMESTAMP,
+
+  PRIMARY KEY (namespace, name, version),
+  FOREIGN KEY (namespace, name) REFERENCES packages (namespace, name),
+  FOREIGN KEY (publisher) REFERENCES users (id)
+);
+
+CREATE INDEX package_releases_by_package_id ON package_releases (namespace, name);
+CREATE INDEX package_releases_by_license ON package_releases (license);
+CREATE INDEX package_releases_by_publisher ON package_releases (publisher);
+
+CREATE TABLE release_dependencies (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  ordering INTEGER NOT NULL,
+  dependency_namespace TEXT NOT NULL,
+  dependency_name TEXT NOT NULL,
+  dependency_version_constraint TEXT NOT NULL,
+  PRIMARY KEY (namespace, name, version, dependency_namespace, dependency_name),
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases
+);
+
+CREATE TABLE files (
+  -- AUTOINCREMENT never reuses ids, so the highest id for a release is always
+  -- the most recent file, as with the BIGSERIAL in Postgres.
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  data BLOB NOT NULL,
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases (namespace, name, version)
+);
+CREATE INDEX files_by_version ON files (namespace, name, version, id);
+
+-- There is no full text search function like package_search; see search.rs.
@@ -0,0 +1,4 @@
+ALTER TABLE files DROP COLUMN signing_key;
+ALTER TABLE files DROP COLUMN signature;
+
+DROP TABLE signing_keys;
@@ -0,0 +1,14 @@
+-- Release signing
+
+CREATE TABLE signing_keys (
+  -- Base64 encoded ed25519 public key. A key belongs to a single user.
+  public_key TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  added_time TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX signing_keys_by_user_id ON signing_keys (user_id);
+
+ALTER TABLE files ADD COLUMN signature TEXT;
+ALTER TABLE files ADD COLUMN signing_key TEXT;
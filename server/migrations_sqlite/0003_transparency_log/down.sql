@@ -0,0 +1 @@
+DROP TABLE transparency_log;
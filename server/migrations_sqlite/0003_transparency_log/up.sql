@@ -0,0 +1,27 @@
+-- Transparency log
+
+CREATE TABLE transparency_log (
+  -- The leaf index in the Merkle tree, counting from 0 without gaps.
+  id BIGINT PRIMARY KEY,
+  -- The JSON entry, exactly as it was hashed.
+  entry TEXT NOT NULL,
+  leaf_hash BLOB NOT NULL,
+  -- For looking up the publication of a release.
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT
+);
+
+CREATE INDEX transparency_log_by_release ON transparency_log (namespace, name, version);
+
+CREATE TRIGGER transparency_log_no_update
+  BEFORE UPDATE ON transparency_log
+BEGIN
+  SELECT RAISE(ABORT, 'transparency_log is append-only');
+END;
+
+CREATE TRIGGER transparency_log_no_delete
+  BEFORE DELETE ON transparency_log
+BEGIN
+  SELECT RAISE(ABORT, 'transparency_log is append-only');
+END;
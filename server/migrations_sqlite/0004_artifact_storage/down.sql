@@ -0,0 +1,16 @@
+-- This fails if any archives have been moved out of the database.
+CREATE TABLE old_files (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  data BLOB NOT NULL,
+  signature TEXT,
+  signing_key TEXT,
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases (namespace, name, version)
+);
+INSERT INTO old_files (id, namespace, name, version, data, signature, signing_key)
+  SELECT id, namespace, name, version, data, signature, signing_key FROM files;
+DROP TABLE files;
+ALTER TABLE old_files RENAME TO files;
+CREATE INDEX files_by_version ON files (namespace, name, version, id);
@@ -0,0 +1,21 @@
+-- Artifact storage
+
+-- SQLite can't change column constraints, so the files table is rebuilt with
+-- data nullable and the new storage_key column. See the Postgres migration.
+CREATE TABLE new_files (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  data BLOB,
+  signature TEXT,
+  signing_key TEXT,
+  storage_key TEXT,
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases (namespace, name, version),
+  CONSTRAINT files_data_or_storage_key CHECK (data IS NOT NULL OR storage_key IS NOT NULL)
+);
+INSERT INTO new_files (id, namespace, name, version, data, signature, signing_key)
+  SELECT id, namespace, name, version, data, signature, signing_key FROM files;
+DROP TABLE files;
+ALTER TABLE new_files RENAME TO files;
+CREATE INDEX files_by_version ON files (namespace, name, version, id);
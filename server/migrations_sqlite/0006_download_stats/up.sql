@@ -0,0 +1,13 @@
+-- Download statistics
+
+CREATE TABLE release_downloads (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  version TEXT NOT NULL,
+  day DATE NOT NULL,
+  downloads BIGINT NOT NULL,
+  PRIMARY KEY (namespace, name, version, day),
+  FOREIGN KEY (namespace, name, version) REFERENCES package_releases (namespace, name, version)
+);
+
+ALTER TABLE package_releases ADD COLUMN downloads BIGINT NOT NULL DEFAULT 0;
@@ -1,3 +1,4 @@
+use std::env;
 use std::fmt;
 use std::str::FromStr;
 
@@ -66,11 +67,27 @@ pub trait AuthProvider {
     fn orgs(&self, token: &str) -> Res<Box<dyn Iterator<Item = OrgRecord>>>;
 }
 
+/// Whether `ENABLE_TEST_AUTH` is set to `1` or `true`; any other value, like
+/// `0`, leaves it off. Test users can be impersonated by anyone who knows
+/// their name, so only enable it on a throwaway registry.
+pub fn test_auth_enabled() -> bool {
+    env::var("ENABLE_TEST_AUTH").map_or(false, |value| value == "1" || value == "true")
+}
+
+/// The provider for `test` users. It has no users unless test authentication
+/// is enabled, in which case every token is the name of a valid user.
 pub struct NullAuth;
 
 impl AuthProvider for NullAuth {
-    fn user(&self, _: &str) -> Res<UserRecord> {
-        Err(Error::UnknownUser("null auth has no users".to_string()))
+    fn user(&self, token: &str) -> Res<UserRecord> {
+        if !test_auth_enabled() {
+            return Err(Error::UnknownUser("null auth has no users".to_string()));
+        }
+        if token.is_empty() || token.contains(':') {
+            return Err(Error::InvalidUserID(token.to_string()));
+        }
+        let user = User::new(AuthSource::Test, token);
+        Ok(UserRecord::new(&user, token, &format!("{}@example.com", token), ""))
     }
 
     fn orgs(&self, _: &str) -> Res<Box<dyn Iterator<Item = OrgRecord>>> {
@@ -0,0 +1,124 @@
+// Download statistics. Serving an archive only counts the download in
+// memory, and a background thread adds the counts to the database every
+// `DOWNLOAD_FLUSH_INTERVAL` seconds (60 by default), so that downloads never
+// wait for the database. Counts which haven't been written yet are lost when
+// the server stops.
+
+use std::collections::HashMap;
+use std::env;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use diesel::prelude::*;
+
+use crate::error::Error;
+use crate::schema::{package_releases, release_downloads};
+use crate::store::{DbConnection, Pool};
+
+/// A release's namespace, name and version, and a day.
+type Key = (String, String, String, NaiveDate);
+
+/// The downloads counted since they were last written to the database.
+#[derive(Clone, Default)]
+pub struct Downloads(Arc<Mutex<HashMap<Key, i64>>>);
+
+impl Downloads {
+    pub fn count(&self, namespace: &str, name: &str, version: &str) {
+        let key = (
+            namespace.to_string(),
+            name.to_string(),
+            version.to_string(),
+            Utc::now().naive_utc().date(),
+        );
+        *self.0.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    /// Add the downloads counted so far to the database. If that fails, they
+    /// are kept to try again.
+    pub fn flush(&self, db: &DbConnection) -> Result<(), Error> {
+        let counts = mem::replace(&mut *self.0.lock().unwrap(), HashMap::new());
+        if counts.is_empty() {
+            return Ok(());
+        }
+        let result = db.transaction::<_, Error, _>(|| {
+            for ((namespace, name, version, day), count) in &counts {
+                add_downloads(db, namespace, name, version, *day, *count)?;
+            }
+            Ok(())
+        });
+        if result.is_err() {
+            let mut pending = self.0.lock().unwrap();
+            for (key, count) in counts {
+                *pending.entry(key).or_insert(0) += count;
+            }
+        }
+        result
+    }
+
+    /// Flush the counts from a background thread, every
+    /// `DOWNLOAD_FLUSH_INTERVAL` seconds.
+    pub fn flush_in_background(&self, pool: Pool) {
+        let interval = match env::var("DOWNLOAD_FLUSH_INTERVAL") {
+            Ok(seconds) => seconds
+                .parse()
+                .expect("DOWNLOAD_FLUSH_INTERVAL must be a number of seconds"),
+            Err(_) => 60,
+        };
+        let downloads = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval));
+            let result = match pool.get() {
+                Ok(db) => downloads.flush(&db),
+                Err(err) => {
+                    println!("error: no database connection to record downloads: {}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = result {
+                println!("error: recording downloads failed: {:?}", err);
+            }
+        });
+    }
+}
+
+fn add_downloads(
+    db: &DbConnection,
+    namespace: &str,
+    name: &str,
+    version: &str,
+    day: NaiveDate,
+    count: i64,
+) -> Result<(), Error> {
+    // Releases are rarely deleted outright, but one might have been since its
+    // downloads were counted.
+    let updated = diesel::update(package_releases::table.find((namespace, name, version)))
+        .set(package_releases::downloads.eq(package_releases::downloads + count))
+        .execute(db)?;
+    if updated == 0 {
+        return Ok(());
+    }
+    let release = release_downloads::namespace
+        .eq(namespace)
+        .and(release_downloads::name.eq(name))
+        .and(release_downloads::version.eq(version));
+    let updated = diesel::update(
+        release_downloads::table.filter(release.and(release_downloads::day.eq(day))),
+    )
+    .set(release_downloads::downloads.eq(release_downloads::downloads + count))
+    .execute(db)?;
+    if updated == 0 {
+        diesel::insert_into(release_downloads::table)
+            .values((
+                release_downloads::namespace.eq(namespace),
+                release_downloads::name.eq(name),
+                release_downloads::version.eq(version),
+                release_downloads::day.eq(day),
+                release_downloads::downloads.eq(count),
+            ))
+            .execute(db)?;
+    }
+    Ok(())
+}
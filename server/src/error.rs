@@ -10,6 +10,9 @@ use rocket::response::{Response, Responder};
 use rocket::request::Request;
 use diesel;
 
+use pm_lib::archive::ArchiveError;
+use pm_lib::publication_request::MetadataError;
+
 use crate::user::User;
 
 quick_error! {
@@ -87,24 +90,83 @@ quick_error! {
         UnknownRelease(namespace: String, name: String, version: String) {
             display("No such package version: {}/{}-{}", namespace, name, version)
         }
+        UnknownArtifact(key: String) {
+            display("No such artifact in storage: {}", key)
+        }
+        StorageFailure(message: String) {
+            display("Artifact storage error: {}", message)
+        }
         AccessDenied(namespace: String, name: String, user: User) {
             display("User {} is not an owner of {}/{}", user, name, namespace)
         }
         InvalidManifest(reason: &'static str) {
             display("Invalid manifest: {}", reason)
         }
-        InvalidArtifact(reason: &'static str) {
-            display("Invalid upload artifact: {}", reason)
+        InvalidMetadata(code: &'static str, message: String) {
+            display("Invalid package metadata: {}", message)
+            from(err: MetadataError) -> (err.code, err.message)
+        }
+        InvalidArchive(err: ArchiveError) {
+            display("Invalid upload artifact: {}", err)
+            from()
+        }
+        PublicationTooLarge(limit: u64) {
+            display("The publication request is larger than {} bytes", limit)
         }
         ReleaseAlreadyExists(namespace: String, name: String, version: String) {
             display("This release already exists: {}/{}-{}", namespace, name, version)
         }
+        InvalidSigningKey(key: String) {
+            display("Not a valid ed25519 public key: {}", key)
+        }
+        SigningKeyInUse(key: String) {
+            display("Signing key {} is registered to another user", key)
+        }
+        UnknownSigningKey(key: String, user: User) {
+            display("Signing key {} is not registered to {}", key, user)
+        }
+        InvalidQuery(message: String) {
+            display("Invalid query: {}", message)
+        }
     }
 }
 
 #[derive(Serialize)]
 struct ServerError {
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+}
+
+impl Error {
+    /// A stable identifier for errors that clients may want to handle or
+    /// display specially.
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Error::InvalidMetadata(code, _) => Some(*code),
+            Error::InvalidArchive(_) => Some("invalid_archive"),
+            Error::PublicationTooLarge(_) => Some("publication_too_large"),
+            Error::InvalidSigningKey(_) => Some("invalid_signing_key"),
+            Error::SigningKeyInUse(_) => Some("signing_key_in_use"),
+            Error::UnknownSigningKey(..) => Some("unknown_signing_key"),
+            Error::InvalidQuery(_) => Some("invalid_query"),
+            _ => None,
+        }
+    }
+
+    fn status(&self) -> Status {
+        match self {
+            Error::InvalidMetadata(..)
+            | Error::InvalidArchive(_)
+            | Error::InvalidSigningKey(_)
+            | Error::UnknownSigningKey(..) => Status::UnprocessableEntity,
+            Error::SigningKeyInUse(_) => Status::Conflict,
+            Error::PublicationTooLarge(_) => Status::PayloadTooLarge,
+            Error::InvalidQuery(_) => Status::BadRequest,
+            Error::UnknownPackage(..) | Error::UnknownRelease(..) => Status::NotFound,
+            _ => Status::InternalServerError,
+        }
+    }
 }
 
 impl<'a> Responder<'a> for Error {
@@ -114,14 +176,17 @@ impl<'a> Responder<'a> for Error {
             // TODO real logging?
             _ => {
                 println!("error: {:?}", self);
-                let data = serde_json::to_vec(&ServerError { message: format!("{}", self) })
-                    .unwrap_or_else(|_|
-                        "{message:\"an error occurred but I couldn't serialise it for you\"}"
-                            .as_bytes()
-                            .to_owned(),
-                    );
+                let data = serde_json::to_vec(&ServerError {
+                    message: format!("{}", self),
+                    code: self.code(),
+                })
+                .unwrap_or_else(|_|
+                    "{message:\"an error occurred but I couldn't serialise it for you\"}"
+                        .as_bytes()
+                        .to_owned(),
+                );
                 Response::build()
-                    .status(Status::InternalServerError)
+                    .status(self.status())
                     .header(ContentType::JSON)
                     .sized_body(Cursor::new(data))
                     .ok()
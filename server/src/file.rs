@@ -7,5 +7,8 @@ pub struct File {
     pub namespace: String,
     pub name: String,
     pub version: String,
-    pub data: Vec<u8>,
+    pub data: Option<Vec<u8>>,
+    pub signature: Option<String>,
+    pub signing_key: Option<String>,
+    pub storage_key: Option<String>,
 }
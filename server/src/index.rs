@@ -1,67 +1,153 @@
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 
 use pm_lib::constraint::VersionConstraint;
 use pm_lib::index;
-use pm_lib::index::Index;
+use pm_lib::index::{Index, Publishers, RegistryIndex, Signatures, SignedRelease};
 use pm_lib::package::PackageName;
+use pm_lib::signing::ReleaseSignature;
 use pm_lib::version::Version;
 
 use crate::package;
-use crate::schema::{package_releases, packages, release_dependencies};
+use crate::schema::{files, package_releases, packages, release_dependencies};
 
-use crate::store::Store;
+use crate::store::{DbConnection, Store};
 
-pub fn compute_index(store: &Store) -> Result<Index, ::failure::Error> {
-    let mut index = Index::new();
+/// The index, publishers and signatures, read in one transaction so that
+/// they agree.
+pub fn compute_registry_index(store: &Store) -> Result<RegistryIndex, ::failure::Error> {
     let db = store.db();
-    db.build_transaction()
-        .serializable()
-        .run::<_, ::failure::Error, _>(|| {
-            let package_names = packages::table
-                .select((packages::namespace, packages::name))
-                .get_results::<(String, String)>(db)?;
-            for (namespace, name) in package_names {
-                index.insert(PackageName { namespace, name }, index::Package::new());
-            }
+    store.serializable_transaction::<_, ::failure::Error, _>(|| {
+        let publishers = package_releases::table
+            .select((
+                package_releases::namespace,
+                package_releases::name,
+                package_releases::version,
+                package_releases::publisher,
+            ))
+            .get_results::<(String, String, String, String)>(db)?
+            .into_iter()
+            .map(|(namespace, name, version, publisher)| ((namespace, name, version), publisher))
+            .collect();
+        Ok(RegistryIndex {
+            packages: load_index(db)?,
+            signatures: load_signatures(db, &publishers)?,
+            publishers: index_publishers(publishers),
+        })
+    })
+}
+
+fn index_publishers(publishers: HashMap<(String, String, String), String>) -> Publishers {
+    let mut index = Publishers::new();
+    for ((namespace, name, version), publisher) in publishers {
+        index
+            .entry(PackageName { namespace, name })
+            .or_insert_with(Default::default)
+            .insert(
+                Version::from_str(&version).expect("invalid version"),
+                publisher,
+            );
+    }
+    index
+}
 
-            let releases = package_releases::table
-                .select((
-                    package_releases::namespace,
-                    package_releases::name,
-                    package_releases::version,
-                ))
-                .get_results::<(String, String, String)>(db)?;
-            for (namespace, name, version) in releases {
-                let package = index
-                    .get_mut(&PackageName { namespace, name })
-                    .expect("orphaned release");
-                package.insert(
-                    Version::from_str(&version).expect("invalid version"),
-                    index::Dependencies::new(),
+/// The signatures of the releases in `publishers`, which maps a release's
+/// namespace, name and version to its publisher.
+fn load_signatures(
+    db: &DbConnection,
+    publishers: &HashMap<(String, String, String), String>,
+) -> Result<Signatures, ::failure::Error> {
+    // We serve the most recent file for each release, so only its signature
+    // counts. Files without a signature remove any earlier one.
+    let files = files::table
+        .select((
+            files::namespace,
+            files::name,
+            files::version,
+            files::signature,
+            files::signing_key,
+        ))
+        .order(files::id)
+        .get_results::<(String, String, String, Option<String>, Option<String>)>(db)?;
+    let mut signatures = Signatures::new();
+    for (namespace, name, version, signature, signing_key) in files {
+        let publisher = match publishers.get(&(namespace.clone(), name.clone(), version.clone())) {
+            Some(publisher) => publisher.clone(),
+            None => continue,
+        };
+        let releases = signatures
+            .entry(PackageName { namespace, name })
+            .or_insert_with(Default::default);
+        let version = Version::from_str(&version).expect("invalid version");
+        match (signature, signing_key) {
+            (Some(signature), Some(public_key)) => {
+                releases.insert(
+                    version,
+                    SignedRelease {
+                        publisher,
+                        signature: ReleaseSignature {
+                            public_key,
+                            signature,
+                        },
+                    },
                 );
             }
-
-            let dependencies =
-                release_dependencies::table.get_results::<package::Dependency>(db)?;
-            for dependency in dependencies {
-                let package = index
-                    .get_mut(&PackageName {
-                        namespace: dependency.namespace.clone(),
-                        name: dependency.name.clone(),
-                    })
-                    .expect("orphaned dependency (package key)");
-                let release = package
-                    .get_mut(&Version::from_str(&dependency.version).expect("invalid version"))
-                    .expect("orphaned dependency (version key)");
-                let dep_name = PackageName {
-                    namespace: dependency.dependency_namespace.clone(),
-                    name: dependency.dependency_name.clone(),
-                };
-                let vc = VersionConstraint::from_str(&dependency.dependency_version_constraint)
-                    .expect("invalid version constraint");
-                release.insert(dep_name, vc);
+            _ => {
+                releases.remove(&version);
             }
-            Ok(())
-        })?;
+        }
+    }
+    signatures.retain(|_, releases| !releases.is_empty());
+    Ok(signatures)
+}
+
+/// Read the index. Call this in a transaction, or the releases and
+/// dependencies may not belong together.
+fn load_index(db: &DbConnection) -> Result<Index, ::failure::Error> {
+    let mut index = Index::new();
+    let package_names = packages::table
+        .select((packages::namespace, packages::name))
+        .get_results::<(String, String)>(db)?;
+    for (namespace, name) in package_names {
+        index.insert(PackageName { namespace, name }, index::Package::new());
+    }
+
+    let releases = package_releases::table
+        .select((
+            package_releases::namespace,
+            package_releases::name,
+            package_releases::version,
+        ))
+        .get_results::<(String, String, String)>(db)?;
+    for (namespace, name, version) in releases {
+        let package = index
+            .get_mut(&PackageName { namespace, name })
+            .expect("orphaned release");
+        package.insert(
+            Version::from_str(&version).expect("invalid version"),
+            index::Dependencies::new(),
+        );
+    }
+
+    let dependencies = release_dependencies::table.get_results::<package::Dependency>(db)?;
+    for dependency in dependencies {
+        let package = index
+            .get_mut(&PackageName {
+                namespace: dependency.namespace.clone(),
+                name: dependency.name.clone(),
+            })
+            .expect("orphaned dependency (package key)");
+        let release = package
+            .get_mut(&Version::from_str(&dependency.version).expect("invalid version"))
+            .expect("orphaned dependency (version key)");
+        let dep_name = PackageName {
+            namespace: dependency.dependency_namespace.clone(),
+            name: dependency.dependency_name.clone(),
+        };
+        let vc = VersionConstraint::from_str(&dependency.dependency_version_constraint)
+            .expect("invalid version constraint");
+        release.insert(dep_name, vc);
+    }
     Ok(index)
 }
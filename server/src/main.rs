@@ -12,103 +12,61 @@ extern crate rocket_contrib;
 extern crate quick_error;
 #[macro_use]
 extern crate diesel;
+#[cfg(feature = "sqlite")]
+#[macro_use]
+extern crate diesel_migrations;
 
 mod auth;
+mod downloads;
 mod error;
 mod file;
 mod github;
 mod gitlab;
 mod index;
 mod package;
+mod package_info;
+mod pagination;
+#[cfg_attr(feature = "sqlite", path = "schema_sqlite.rs")]
 mod schema;
 mod search;
+mod sql_types;
+mod storage;
 mod store;
+mod transparency_log;
 mod upload;
 mod user;
+mod web;
 
 use std::collections::HashMap;
 use std::env;
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 
 use rocket::config::{Config, Environment, Value};
-use rocket::http::{ContentType, Status};
+use rocket::fairing::AdHoc;
+use rocket::http::{ContentType, Header, Status};
 use rocket::request::{Form, FromRequest, Request};
 use rocket::response::{content, Redirect, Response};
-use rocket::{Data, Outcome};
+use rocket::{Data, Outcome, State};
 use rocket_contrib::json::Json;
 
+use diesel::Connection;
 use url::Url;
 
-use crate::auth::{AuthProvider, AuthToken};
+use pm_lib::archive::ArchiveLimits;
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::package_info::{DependentsPage, PackageInfo, ReleaseInfo};
+use pm_lib::search::{SearchQuery, SearchResults};
+use pm_lib::transparency_log::{ConsistencyProof, InclusionProof, LoggedEntry, TreeHead};
+
+use crate::auth::{test_auth_enabled, AuthProvider, AuthToken, NullAuth};
+use crate::downloads::Downloads;
 use crate::error::{Error, Res};
 use crate::github::{Github, GITHUB_CLIENT_ID};
 use crate::gitlab::{Gitlab, GITLAB_CLIENT_ID};
-use crate::store::Store;
-
-static STYLES: &str = "
-body {
-    background: white;
-    color: black;
-    margin: 0 4em;
-    padding: 0;
-    text-align: center;
-    font-size: 1em;
-    font-family: serif;
-}
-
-h1 {
-    background: red;
-    color: yellow;
-    width: 100%;
-    padding: 0.5em 0;
-}
-
-.btn {
-    background: #3498db;
-    background-image: -webkit-linear-gradient(top, #3498db, #2980b9);
-    background-image: -moz-linear-gradient(top, #3498db, #2980b9);
-    background-image: -ms-linear-gradient(top, #3498db, #2980b9);
-    background-image: -o-linear-gradient(top, #3498db, #2980b9);
-    background-image: linear-gradient(to bottom, #3498db, #2980b9);
-    -webkit-border-radius: 4;
-    -moz-border-radius: 4;
-    border-radius: 4px;
-    font-family: sans-serif;
-    color: #ffffff;
-    font-size: 20px;
-    padding: 10px 20px 10px 20px;
-    text-decoration: none;
-}
-
-.btn:hover {
-    background: #3cb0fd;
-    background-image: -webkit-linear-gradient(top, #3cb0fd, #3498db);
-    background-image: -moz-linear-gradient(top, #3cb0fd, #3498db);
-    background-image: -ms-linear-gradient(top, #3cb0fd, #3498db);
-    background-image: -o-linear-gradient(top, #3cb0fd, #3498db);
-    background-image: linear-gradient(to bottom, #3cb0fd, #3498db);
-    text-decoration: none;
-}
-
-.pad { padding: 1em; }
-";
-
-fn html_doc(content: &str) -> content::Html<String> {
-    content::Html(format!(
-        "<!doctype html>
-<html>
-  <head>
-    <style>{}</style>
-  </head>
-  <body>
-    <h1>☭ People's Revolutionary Package Registry ☭</h1>
-    {}
-  </body>
-</html>
-",
-        STYLES, content
-    ))
-}
+use crate::pagination::Page;
+use crate::storage::ArtifactStorage;
+use crate::store::{Artifact, DbConnection, Store};
+use crate::web::html_doc;
 
 fn parse_auth_header(header: &str) -> Option<&str> {
     let start = "Bearer ";
@@ -159,38 +117,170 @@ impl Authenticate {
 }
 
 #[derive(FromForm)]
-struct SearchQuery {
-    ns: String,
-    q: String,
+struct SearchParams {
+    q: Option<String>,
+    ns: Option<String>,
+    page: Option<usize>,
+    limit: Option<usize>,
 }
 
 #[get("/files/tar-br/<namespace>/<name>/<version>")]
-fn files(store: Store, namespace: String, name: String, version: String) -> Res<Response<'static>> {
-    match store.get_tar_br(&namespace, &name, &version) {
-        Err(_) => Err(Error::Status(Status::NotFound)),
-        Ok(tar_br) => Response::build()
-            .status(Status::Ok)
-            .header(ContentType::new("application", "brotli"))
-            .sized_body(Cursor::new(tar_br))
-            .ok(),
-    }
+fn files(
+    store: Store,
+    storage: State<Box<dyn ArtifactStorage>>,
+    downloads: State<Downloads>,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<Response<'static>> {
+    let artifact = match store.get_artifact(&namespace, &name, &version) {
+        Err(Error::UnknownRelease(..)) => return Err(Error::Status(Status::NotFound)),
+        result => result?,
+    };
+    downloads.count(&namespace, &name, &version);
+    let tar_br: Box<dyn Read> = match artifact {
+        Artifact::Inline(data) => Box::new(Cursor::new(data)),
+        Artifact::Stored(key) => {
+            if let Some(url) = storage.download_url(&key)? {
+                return Response::build()
+                    .status(Status::Found)
+                    .header(Header::new("Location", url))
+                    .ok();
+            }
+            storage.get(&key)?
+        }
+    };
+    Response::build()
+        .status(Status::Ok)
+        .header(ContentType::new("application", "brotli"))
+        .streamed_body(tar_br)
+        .ok()
 }
 
-#[get("/search?<query..>")]
-fn search(query: Form<SearchQuery>, store: Store) -> Res<Json<Vec<search::SearchResult>>> {
+#[get("/search?<params..>")]
+fn search(params: Form<SearchParams>, store: Store) -> Res<Json<SearchResults>> {
     Ok(Json(search::search(
         &store,
-        &query.ns,
-        query.q.split_whitespace().map(str::to_string).collect(),
+        &search::namespaces(params.ns.as_ref().map_or("", String::as_str)),
+        &SearchQuery::parse(params.q.as_ref().map_or("", String::as_str)),
+        Page::new(params.page, params.limit)?,
     )?))
 }
 
+#[get("/api/packages/<namespace>/<name>")]
+fn api_package(store: Store, namespace: String, name: String) -> Res<Json<PackageInfo>> {
+    Ok(Json(package_info::package_info(&store, &namespace, &name)?))
+}
+
+// Ranked below `api_dependents`, so that isn't taken for a version.
+#[get("/api/packages/<namespace>/<name>/<version>", rank = 2)]
+fn api_release(
+    store: Store,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<Json<ReleaseInfo>> {
+    Ok(Json(package_info::release_info(
+        &store, &namespace, &name, &version,
+    )?))
+}
+
+#[get("/api/packages/<namespace>/<name>/dependents?<range>&<page>&<limit>")]
+fn api_dependents(
+    store: Store,
+    namespace: String,
+    name: String,
+    range: Option<String>,
+    page: Option<usize>,
+    limit: Option<usize>,
+) -> Res<Json<DependentsPage>> {
+    let range = match range {
+        Some(range) => Some(
+            VersionConstraint::from_str(&range)
+                .ok_or_else(|| Error::InvalidQuery(format!("invalid version range {:?}", range)))?,
+        ),
+        None => None,
+    };
+    Ok(Json(package_info::dependents_page(
+        &store,
+        &namespace,
+        &name,
+        range.as_ref(),
+        Page::new(page, limit)?,
+    )?))
+}
+
+#[get("/packages?<ns>&<q>&<page>")]
+fn search_page(
+    store: Store,
+    ns: Option<String>,
+    q: Option<String>,
+    page: Option<usize>,
+) -> Res<content::Html<String>> {
+    let ns = ns.unwrap_or_default();
+    let q = q.unwrap_or_default();
+    let results = if ns.trim().is_empty() && q.trim().is_empty() {
+        None
+    } else {
+        Some(search::search(
+            &store,
+            &search::namespaces(&ns),
+            &SearchQuery::parse(&q),
+            Page::new(page, None)?,
+        )?)
+    };
+    Ok(web::search_page(&ns, &q, results.as_ref()))
+}
+
+/// The package, or a 404 page rather than the API's JSON error.
+fn package_for_page(store: &Store, namespace: &str, name: &str) -> Res<PackageInfo> {
+    match package_info::package_info(store, namespace, name) {
+        Err(Error::UnknownPackage(..)) => Err(Error::Status(Status::NotFound)),
+        result => result,
+    }
+}
+
+#[get("/packages/<namespace>/<name>")]
+fn package_page(store: Store, namespace: String, name: String) -> Res<content::Html<String>> {
+    let package = package_for_page(&store, &namespace, &name)?;
+    let dependents = package_info::dependents(&store, &namespace, &name)?;
+    Ok(web::package_page(
+        &package,
+        package.latest.as_ref(),
+        &dependents,
+    ))
+}
+
+#[get("/packages/<namespace>/<name>/<version>")]
+fn release_page(
+    store: Store,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<content::Html<String>> {
+    let package = package_for_page(&store, &namespace, &name)?;
+    let release = match package_info::release_info(&store, &namespace, &name, &version) {
+        Err(Error::UnknownRelease(..)) => return Err(Error::Status(Status::NotFound)),
+        result => result?,
+    };
+    let dependents = package_info::dependents(&store, &namespace, &name)?;
+    Ok(web::package_page(&package, Some(&release), &dependents))
+}
+
 #[post("/publish", data = "<data>")]
-fn publish(data: Data, auth: Authenticate, store: Store) -> Res<Json<()>> {
+fn publish(
+    data: Data,
+    auth: Authenticate,
+    store: Store,
+    archive_limits: State<ArchiveLimits>,
+    storage: State<Box<dyn ArtifactStorage>>,
+) -> Res<Json<()>> {
     let token = auth.validate(&store)?;
     Ok(Json(upload::process_upload(
         &store,
         &token.user,
+        &archive_limits,
+        storage.inner().as_ref(),
         data.open(),
     )?))
 }
@@ -198,17 +288,84 @@ fn publish(data: Data, auth: Authenticate, store: Store) -> Res<Json<()>> {
 #[get("/")]
 fn root() -> Res<content::Html<String>> {
     Ok(html_doc(
-        "
+        "Package Registry",
+        &format!(
+            "{}
 <p class=\"pad\">
   <a class=\"btn\" href=\"/login\">Log in?</a>
 </p>
 ",
+            web::search_form("", "")
+        ),
     ))
 }
 
 #[get("/index")]
-fn index(store: Store) -> Result<Json<::pm_lib::index::Index>, ::failure::Error> {
-    Ok(Json(index::compute_index(&store)?))
+fn index(store: Store) -> Result<Json<::pm_lib::index::RegistryIndex>, ::failure::Error> {
+    Ok(Json(index::compute_registry_index(&store)?))
+}
+
+#[get("/log/head")]
+fn log_head(store: Store) -> Res<Json<TreeHead>> {
+    Ok(Json(transparency_log::tree_head(&store)?))
+}
+
+#[get("/log/entries?<start>&<end>")]
+fn log_entries(store: Store, start: u64, end: u64) -> Res<Json<Vec<LoggedEntry>>> {
+    Ok(Json(transparency_log::entries(&store, start, end)?))
+}
+
+#[get("/log/releases/<namespace>/<name>/<version>")]
+fn log_release_entries(
+    store: Store,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<Json<Vec<LoggedEntry>>> {
+    Ok(Json(transparency_log::release_entries(
+        &store, &namespace, &name, &version,
+    )?))
+}
+
+#[get("/log/proof/inclusion?<leaf_index>&<tree_size>")]
+fn log_inclusion_proof(
+    store: Store,
+    leaf_index: u64,
+    tree_size: u64,
+) -> Res<Json<InclusionProof>> {
+    Ok(Json(transparency_log::inclusion_proof(
+        &store, leaf_index, tree_size,
+    )?))
+}
+
+#[get("/log/proof/consistency?<first>&<second>")]
+fn log_consistency_proof(store: Store, first: u64, second: u64) -> Res<Json<ConsistencyProof>> {
+    Ok(Json(transparency_log::consistency_proof(
+        &store, first, second,
+    )?))
+}
+
+#[derive(Deserialize)]
+struct SigningKeyRequest {
+    public_key: String,
+}
+
+#[get("/keys")]
+fn keys(auth: Authenticate, store: Store) -> Res<Json<Vec<String>>> {
+    let token = auth.validate(&store)?;
+    Ok(Json(store.get_signing_keys(&token.user)?))
+}
+
+#[post("/keys", data = "<key>")]
+fn add_key(auth: Authenticate, store: Store, key: Json<SigningKeyRequest>) -> Res<Json<()>> {
+    let token = auth.validate(&store)?;
+    Ok(Json(store.add_signing_key(&token.user, &key.public_key)?))
+}
+
+#[delete("/keys", data = "<key>")]
+fn remove_key(auth: Authenticate, store: Store, key: Json<SigningKeyRequest>) -> Res<Json<()>> {
+    let token = auth.validate(&store)?;
+    Ok(Json(store.remove_signing_key(&token.user, &key.public_key)?))
 }
 
 #[derive(FromForm)]
@@ -229,8 +386,10 @@ fn login_client(store: Store, login: Form<Login>) -> Res<content::Html<String>>
         GITLAB_CLIENT_ID,
         login.token
     );
-    Ok(html_doc(&format!(
-        "
+    Ok(html_doc(
+        "Log in",
+        &format!(
+            "
 <p>Use this decadent bourgeois identity provider to log in:</p>
 <p class=\"pad\">
   <a class=\"btn\" href=\"{}\">Log in with GitHub</a>
@@ -240,8 +399,9 @@ fn login_client(store: Store, login: Form<Login>) -> Res<content::Html<String>>
   <a class=\"btn\" href=\"{}\">Log in with GitLab</a>
 </p>
 ",
-        github_url, gitlab_url
-    )))
+            github_url, gitlab_url
+        ),
+    ))
 }
 
 #[derive(FromForm)]
@@ -282,6 +442,74 @@ fn gitlab_callback(store: Store, callback: Form<OAuthCallback>) -> Res<Redirect>
     Ok(Redirect::to(redirect.as_str().to_string()))
 }
 
+#[derive(FromForm)]
+struct TestLogin {
+    state: String,
+    user: String,
+}
+
+/// Finish a login as the test user `user`, without asking an identity
+/// provider. Only exists when test authentication is enabled; the end to end
+/// tests use it in place of the OAuth callbacks.
+#[get("/test/login?<login..>")]
+fn test_login(store: Store, login: Form<TestLogin>) -> Res<Redirect> {
+    if !test_auth_enabled() {
+        return Err(Error::Status(Status::NotFound));
+    }
+    let mut redirect = Url::parse(&store.validate_login(&login.state)?)?;
+    let user = NullAuth.user(&login.user)?;
+    let auth = AuthToken::new(&user.user()?, &login.user);
+    store.update_user(&user)?;
+    redirect
+        .query_pairs_mut()
+        .append_pair("token", &auth.encode()?)
+        .append_pair("state", &login.state);
+    Ok(Redirect::to(redirect.as_str().to_string()))
+}
+
+/// Read archive limits for uploads from `ARCHIVE_MAX_ENTRIES` and
+/// `ARCHIVE_MAX_UNPACKED_SIZE` (in bytes), if set.
+fn archive_limits() -> ArchiveLimits {
+    let mut limits = ArchiveLimits::default();
+    if let Ok(max_entries) = env::var("ARCHIVE_MAX_ENTRIES") {
+        limits.max_entries = max_entries
+            .parse()
+            .expect("ARCHIVE_MAX_ENTRIES must be a number");
+    }
+    if let Ok(max_unpacked_size) = env::var("ARCHIVE_MAX_UNPACKED_SIZE") {
+        limits.max_unpacked_size = max_unpacked_size
+            .parse()
+            .expect("ARCHIVE_MAX_UNPACKED_SIZE must be a number");
+    }
+    limits
+}
+
+// A SQLite registry creates and migrates its database on launch, so that
+// tests and small deployments don't need the Diesel command line tool.
+#[cfg(feature = "sqlite")]
+embed_migrations!("migrations_sqlite");
+
+#[cfg(feature = "sqlite")]
+fn run_migrations(rocket: rocket::Rocket) -> Result<rocket::Rocket, rocket::Rocket> {
+    let store = Store::get_one(&rocket).expect("Unable to connect to the database");
+    match embedded_migrations::run(store.db()) {
+        Ok(()) => Ok(rocket),
+        Err(err) => {
+            println!("error: running database migrations failed: {}", err);
+            Err(rocket)
+        }
+    }
+}
+
+fn record_downloads(rocket: rocket::Rocket) -> Result<rocket::Rocket, rocket::Rocket> {
+    let pool = Store::pool(&rocket).expect("Unable to connect to the database");
+    rocket
+        .state::<Downloads>()
+        .unwrap()
+        .flush_in_background(pool);
+    Ok(rocket)
+}
+
 fn main() {
     #[cfg(not(test))]
     dotenv::dotenv().ok();
@@ -289,27 +517,67 @@ fn main() {
     let mut database_config = HashMap::new();
     let mut databases = HashMap::new();
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL environment variable not set");
+
+    if env::args().nth(1).as_ref().map(String::as_str) == Some("migrate-artifacts") {
+        let db = DbConnection::establish(&database_url).expect("Unable to connect to the database");
+        let moved = storage::migrate_artifacts(&db, storage::from_env().as_ref())
+            .expect("Moving artifacts out of the database failed");
+        println!("Moved {} artifact(s) out of the database", moved);
+        return;
+    }
+
     database_config.insert("url", Value::from(database_url));
+    // SQLite allows a single writer at a time, and other connections fail
+    // rather than wait for it, so we only use one.
+    #[cfg(feature = "sqlite")]
+    database_config.insert("pool_size", Value::from(1));
     databases.insert("registry", Value::from(database_config));
-    let config = Config::build(Environment::Development)
-        .extra("databases", databases)
+    let mut config = Config::build(Environment::Development).extra("databases", databases);
+    if let Ok(address) = env::var("ADDRESS") {
+        config = config.address(address);
+    }
+    if let Ok(port) = env::var("PORT") {
+        config = config.port(port.parse().expect("PORT must be a port number"));
+    }
+    let config = config
         //.log_level(::rocket::config::LoggingLevel::Debug)
         .finalize()
         .unwrap();
 
-    rocket::custom(config)
-        .attach(Store::fairing())
+    let rocket = rocket::custom(config).attach(Store::fairing());
+    #[cfg(feature = "sqlite")]
+    let rocket = rocket.attach(AdHoc::on_attach("Database migrations", run_migrations));
+    rocket
+        .manage(Downloads::default())
+        .attach(AdHoc::on_attach("Download counts", record_downloads))
+        .manage(archive_limits())
+        .manage(storage::from_env())
         .mount(
             "/",
             routes![
                 root,
                 index,
                 search,
+                api_package,
+                api_release,
+                api_dependents,
+                search_page,
+                package_page,
+                release_page,
                 publish,
                 files,
+                keys,
+                add_key,
+                remove_key,
+                log_head,
+                log_entries,
+                log_release_entries,
+                log_inclusion_proof,
+                log_consistency_proof,
                 login_client,
                 github_callback,
                 gitlab_callback,
+                test_login,
             ],
         )
         .launch();
@@ -1,6 +1,7 @@
-use std::time::SystemTime;
+use chrono::NaiveDateTime;
 
 use crate::schema::{package_owners, package_releases, packages, release_dependencies};
+use crate::sql_types::StringList;
 use crate::user::UserRecord;
 
 #[derive(Identifiable, Queryable, Insertable, AsChangeset, Debug)]
@@ -10,7 +11,7 @@ pub struct Package {
     pub namespace: String,
     pub name: String,
     pub deleted: Option<String>,
-    pub deleted_on: Option<SystemTime>,
+    pub deleted_on: Option<NaiveDateTime>,
 }
 
 #[derive(Identifiable, Queryable, Insertable, AsChangeset, Associations, Debug)]
@@ -22,7 +23,7 @@ pub struct PackageOwner {
     pub name: String,
     pub user_id: String,
     // need ordering
-    pub added_time: SystemTime,
+    pub added_time: NaiveDateTime,
 }
 
 #[derive(Insertable, Identifiable, Associations, Debug)]
@@ -35,8 +36,8 @@ pub struct Release {
     pub version: String,
 
     pub description: String,
-    pub authors: Vec<String>,
-    pub keywords: Vec<String>,
+    pub authors: StringList,
+    pub keywords: StringList,
     pub homepage_url: Option<String>,
     pub repository_type: Option<String>,
     pub repository_url: Option<String>,
@@ -55,6 +56,38 @@ pub struct Release {
     pub publisher: String,
 }
 
+/// A release as stored, including the columns the database fills in.
+#[derive(Queryable, Debug)]
+pub struct ReleaseRecord {
+    pub namespace: String,
+    pub name: String,
+    pub version: String,
+
+    pub description: String,
+    pub authors: StringList,
+    pub keywords: StringList,
+    pub homepage_url: Option<String>,
+    pub repository_type: Option<String>,
+    pub repository_url: Option<String>,
+    pub bugs_url: Option<String>,
+
+    pub license: Option<String>,
+    pub license_file_name: Option<String>,
+    pub license_file_contents: Option<String>,
+
+    pub manifest_file_name: Option<String>,
+    pub manifest_file_contents: Option<String>,
+
+    pub readme_name: Option<String>,
+    pub readme_contents: Option<String>,
+
+    pub publisher: String,
+    pub publish_time: NaiveDateTime,
+    pub deleted: Option<String>,
+    pub deleted_on: Option<NaiveDateTime>,
+    pub downloads: i64,
+}
+
 #[derive(Insertable, AsChangeset, Identifiable, Queryable, Associations, Debug)]
 // We'd like to have a #[belongs_to] attribute to link this to Release, but
 // belongs_to doesn't support composite keys yet.
@@ -0,0 +1,307 @@
+// The package metadata API. See `pm_lib::package_info` for what it returns.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::dependencies::Dependency;
+use pm_lib::package::PackageName;
+use pm_lib::package_info::{
+    highest_dependents, latest_release, DailyDownloads, Dependent, DependentsPage, OwnerInfo,
+    PackageInfo, ReleaseInfo, ReleaseSummary, UserInfo,
+};
+use pm_lib::publication_request::{NamedTextFile, Repository};
+use pm_lib::signing::ReleaseSignature;
+use pm_lib::version::Version;
+
+use crate::error::{Error, Res};
+use crate::package::{self, PackageOwner, ReleaseRecord};
+use crate::pagination::Page;
+use crate::schema::{
+    files, package_owners, package_releases, release_dependencies, release_downloads, users,
+};
+use crate::store::Store;
+use crate::user::UserRecord;
+
+fn user_info(user: UserRecord) -> UserInfo {
+    UserInfo {
+        id: user.id,
+        name: user.name,
+        email: user.email,
+        avatar: user.avatar,
+    }
+}
+
+fn named_text_file(name: Option<String>, contents: Option<String>) -> Option<NamedTextFile> {
+    match (name, contents) {
+        (Some(name), Some(contents)) => Some(NamedTextFile { name, contents }),
+        _ => None,
+    }
+}
+
+pub fn release_info(store: &Store, namespace: &str, name: &str, version: &str) -> Res<ReleaseInfo> {
+    let db = store.db();
+    let unknown_release = || {
+        Error::UnknownRelease(
+            namespace.to_string(),
+            name.to_string(),
+            version.to_string(),
+        )
+    };
+    // Versions are stored as `Version::to_string` writes them.
+    let version = Version::from_str(version).ok_or_else(unknown_release)?;
+    let release: ReleaseRecord = package_releases::table
+        .find((namespace, name, version.to_string()))
+        .get_result(db)
+        .optional()?
+        .ok_or_else(unknown_release)?;
+    let publisher: UserRecord = users::table.find(&release.publisher).get_result(db)?;
+    let dependencies = release_dependencies::table
+        .filter(
+            release_dependencies::namespace
+                .eq(namespace)
+                .and(release_dependencies::name.eq(name))
+                .and(release_dependencies::version.eq(&release.version)),
+        )
+        .order(release_dependencies::ordering)
+        .get_results::<package::Dependency>(db)?
+        .into_iter()
+        .map(|dependency| Dependency {
+            package_name: PackageName {
+                namespace: dependency.dependency_namespace,
+                name: dependency.dependency_name,
+            },
+            version_constraint: VersionConstraint::from_str(
+                &dependency.dependency_version_constraint,
+            )
+            .expect("invalid version constraint"),
+        })
+        .collect();
+    // As in the index, only the most recent file's signature counts.
+    let signature = files::table
+        .select((files::signature, files::signing_key))
+        .filter(
+            files::namespace
+                .eq(namespace)
+                .and(files::name.eq(name))
+                .and(files::version.eq(&release.version)),
+        )
+        .order(files::id.desc())
+        .first::<(Option<String>, Option<String>)>(db)
+        .optional()?;
+    let signature = match signature {
+        Some((Some(signature), Some(public_key))) => Some(ReleaseSignature {
+            public_key,
+            signature,
+        }),
+        _ => None,
+    };
+    let first_day = Utc::now().naive_utc().date() - Duration::days(89);
+    let daily_downloads = release_downloads::table
+        .select((release_downloads::day, release_downloads::downloads))
+        .filter(
+            release_downloads::namespace
+                .eq(namespace)
+                .and(release_downloads::name.eq(name))
+                .and(release_downloads::version.eq(&release.version))
+                .and(release_downloads::day.ge(first_day)),
+        )
+        .order(release_downloads::day)
+        .get_results::<(NaiveDate, i64)>(db)?
+        .into_iter()
+        .map(|(day, downloads)| DailyDownloads {
+            day: day.to_string(),
+            downloads: downloads as u64,
+        })
+        .collect();
+    let repository = match (release.repository_type, release.repository_url) {
+        (Some(type_), Some(url)) => Some(Repository { type_, url }),
+        _ => None,
+    };
+
+    Ok(ReleaseInfo {
+        package: PackageName {
+            namespace: release.namespace,
+            name: release.name,
+        },
+        version,
+
+        description: release.description,
+        authors: release.authors.0,
+        keywords: release.keywords.0,
+        homepage_url: release.homepage_url,
+        repository,
+        bugs_url: release.bugs_url,
+        license: release.license,
+        license_file: named_text_file(release.license_file_name, release.license_file_contents),
+        manifest: named_text_file(release.manifest_file_name, release.manifest_file_contents),
+        readme: named_text_file(release.readme_name, release.readme_contents),
+
+        dependencies,
+        publisher: user_info(publisher),
+        publish_time: release.publish_time.timestamp(),
+        signature,
+        deleted: release.deleted,
+        deleted_on: release.deleted_on.map(|time| time.timestamp()),
+
+        downloads: release.downloads as u64,
+        daily_downloads,
+    })
+}
+
+pub fn package_info(store: &Store, namespace: &str, name: &str) -> Res<PackageInfo> {
+    let db = store.db();
+    store.serializable_transaction::<_, Error, _>(|| {
+        let package = store
+            .get_package(namespace, name)?
+            .ok_or_else(|| Error::UnknownPackage(namespace.to_string(), name.to_string()))?;
+        let owners = package_owners::table
+            .inner_join(users::table)
+            .filter(
+                package_owners::namespace
+                    .eq(namespace)
+                    .and(package_owners::name.eq(name)),
+            )
+            .order(package_owners::added_time)
+            .get_results::<(PackageOwner, UserRecord)>(db)?
+            .into_iter()
+            .map(|(owner, user)| OwnerInfo {
+                user: user_info(user),
+                added_time: owner.added_time.timestamp(),
+            })
+            .collect();
+        let mut releases = package_releases::table
+            .select((
+                package_releases::version,
+                package_releases::description,
+                package_releases::publisher,
+                package_releases::publish_time,
+                package_releases::deleted,
+                package_releases::downloads,
+            ))
+            .filter(
+                package_releases::namespace
+                    .eq(namespace)
+                    .and(package_releases::name.eq(name)),
+            )
+            .get_results::<(String, String, String, NaiveDateTime, Option<String>, i64)>(db)?
+            .into_iter()
+            .map(
+                |(version, description, publisher, publish_time, deleted, downloads)| {
+                    ReleaseSummary {
+                        version: Version::from_str(&version).expect("invalid version"),
+                        description,
+                        publisher,
+                        publish_time: publish_time.timestamp(),
+                        deleted,
+                        downloads: downloads as u64,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+        releases.sort_by(|a, b| b.version.semver_cmp(&a.version));
+        let downloads = releases.iter().map(|release| release.downloads).sum();
+        let latest = match latest_release(&releases) {
+            Some(release) => Some(release_info(
+                store,
+                namespace,
+                name,
+                &release.version.to_string(),
+            )?),
+            None => None,
+        };
+
+        Ok(PackageInfo {
+            package: PackageName {
+                namespace: package.namespace,
+                name: package.name,
+            },
+            owners,
+            releases,
+            latest,
+            deleted: package.deleted,
+            deleted_on: package.deleted_on.map(|time| time.timestamp()),
+            downloads,
+        })
+    })
+}
+
+/// Every release that depends on a package, sorted by package name and then
+/// from the highest version down.
+pub fn dependents(store: &Store, namespace: &str, name: &str) -> Res<Vec<Dependent>> {
+    let mut dependents = release_dependencies::table
+        .filter(
+            release_dependencies::dependency_namespace
+                .eq(namespace)
+                .and(release_dependencies::dependency_name.eq(name)),
+        )
+        .get_results::<package::Dependency>(store.db())?
+        .into_iter()
+        .map(|dependency| Dependent {
+            package: PackageName {
+                namespace: dependency.namespace,
+                name: dependency.name,
+            },
+            version: Version::from_str(&dependency.version).expect("invalid version"),
+            constraint: VersionConstraint::from_str(&dependency.dependency_version_constraint)
+                .expect("invalid version constraint"),
+        })
+        .collect::<Vec<_>>();
+    dependents.sort_by(|a, b| {
+        a.package
+            .cmp(&b.package)
+            .then_with(|| b.version.semver_cmp(&a.version))
+    });
+    Ok(dependents)
+}
+
+/// A page of the packages which depend on a package, as the highest release of
+/// each that does. With a `range`, only the releases which accept one of the
+/// package's releases in the range count.
+pub fn dependents_page(
+    store: &Store,
+    namespace: &str,
+    name: &str,
+    range: Option<&VersionConstraint>,
+    page: Page,
+) -> Res<DependentsPage> {
+    let db = store.db();
+    store.serializable_transaction::<_, Error, _>(|| {
+        if store.get_package(namespace, name)?.is_none() {
+            return Err(Error::UnknownPackage(
+                namespace.to_string(),
+                name.to_string(),
+            ));
+        }
+        let all = dependents(store, namespace, name)?;
+        let dependents = match range {
+            None => highest_dependents(&all),
+            Some(range) => {
+                let versions = package_releases::table
+                    .select(package_releases::version)
+                    .filter(
+                        package_releases::namespace
+                            .eq(namespace)
+                            .and(package_releases::name.eq(name)),
+                    )
+                    .get_results::<String>(db)?
+                    .into_iter()
+                    .map(|version| Version::from_str(&version).expect("invalid version"))
+                    .filter(|version| range.contains(version))
+                    .collect::<Vec<_>>();
+                highest_dependents(all.iter().filter(|dependent| {
+                    versions
+                        .iter()
+                        .any(|version| dependent.constraint.contains(version))
+                }))
+            }
+        };
+
+        Ok(DependentsPage {
+            total: dependents.len(),
+            page: page.page,
+            limit: page.limit,
+            dependents: page.of(dependents).into_iter().cloned().collect(),
+        })
+    })
+}
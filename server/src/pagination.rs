@@ -0,0 +1,47 @@
+// Paging through long lists in the API, which clients do with `page` and
+// `limit` query parameters.
+
+use crate::error::{Error, Res};
+
+/// How many results a page holds unless the client asks for another number.
+pub const DEFAULT_LIMIT: usize = 50;
+/// The most results a client can ask for at once.
+pub const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    /// Counting from 1.
+    pub page: usize,
+    pub limit: usize,
+}
+
+impl Page {
+    pub fn new(page: Option<usize>, limit: Option<usize>) -> Res<Page> {
+        let page = page.unwrap_or(1);
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if page == 0 {
+            return Err(Error::InvalidQuery("pages are counted from 1".to_string()));
+        }
+        if limit == 0 || limit > MAX_LIMIT {
+            return Err(Error::InvalidQuery(format!(
+                "limit must be between 1 and {}",
+                MAX_LIMIT
+            )));
+        }
+        Ok(Page { page, limit })
+    }
+
+    /// How many results come before this page.
+    pub fn offset(&self) -> usize {
+        (self.page - 1).saturating_mul(self.limit)
+    }
+
+    /// This page's share of all the results.
+    pub fn of<A>(&self, results: Vec<A>) -> Vec<A> {
+        results
+            .into_iter()
+            .skip(self.offset())
+            .take(self.limit)
+            .collect()
+    }
+}
@@ -4,7 +4,10 @@ table! {
         namespace -> Text,
         name -> Text,
         version -> Text,
-        data -> Bytea,
+        data -> Nullable<Bytea>,
+        signature -> Nullable<Text>,
+        signing_key -> Nullable<Text>,
+        storage_key -> Nullable<Text>,
     }
 }
 
@@ -48,6 +51,7 @@ table! {
         publish_time -> Timestamp,
         deleted -> Nullable<Text>,
         deleted_on -> Nullable<Timestamp>,
+        downloads -> Int8,
     }
 }
 
@@ -72,6 +76,35 @@ table! {
     }
 }
 
+table! {
+    release_downloads (namespace, name, version, day) {
+        namespace -> Text,
+        name -> Text,
+        version -> Text,
+        day -> Date,
+        downloads -> Int8,
+    }
+}
+
+table! {
+    signing_keys (public_key) {
+        public_key -> Text,
+        user_id -> Text,
+        added_time -> Timestamp,
+    }
+}
+
+table! {
+    transparency_log (id) {
+        id -> Int8,
+        entry -> Text,
+        leaf_hash -> Bytea,
+        namespace -> Text,
+        name -> Text,
+        version -> Nullable<Text>,
+    }
+}
+
 table! {
     users (id) {
         id -> Text,
@@ -83,6 +116,7 @@ table! {
 
 joinable!(package_owners -> users (user_id));
 joinable!(package_releases -> users (publisher));
+joinable!(signing_keys -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
     files,
@@ -91,5 +125,8 @@ allow_tables_to_appear_in_same_query!(
     package_releases,
     packages,
     release_dependencies,
+    release_downloads,
+    signing_keys,
+    transparency_log,
     users,
 );
@@ -0,0 +1,135 @@
+// The SQLite counterpart of schema.rs. SQLite has no arrays, so the array
+// columns hold JSON arrays instead (see `sql_types::StringList`).
+
+table! {
+    files (id) {
+        id -> BigInt,
+        namespace -> Text,
+        name -> Text,
+        version -> Text,
+        data -> Nullable<Binary>,
+        signature -> Nullable<Text>,
+        signing_key -> Nullable<Text>,
+        storage_key -> Nullable<Text>,
+    }
+}
+
+table! {
+    login_sessions (token) {
+        token -> Text,
+        callback -> Text,
+        stamp -> Timestamp,
+    }
+}
+
+table! {
+    package_owners (namespace, name, user_id) {
+        namespace -> Text,
+        name -> Text,
+        user_id -> Text,
+        added_time -> Timestamp,
+    }
+}
+
+table! {
+    package_releases (namespace, name, version) {
+        namespace -> Text,
+        name -> Text,
+        version -> Text,
+        description -> Text,
+        authors -> Text,
+        keywords -> Text,
+        homepage_url -> Nullable<Text>,
+        repository_type -> Nullable<Text>,
+        repository_url -> Nullable<Text>,
+        bugs_url -> Nullable<Text>,
+        license -> Nullable<Text>,
+        license_file_name -> Nullable<Text>,
+        license_file_contents -> Nullable<Text>,
+        manifest_file_name -> Nullable<Text>,
+        manifest_file_contents -> Nullable<Text>,
+        readme_name -> Nullable<Text>,
+        readme_contents -> Nullable<Text>,
+        publisher -> Text,
+        publish_time -> Timestamp,
+        deleted -> Nullable<Text>,
+        deleted_on -> Nullable<Timestamp>,
+        downloads -> BigInt,
+    }
+}
+
+table! {
+    packages (namespace, name) {
+        namespace -> Text,
+        name -> Text,
+        deleted -> Nullable<Text>,
+        deleted_on -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    release_dependencies (namespace, name, version, dependency_namespace, dependency_name) {
+        namespace -> Text,
+        name -> Text,
+        version -> Text,
+        ordering -> Integer,
+        dependency_namespace -> Text,
+        dependency_name -> Text,
+        dependency_version_constraint -> Text,
+    }
+}
+
+table! {
+    release_downloads (namespace, name, version, day) {
+        namespace -> Text,
+        name -> Text,
+        version -> Text,
+        day -> Date,
+        downloads -> BigInt,
+    }
+}
+
+table! {
+    signing_keys (public_key) {
+        public_key -> Text,
+        user_id -> Text,
+        added_time -> Timestamp,
+    }
+}
+
+table! {
+    transparency_log (id) {
+        id -> BigInt,
+        entry -> Text,
+        leaf_hash -> Binary,
+        namespace -> Text,
+        name -> Text,
+        version -> Nullable<Text>,
+    }
+}
+
+table! {
+    users (id) {
+        id -> Text,
+        name -> Text,
+        email -> Text,
+        avatar -> Nullable<Text>,
+    }
+}
+
+joinable!(package_owners -> users (user_id));
+joinable!(package_releases -> users (publisher));
+joinable!(signing_keys -> users (user_id));
+
+allow_tables_to_appear_in_same_query!(
+    files,
+    login_sessions,
+    package_owners,
+    package_releases,
+    packages,
+    release_dependencies,
+    release_downloads,
+    signing_keys,
+    transparency_log,
+    users,
+);
@@ -1,64 +1,270 @@
+// Searching the registry; see `pm_lib::search` for what a query can ask for.
+// Packages are ranked here rather than in the database, so that both backends
+// rank them the same way. The database only finds the candidates: packages
+// with a release that contains every search term and qualifier somewhere, as
+// far as a case insensitive LIKE can tell. For those, we read the latest
+// release with its download count, and their owners if the query filters by
+// owner, and check and rank them properly.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "postgres")]
 use diesel::pg::types::sql_types::Array;
 use diesel::prelude::*;
-use diesel::sql_query;
-use diesel::sql_types::Text;
+use diesel::sql_types::{Bool, Text};
 
+use pm_lib::package::PackageName;
+use pm_lib::search::{relevance, SearchQuery, SearchResult, SearchResults};
 use pm_lib::version::Version;
 
-use im::OrdMap as Map;
-
 use crate::error::Error;
-use crate::store::Store;
-
-#[derive(QueryableByName, Serialize, Clone, Debug, PartialEq, Eq)]
-pub struct SearchResult {
-    #[sql_type = "Text"]
-    pub name: String,
-    #[sql_type = "Text"]
-    pub version: String,
-    #[sql_type = "Text"]
-    pub publisher: String,
-    #[sql_type = "Text"]
-    pub description: String,
+use crate::pagination::Page;
+use crate::schema::{package_owners, package_releases, packages, users};
+use crate::sql_types::StringList;
+use crate::store::{DbConnection, Store};
+
+/// The namespaces in an `ns` parameter, which separates them with commas.
+pub fn namespaces(ns: &str) -> Vec<String> {
+    ns.split(',')
+        .map(str::trim)
+        .filter(|namespace| !namespace.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
-pub fn search_db(
-    db: &PgConnection,
-    ns: &str,
-    query: Vec<String>,
-) -> Result<Vec<SearchResult>, Error> {
-    let result: Vec<SearchResult> = sql_query(
-        "
-select package_releases.name,
-       package_releases.version,
-       package_releases.publisher,
-       package_releases.description,
-       package_releases.publish_time
-from package_releases, (
-  select package_search($1, $2)) as result
-    where result.package_search = package_releases.name
-      and package_releases.namespace = $1;
-",
-    ).bind::<Text, _>(ns)
-    .bind::<Array<Text>, _>(query)
-    .get_results(db)?;
-    Ok(group_by_semver(result))
+type Backend = <DbConnection as Connection>::Backend;
+type Condition = Box<dyn BoxableExpression<package_releases::table, Backend, SqlType = Bool>>;
+type Names<'a> = package_releases::BoxedQuery<'a, Backend, Text>;
+
+sql_function!(fn lower(text: Text) -> Text);
+#[cfg(feature = "postgres")]
+sql_function!(fn array_to_string(array: Array<Text>, separator: Text) -> Text);
+
+/// Whether the database can look for `word` for us. SQLite only lowercases
+/// ASCII, and stores keywords as JSON, which escapes some characters. Words
+/// it can't look for are only checked in `search`.
+fn can_look_for(word: &str) -> bool {
+    cfg!(feature = "postgres")
+        || word.is_ascii() && !word.contains(|c: char| c == '"' || c == '\\' || c.is_control())
 }
 
-pub fn search(store: &Store, ns: &str, query: Vec<String>) -> Result<Vec<SearchResult>, Error> {
-    search_db(&store.db(), ns, query)
+/// A LIKE pattern for text that contains `word`.
+fn containing(word: &str) -> String {
+    format!(
+        "%{}%",
+        word.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
 }
 
-fn group_by_semver(results: Vec<SearchResult>) -> Vec<SearchResult> {
-    let mut groups = Map::<String, Map<Version, SearchResult>>::new();
-    for result in results {
-        groups.entry(result.name.clone()).or_default().insert(
-            Version::from_str(&result.version).expect("illegal version string during search"),
-            result.clone(),
+fn keywords_like(pattern: String) -> Condition {
+    #[cfg(feature = "postgres")]
+    let keywords = array_to_string(package_releases::keywords, " ");
+    #[cfg(feature = "sqlite")]
+    let keywords = package_releases::keywords;
+    Box::new(lower(keywords).like(pattern).escape('\\'))
+}
+
+/// The names of the packages in `namespaces`, or in every namespace if there
+/// are none, that may match `query`. It's up to the caller to check that
+/// they do: packages in different namespaces can have the same name.
+fn candidates<'a>(namespaces: &'a [String], query: &SearchQuery) -> Names<'a> {
+    let mut names = package_releases::table
+        .select(package_releases::name)
+        .into_boxed();
+    if !namespaces.is_empty() {
+        names = names.filter(package_releases::namespace.eq_any(namespaces));
+    }
+    for term in query.terms.iter().filter(|term| can_look_for(term)) {
+        let pattern = containing(term);
+        names = names.filter(
+            lower(package_releases::name)
+                .like(pattern.clone())
+                .escape('\\')
+                .or(lower(package_releases::description)
+                    .like(pattern.clone())
+                    .escape('\\'))
+                .or(keywords_like(pattern)),
         );
     }
-    groups
-        .values()
-        .map(|m| m.get_min().unwrap().1.clone())
-        .collect()
+    for keyword in query.keywords.iter().filter(|keyword| can_look_for(keyword)) {
+        names = names.filter(keywords_like(containing(keyword)));
+    }
+    for owner in query.owners.iter().filter(|owner| can_look_for(owner)) {
+        names = names.filter(
+            package_releases::name.eq_any(
+                package_owners::table
+                    .inner_join(users::table)
+                    .select(package_owners::name)
+                    .filter(
+                        lower(users::id)
+                            .eq(owner.clone())
+                            .or(lower(users::name).eq(owner.clone())),
+                    ),
+            ),
+        );
+    }
+    names
+}
+
+/// The latest release of each candidate package for `query` in `namespaces`,
+/// leaving out yanked releases and deleted packages. The downloads of yanked
+/// releases still count towards their package's.
+fn latest_releases(
+    db: &DbConnection,
+    namespaces: &[String],
+    query: &SearchQuery,
+) -> Result<BTreeMap<PackageName, SearchResult>, Error> {
+    let deleted = packages::table
+        .select((packages::namespace, packages::name))
+        .filter(packages::deleted.is_not_null())
+        .filter(packages::name.eq_any(candidates(namespaces, query)))
+        .load::<(String, String)>(db)?
+        .into_iter()
+        .map(|(namespace, name)| PackageName { namespace, name })
+        .collect::<BTreeSet<_>>();
+    let mut releases = package_releases::table
+        .select((
+            package_releases::namespace,
+            package_releases::name,
+            package_releases::version,
+            package_releases::description,
+            package_releases::keywords,
+            package_releases::publisher,
+            package_releases::deleted,
+            package_releases::downloads,
+        ))
+        .filter(package_releases::name.eq_any(candidates(namespaces, query)))
+        .into_boxed();
+    if !namespaces.is_empty() {
+        releases = releases.filter(package_releases::namespace.eq_any(namespaces));
+    }
+
+    let mut latest = BTreeMap::<PackageName, SearchResult>::new();
+    let mut downloads = BTreeMap::<PackageName, u64>::new();
+    for (namespace, name, version, description, keywords, publisher, yanked, release_downloads) in
+        releases.load::<(
+            String,
+            String,
+            String,
+            String,
+            StringList,
+            String,
+            Option<String>,
+            i64,
+        )>(db)?
+    {
+        let package = PackageName { namespace, name };
+        if deleted.contains(&package) {
+            continue;
+        }
+        *downloads.entry(package.clone()).or_insert(0) += release_downloads as u64;
+        if yanked.is_some() {
+            continue;
+        }
+        let version = Version::from_str(&version).expect("invalid version");
+        // Versions are ordered by preference, as in `latest_release`.
+        if let Some(result) = latest.get(&package) {
+            if result.version <= version {
+                continue;
+            }
+        }
+        latest.insert(
+            package.clone(),
+            SearchResult {
+                package,
+                version,
+                description,
+                keywords: keywords.0,
+                publisher,
+                downloads: 0,
+            },
+        );
+    }
+    for (package, result) in &mut latest {
+        result.downloads = downloads[package];
+    }
+    Ok(latest)
+}
+
+/// The IDs and names of the owners of each candidate package for `query` in
+/// `namespaces`.
+fn owners(
+    db: &DbConnection,
+    namespaces: &[String],
+    query: &SearchQuery,
+) -> Result<BTreeMap<PackageName, Vec<(String, String)>>, Error> {
+    let mut owners_query = package_owners::table
+        .inner_join(users::table)
+        .select((
+            package_owners::namespace,
+            package_owners::name,
+            users::id,
+            users::name,
+        ))
+        .filter(package_owners::name.eq_any(candidates(namespaces, query)))
+        .into_boxed();
+    if !namespaces.is_empty() {
+        owners_query = owners_query.filter(package_owners::namespace.eq_any(namespaces));
+    }
+    let mut owners = BTreeMap::<PackageName, Vec<(String, String)>>::new();
+    for (namespace, name, id, user_name) in
+        owners_query.load::<(String, String, String, String)>(db)?
+    {
+        owners
+            .entry(PackageName { namespace, name })
+            .or_default()
+            .push((id, user_name));
+    }
+    Ok(owners)
+}
+
+pub fn search(
+    store: &Store,
+    namespaces: &[String],
+    query: &SearchQuery,
+    page: Page,
+) -> Result<SearchResults, Error> {
+    if namespaces.is_empty() && query == &SearchQuery::default() {
+        return Err(Error::InvalidQuery(
+            "give search terms or a namespace to search".to_string(),
+        ));
+    }
+    let db = store.db();
+    let owners = if query.owners.is_empty() {
+        BTreeMap::new()
+    } else {
+        owners(db, namespaces, query)?
+    };
+    let mut ranked = latest_releases(db, namespaces, query)?
+        .into_iter()
+        .filter(|(package, result)| {
+            query.matches_keywords(&result.keywords)
+                && query.matches_owners(owners.get(package).map_or(&[][..], Vec::as_slice))
+        })
+        .filter_map(|(package, result)| {
+            relevance(
+                &query.terms,
+                &package.name,
+                &result.keywords,
+                &result.description,
+            )
+            .map(|score| (score, result))
+        })
+        .collect::<Vec<_>>();
+    // The most relevant first, then the most downloaded, and otherwise by
+    // name, as they come out of the map: the sort is stable.
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.downloads.cmp(&a.1.downloads)));
+
+    Ok(SearchResults {
+        total: ranked.len(),
+        page: page.page,
+        limit: page.limit,
+        results: page
+            .of(ranked)
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect(),
+    })
 }
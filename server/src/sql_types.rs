@@ -0,0 +1,53 @@
+// Column types that differ between the database backends.
+
+use std::io::Write;
+
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+#[cfg(feature = "postgres")]
+use diesel::{pg::Pg, sql_types::Array, sql_types::Text};
+#[cfg(feature = "sqlite")]
+use diesel::{sql_types::Text, sqlite::Sqlite};
+
+/// A list of strings, like a release's keywords. Postgres stores it as a
+/// `TEXT[]`; SQLite has no arrays, so there it's a JSON array in a `TEXT`
+/// column.
+#[derive(AsExpression, FromSqlRow, Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "postgres",
+    sql_type = "diesel::sql_types::Array<diesel::sql_types::Text>"
+)]
+#[cfg_attr(feature = "sqlite", sql_type = "diesel::sql_types::Text")]
+pub struct StringList(pub Vec<String>);
+
+#[cfg(feature = "postgres")]
+impl ToSql<Array<Text>, Pg> for StringList {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Array<Text>, Pg>::to_sql(&self.0, out)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl FromSql<Array<Text>, Pg> for StringList {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        Ok(StringList(FromSql::<Array<Text>, Pg>::from_sql(bytes)?))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ToSql<Text, Sqlite> for StringList {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+        let json = serde_json::to_string(&self.0)?;
+        ToSql::<Text, Sqlite>::to_sql(&json, out)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl FromSql<Text, Sqlite> for StringList {
+    fn from_sql(
+        value: Option<&<Sqlite as diesel::backend::Backend>::RawValue>,
+    ) -> deserialize::Result<Self> {
+        let json: String = FromSql::<Text, Sqlite>::from_sql(value)?;
+        Ok(StringList(serde_json::from_str(&json)?))
+    }
+}
@@ -1,25 +1,31 @@
 use std::str::FromStr;
-use std::time::SystemTime;
 
+use chrono::{NaiveDateTime, Utc};
 use diesel;
-use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::result::Error::NotFound;
 
+use pm_lib::package::PackageName;
+use pm_lib::signing::parse_public_key;
+use pm_lib::transparency_log::LogEvent;
+
 use data_encoding::BASE64;
+use rocket::Rocket;
+use rocket_contrib::databases::{r2d2, Poolable};
 
 use crate::error::{Error, Res};
 use crate::package::{Package, PackageOwner};
-use crate::user::{User, UserRecord};
+use crate::transparency_log;
+use crate::user::{SigningKey, User, UserRecord};
 
-use crate::schema::{files, login_sessions, package_owners, packages, users};
+use crate::schema::{files, login_sessions, package_owners, packages, signing_keys, users};
 
 #[allow(dead_code)]
 #[derive(Queryable)]
 pub struct LoginSession {
     token: String,
     callback: String,
-    stamp: SystemTime,
+    stamp: NaiveDateTime,
 }
 
 #[derive(Insertable)]
@@ -29,18 +35,66 @@ pub struct NewLoginSession {
     callback: String,
 }
 
-// This struct is a wrapper around a thread-pooled connection to the Postgres
+/// Where to find a release archive.
+pub enum Artifact {
+    /// In artifact storage, under this key.
+    Stored(String),
+    /// Still in the database, from before artifact storage existed.
+    Inline(Vec<u8>),
+}
+
+#[cfg(all(feature = "postgres", feature = "sqlite"))]
+compile_error!("The postgres and sqlite features can't be enabled together");
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("Either the postgres or the sqlite feature must be enabled");
+
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::pg::PgConnection;
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::sqlite::SqliteConnection;
+
+// This struct is a wrapper around a thread-pooled connection to the database
 // server. It would be called "RegistryDbConn" if we went by the sample code in
 // the Rocket guides. It must only have a single data field, but we can freely
 // add our own methods, which we do in the impl below.
 #[database("registry")]
-pub struct Store(rocket_contrib::databases::diesel::PgConnection);
+pub struct Store(DbConnection);
+
+/// The database connection pool, for work that isn't done in a request.
+pub type Pool = r2d2::Pool<<DbConnection as Poolable>::Manager>;
 
 impl Store {
-    pub fn db(&self) -> &PgConnection {
+    /// Rocket's connection pool, once `Store::fairing` has set it up.
+    pub fn pool(rocket: &Rocket) -> Option<Pool> {
+        rocket.state::<StorePool>().map(|pool| pool.0.clone())
+    }
+
+    pub fn db(&self) -> &DbConnection {
         &self.0
     }
 
+    /// Run `f` in a serializable transaction.
+    #[cfg(feature = "postgres")]
+    pub fn serializable_transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: From<diesel::result::Error>,
+    {
+        self.db().build_transaction().serializable().run(f)
+    }
+
+    /// Run `f` in a serializable transaction. SQLite transactions are always
+    /// serializable; we take the write lock up front so that concurrent
+    /// transactions wait for each other instead of failing when they write.
+    #[cfg(feature = "sqlite")]
+    pub fn serializable_transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: From<diesel::result::Error>,
+    {
+        self.db().immediate_transaction(f)
+    }
+
     pub fn register_login(&self, token: &str, callback: &str) -> Res<()> {
         let db = self.db();
         if BASE64.decode(token.as_bytes()).is_err() {
@@ -60,13 +114,21 @@ impl Store {
         if BASE64.decode(token.as_bytes()).is_err() {
             return Err(Error::InvalidLoginState(token.to_string()));
         }
-        let session: LoginSession =
+        #[cfg(feature = "postgres")]
+        let session: Result<LoginSession, _> =
             diesel::delete(login_sessions::table.filter(login_sessions::token.eq(token)))
-                .get_result(db)
-                .map_err(|err| match err {
-                    NotFound => Error::InvalidLoginState(token.to_string()),
-                    e => Error::from(e),
-                })?;
+                .get_result(db);
+        // SQLite has no DELETE ... RETURNING.
+        #[cfg(feature = "sqlite")]
+        let session: Result<LoginSession, _> = self.serializable_transaction(|| {
+            let session = login_sessions::table.find(token).get_result(db)?;
+            diesel::delete(login_sessions::table.find(token)).execute(db)?;
+            Ok(session)
+        });
+        let session = session.map_err(|err| match err {
+            NotFound => Error::InvalidLoginState(token.to_string()),
+            e => Error::from(e),
+        })?;
         Ok(session.callback)
     }
 
@@ -76,12 +138,24 @@ impl Store {
             user.id.contains(':'),
             "user_record.id must be namespaced to prevent collisions between authentication providers"
         );
+        #[cfg(feature = "postgres")]
         diesel::insert_into(users::table)
             .values(user)
             .on_conflict(users::id)
             .do_update()
             .set(user)
             .execute(db)?;
+        // Diesel only supports upserts on Postgres.
+        #[cfg(feature = "sqlite")]
+        self.serializable_transaction::<_, Error, _>(|| {
+            let updated = diesel::update(users::table.find(&user.id))
+                .set(user)
+                .execute(db)?;
+            if updated == 0 {
+                diesel::insert_into(users::table).values(user).execute(db)?;
+            }
+            Ok(())
+        })?;
         Ok(())
     }
 
@@ -122,25 +196,95 @@ impl Store {
         results.iter().map(|o| User::from_str(&o.user_id)).collect()
     }
 
-    pub fn remove_package_owner(&self, namespace: &str, name: &str, owner: &User) -> Res<()> {
+    pub fn remove_package_owner(
+        &self,
+        namespace: &str,
+        name: &str,
+        owner: &User,
+        actor: &User,
+    ) -> Res<()> {
         let db = self.db();
-        diesel::delete(
-            package_owners::table.filter(
-                package_owners::namespace.eq(namespace).and(
-                    package_owners::name
-                        .eq(name)
-                        .and(package_owners::user_id.eq(&owner.to_string())),
+        db.transaction::<_, Error, _>(|| {
+            let removed = diesel::delete(
+                package_owners::table.filter(
+                    package_owners::namespace.eq(namespace).and(
+                        package_owners::name
+                            .eq(name)
+                            .and(package_owners::user_id.eq(&owner.to_string())),
+                    ),
                 ),
+            )
+            .execute(db)?;
+            if removed > 0 {
+                transparency_log::append(
+                    self,
+                    LogEvent::RemoveOwner {
+                        package: PackageName {
+                            namespace: namespace.to_string(),
+                            name: name.to_string(),
+                        },
+                        owner: owner.to_string(),
+                    },
+                    actor,
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn get_signing_keys(&self, user: &User) -> Res<Vec<String>> {
+        let db = self.db();
+        let keys = signing_keys::table
+            .select(signing_keys::public_key)
+            .filter(signing_keys::user_id.eq(user.to_string()))
+            .order(signing_keys::added_time)
+            .get_results(db)?;
+        Ok(keys)
+    }
+
+    pub fn add_signing_key(&self, user: &User, public_key: &str) -> Res<()> {
+        let db = self.db();
+        if parse_public_key(public_key).is_err() {
+            return Err(Error::InvalidSigningKey(public_key.to_string()));
+        }
+        let key = SigningKey {
+            public_key: public_key.to_string(),
+            user_id: user.to_string(),
+            added_time: Utc::now().naive_utc(),
+        };
+        #[cfg(feature = "postgres")]
+        let inserted = diesel::insert_into(signing_keys::table)
+            .values(&key)
+            .on_conflict_do_nothing()
+            .execute(db)?;
+        #[cfg(feature = "sqlite")]
+        let inserted = diesel::insert_or_ignore_into(signing_keys::table)
+            .values(&key)
+            .execute(db)?;
+        // Registering a key twice is fine, but it can't belong to two users.
+        if inserted == 0 && !self.get_signing_keys(user)?.iter().any(|key| key == public_key) {
+            return Err(Error::SigningKeyInUse(public_key.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn remove_signing_key(&self, user: &User, public_key: &str) -> Res<()> {
+        let db = self.db();
+        diesel::delete(
+            signing_keys::table.filter(
+                signing_keys::public_key
+                    .eq(public_key)
+                    .and(signing_keys::user_id.eq(user.to_string())),
             ),
         )
         .execute(db)?;
         Ok(())
     }
 
-    pub fn get_tar_br(&self, namespace: &str, name: &str, version: &str) -> Res<Vec<u8>> {
+    pub fn get_artifact(&self, namespace: &str, name: &str, version: &str) -> Res<Artifact> {
         let db = self.db();
-        let tar_br = files::table
-            .select(files::data)
+        let (storage_key, data) = files::table
+            .select((files::storage_key, files::data))
             .filter(
                 files::namespace
                     .eq(namespace)
@@ -149,7 +293,7 @@ impl Store {
             )
             .order(files::id.desc())
             .limit(1)
-            .get_result::<Vec<u8>>(db)
+            .get_result::<(Option<String>, Option<Vec<u8>>)>(db)
             .map_err(|err| match err {
                 NotFound => Error::UnknownRelease(
                     namespace.to_string(),
@@ -158,6 +302,10 @@ impl Store {
                 ),
                 e => Error::from(e),
             })?;
-        Ok(tar_br)
+        match (storage_key, data) {
+            (Some(key), _) => Ok(Artifact::Stored(key)),
+            (None, Some(data)) => Ok(Artifact::Inline(data)),
+            (None, None) => unreachable!("files_data_or_storage_key constraint violated"),
+        }
     }
 }
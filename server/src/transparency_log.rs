@@ -0,0 +1,168 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::dsl::max;
+use diesel::prelude::*;
+use rocket::http::Status;
+
+use pm_lib::transparency_log::{
+    consistency_proof as compute_consistency_proof, inclusion_proof as compute_inclusion_proof,
+    leaf_hash, root_hash, to_hex, ConsistencyProof, Hash, InclusionProof, LogEntry, LogEvent,
+    LoggedEntry, TreeHead,
+};
+
+use crate::error::{Error, Res};
+use crate::schema::transparency_log;
+use crate::store::Store;
+use crate::user::User;
+
+/// The most entries `entries` returns at once.
+const MAX_ENTRIES: u64 = 1000;
+
+#[derive(Insertable, Queryable, Debug)]
+#[table_name = "transparency_log"]
+struct LogRecord {
+    id: i64,
+    entry: String,
+    leaf_hash: Vec<u8>,
+    namespace: String,
+    name: String,
+    version: Option<String>,
+}
+
+impl From<LogRecord> for LoggedEntry {
+    fn from(record: LogRecord) -> LoggedEntry {
+        LoggedEntry {
+            index: record.id as u64,
+            entry: record.entry,
+        }
+    }
+}
+
+/// Add an event to the log. Call this in the same transaction as the change
+/// it records.
+pub fn append(store: &Store, event: LogEvent, actor: &User) -> Res<()> {
+    let db = store.db();
+    let (package, version) = match event {
+        LogEvent::Publish {
+            ref package,
+            ref version,
+            ..
+        }
+        | LogEvent::Yank {
+            ref package,
+            ref version,
+        } => (package.clone(), Some(version.to_string())),
+        LogEvent::AddOwner { ref package, .. } | LogEvent::RemoveOwner { ref package, .. } => {
+            (package.clone(), None)
+        }
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before 1970")
+        .as_secs();
+    let entry = serde_json::to_string(&LogEntry {
+        event,
+        actor: actor.to_string(),
+        timestamp,
+    })?;
+    let last_id: Option<i64> = transparency_log::table
+        .select(max(transparency_log::id))
+        .get_result(db)?;
+    diesel::insert_into(transparency_log::table)
+        .values(&LogRecord {
+            id: last_id.map_or(0, |id| id + 1),
+            leaf_hash: leaf_hash(entry.as_bytes()).to_vec(),
+            entry,
+            namespace: package.namespace,
+            name: package.name,
+            version,
+        })
+        .execute(db)?;
+    Ok(())
+}
+
+fn leaves(store: &Store) -> Res<Vec<Hash>> {
+    let db = store.db();
+    let leaves = transparency_log::table
+        .select(transparency_log::leaf_hash)
+        .order(transparency_log::id)
+        .get_results::<Vec<u8>>(db)?;
+    Ok(leaves
+        .into_iter()
+        .map(|bytes| {
+            let mut hash = Hash::default();
+            hash.copy_from_slice(&bytes);
+            hash
+        })
+        .collect())
+}
+
+pub fn tree_head(store: &Store) -> Res<TreeHead> {
+    let leaves = leaves(store)?;
+    Ok(TreeHead {
+        tree_size: leaves.len() as u64,
+        root_hash: to_hex(&root_hash(&leaves)),
+    })
+}
+
+pub fn entries(store: &Store, start: u64, end: u64) -> Res<Vec<LoggedEntry>> {
+    let db = store.db();
+    // Ids are stored as i64, and `start` comes straight from the request.
+    let start = start.min(i64::MAX as u64);
+    let end = end.min(start.saturating_add(MAX_ENTRIES)).min(i64::MAX as u64);
+    let records = transparency_log::table
+        .filter(
+            transparency_log::id
+                .ge(start as i64)
+                .and(transparency_log::id.lt(end as i64)),
+        )
+        .order(transparency_log::id)
+        .get_results::<LogRecord>(db)?;
+    Ok(records.into_iter().map(LoggedEntry::from).collect())
+}
+
+/// All entries about a release, oldest first.
+pub fn release_entries(
+    store: &Store,
+    namespace: &str,
+    name: &str,
+    version: &str,
+) -> Res<Vec<LoggedEntry>> {
+    let db = store.db();
+    let records = transparency_log::table
+        .filter(
+            transparency_log::namespace
+                .eq(namespace)
+                .and(transparency_log::name.eq(name))
+                .and(transparency_log::version.eq(version)),
+        )
+        .order(transparency_log::id)
+        .get_results::<LogRecord>(db)?;
+    Ok(records.into_iter().map(LoggedEntry::from).collect())
+}
+
+pub fn inclusion_proof(store: &Store, leaf_index: u64, tree_size: u64) -> Res<InclusionProof> {
+    let leaves = leaves(store)?;
+    if leaf_index >= tree_size || tree_size > leaves.len() as u64 {
+        return Err(Error::Status(Status::BadRequest));
+    }
+    let audit_path = compute_inclusion_proof(&leaves[..tree_size as usize], leaf_index as usize);
+    Ok(InclusionProof {
+        leaf_index,
+        tree_size,
+        audit_path: audit_path.iter().map(|hash| to_hex(hash)).collect(),
+    })
+}
+
+pub fn consistency_proof(store: &Store, first: u64, second: u64) -> Res<ConsistencyProof> {
+    let leaves = leaves(store)?;
+    if first > second || second > leaves.len() as u64 {
+        return Err(Error::Status(Status::BadRequest));
+    }
+    let proof = compute_consistency_proof(&leaves[..second as usize], first as usize);
+    Ok(ConsistencyProof {
+        first,
+        second,
+        proof: proof.iter().map(|hash| to_hex(hash)).collect(),
+    })
+}
@@ -1,37 +1,94 @@
 use std::io::Read;
-use std::time::SystemTime;
 
-use brotli;
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
+use pm_lib::archive::{validate_archive, ArchiveLimits};
+use pm_lib::package::PackageName;
 use pm_lib::publication_request::PublicationRequest;
+use pm_lib::transparency_log::{digest, LogEvent};
 use rmp_serde::decode;
-use tar;
 
 use crate::error::{Error, Res};
 use crate::file::File;
 use crate::package;
 use crate::package::{Package, PackageOwner};
 use crate::schema::{files, package_owners, package_releases, packages, release_dependencies};
+use crate::sql_types::StringList;
+use crate::storage::{artifact_key, ArtifactStorage};
 use crate::store::Store;
+use crate::transparency_log;
 use crate::user::User;
 
-fn validate_archive<R: Read>(mut reader: R) -> Res<()> {
-    // TODO validate file names, content length
-    for entry in tar::Archive::new(brotli::Decompressor::new(&mut reader, 4096))
-        .entries()
-        .map_err(|_| Error::InvalidArtifact("not a Brotli compressed TAR archive"))?
-    {
-        entry.map_err(|_| Error::InvalidArtifact("malformed TAR archive"))?;
+fn validate_metadata(store: &Store, pr: &PublicationRequest) -> Res<()> {
+    pr.validate_metadata()?;
+    for dependency in &pr.dependencies {
+        let package_name = &dependency.package_name;
+        if store
+            .get_package(&package_name.namespace, &package_name.name)?
+            .is_none()
+        {
+            return Err(Error::InvalidMetadata(
+                "unknown_dependency",
+                format!("Dependency {} does not exist in the registry", package_name),
+            ));
+        }
     }
     Ok(())
 }
 
-pub fn process_upload<R: Read>(store: &Store, user: &User, reader: R) -> Res<()> {
+/// `validate_metadata` has checked that the signature matches; here we check
+/// that the key belongs to the publisher.
+fn validate_signing_key(store: &Store, user: &User, pr: &PublicationRequest) -> Res<()> {
+    if let Some(ref signature) = pr.signature {
+        let public_key = &signature.public_key;
+        if !store.get_signing_keys(user)?.contains(public_key) {
+            return Err(Error::UnknownSigningKey(public_key.clone(), user.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn package_name(pr: &PublicationRequest) -> PackageName {
+    PackageName {
+        namespace: pr.namespace.clone(),
+        name: pr.name.clone(),
+    }
+}
+
+/// Room for a publication request's metadata, readme and license file on top
+/// of the archive.
+const MAX_METADATA_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The largest publication request we read: an archive can't be much larger
+/// compressed than unpacked, even if it doesn't compress at all.
+fn max_publication_size(archive_limits: &ArchiveLimits) -> u64 {
+    archive_limits
+        .max_unpacked_size
+        .saturating_add(MAX_METADATA_SIZE)
+}
+
+pub fn process_upload<R: Read>(
+    store: &Store,
+    user: &User,
+    archive_limits: &ArchiveLimits,
+    storage: &dyn ArtifactStorage,
+    reader: R,
+) -> Res<()> {
     let db = store.db();
-    db.build_transaction().serializable().run(|| {
-        let pr: PublicationRequest = decode::from_read(reader)?;
+    let max_size = max_publication_size(archive_limits);
+    let mut body = Vec::new();
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > max_size {
+        return Err(Error::PublicationTooLarge(max_size));
+    }
+    store.serializable_transaction(|| {
+        let pr: PublicationRequest = decode::from_slice(&body)?;
+        validate_metadata(store, &pr)?;
+        validate_signing_key(store, user, &pr)?;
         if store.get_package(&pr.namespace, &pr.name)?.is_some() {
             let owners = store.get_package_owners(&pr.namespace, &pr.name)?;
             if !owners.iter().any(|o| o == user) {
@@ -60,21 +117,28 @@ pub fn process_upload<R: Read>(store: &Store, user: &User, reader: R) -> Res<()>
                     namespace: pr.namespace.clone(),
                     name: pr.name.clone(),
                     user_id: user.to_string(),
-                    added_time: SystemTime::now(),
+                    added_time: Utc::now().naive_utc(),
                 })
                 .execute(db)?;
+            transparency_log::append(
+                store,
+                LogEvent::AddOwner {
+                    package: package_name(&pr),
+                    owner: user.to_string(),
+                },
+                user,
+            )?;
         }
 
-        // TODO validate metadata
-        validate_archive(&mut pr.tar_br.as_slice())?;
+        validate_archive(pr.tar_br.as_slice(), archive_limits)?;
         let release = package::Release {
             namespace: pr.namespace.clone(),
             name: pr.name.clone(),
             version: pr.version.to_string(),
 
             description: pr.description.clone(),
-            authors: pr.authors.clone(),
-            keywords: pr.keywords.clone(),
+            authors: StringList(pr.authors.clone()),
+            keywords: StringList(pr.keywords.clone()),
             homepage_url: pr.homepage_url.clone(),
             repository_type: pr.repository.as_ref().map(|r| r.type_.clone()),
             repository_url: pr.repository.as_ref().map(|r| r.url.clone()),
@@ -143,12 +207,31 @@ pub fn process_upload<R: Read>(store: &Store, user: &User, reader: R) -> Res<()>
         diesel::insert_into(release_dependencies::table)
             .values(&dependencies)
             .execute(db)?;
+        transparency_log::append(
+            store,
+            LogEvent::Publish {
+                package: package_name(&pr),
+                version: pr.version.clone(),
+                digest: digest(&pr.tar_br),
+            },
+            user,
+        )?;
+        // The artifact is stored inside the transaction, so if the transaction
+        // fails after this, it's left behind with no file referring to it. A
+        // retry stores it under the same key. We don't delete it on failure,
+        // because a concurrent publication of the same archive may have
+        // stored it too and be about to commit.
+        let storage_key = artifact_key(&pr.namespace, &pr.name, &release.version, &pr.tar_br);
+        storage.put(&storage_key, &pr.tar_br)?;
         diesel::insert_into(files::table)
             .values(&File {
                 namespace: release.namespace.to_owned(),
                 name: release.name.to_owned(),
                 version: release.version,
-                data: pr.tar_br,
+                data: None,
+                signature: pr.signature.as_ref().map(|s| s.signature.clone()),
+                signing_key: pr.signature.as_ref().map(|s| s.public_key.clone()),
+                storage_key: Some(storage_key),
             })
             .execute(db)?;
         Ok(())
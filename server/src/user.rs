@@ -1,12 +1,13 @@
 use std::fmt;
 use std::str::FromStr;
 
+use chrono::NaiveDateTime;
 use rocket::request::FromFormValue;
 use rocket::http::RawStr;
 
 use crate::error::{Res, Error};
 use crate::auth::AuthSource;
-use crate::schema::users;
+use crate::schema::{signing_keys, users};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct User {
@@ -84,6 +85,17 @@ impl UserRecord {
     }
 }
 
+/// An ed25519 public key that a user signs their releases with.
+#[derive(Insertable, Queryable, Identifiable, Associations, Debug)]
+#[table_name = "signing_keys"]
+#[primary_key(public_key)]
+#[belongs_to(UserRecord, foreign_key = "user_id")]
+pub struct SigningKey {
+    pub public_key: String,
+    pub user_id: String,
+    pub added_time: NaiveDateTime,
+}
+
 
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
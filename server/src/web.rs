@@ -0,0 +1,647 @@
+// The registry's web pages: a search page, and a page for every package and
+// release, rendered from what the package metadata API returns.
+
+use std::fmt::Write;
+
+use chrono::NaiveDateTime;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use rocket::response::content;
+use url::form_urlencoded;
+
+use pm_lib::package::PackageName;
+use pm_lib::package_info::{highest_dependents, Dependent, PackageInfo, ReleaseInfo};
+use pm_lib::publication_request::NamedTextFile;
+use pm_lib::search::SearchResults;
+use pm_lib::version::Version;
+
+static STYLES: &str = "
+body {
+    background: white;
+    color: black;
+    margin: 0 4em;
+    padding: 0;
+    text-align: center;
+    font-size: 1em;
+    font-family: serif;
+}
+
+h1 {
+    background: red;
+    color: yellow;
+    width: 100%;
+    padding: 0.5em 0;
+}
+
+h1 a {
+    color: yellow;
+    text-decoration: none;
+}
+
+.btn {
+    background: #3498db;
+    background-image: -webkit-linear-gradient(top, #3498db, #2980b9);
+    background-image: -moz-linear-gradient(top, #3498db, #2980b9);
+    background-image: -ms-linear-gradient(top, #3498db, #2980b9);
+    background-image: -o-linear-gradient(top, #3498db, #2980b9);
+    background-image: linear-gradient(to bottom, #3498db, #2980b9);
+    -webkit-border-radius: 4;
+    -moz-border-radius: 4;
+    border-radius: 4px;
+    font-family: sans-serif;
+    color: #ffffff;
+    font-size: 20px;
+    padding: 10px 20px 10px 20px;
+    text-decoration: none;
+}
+
+.btn:hover {
+    background: #3cb0fd;
+    background-image: -webkit-linear-gradient(top, #3cb0fd, #3498db);
+    background-image: -moz-linear-gradient(top, #3cb0fd, #3498db);
+    background-image: -ms-linear-gradient(top, #3cb0fd, #3498db);
+    background-image: -o-linear-gradient(top, #3cb0fd, #3498db);
+    background-image: linear-gradient(to bottom, #3cb0fd, #3498db);
+    text-decoration: none;
+}
+
+.pad { padding: 1em; }
+
+.page {
+    text-align: left;
+    max-width: 60em;
+    margin: 0 auto;
+}
+
+.columns {
+    display: flex;
+    flex-wrap: wrap;
+}
+
+.main {
+    flex: 3;
+    min-width: 20em;
+    margin-right: 2em;
+}
+
+.side {
+    flex: 1;
+    min-width: 15em;
+}
+
+.side ul {
+    list-style: none;
+    padding: 0;
+}
+
+pre {
+    background: #f4f4f4;
+    padding: 0.5em;
+    overflow-x: auto;
+}
+
+.deleted {
+    background: #fdd;
+    padding: 0.5em;
+}
+
+.yanked { color: #999; }
+.current { font-weight: bold; }
+
+.avatar {
+    width: 1.5em;
+    height: 1.5em;
+    vertical-align: middle;
+}
+
+.results {
+    width: 100%;
+    border-collapse: collapse;
+}
+
+.results th, .results td {
+    text-align: left;
+    padding: 0.3em;
+    border-bottom: 1px solid #ddd;
+}
+";
+
+pub fn html_doc(title: &str, content: &str) -> content::Html<String> {
+    content::Html(format!(
+        "<!doctype html>
+<html>
+  <head>
+    <meta charset=\"utf-8\">
+    <title>{}</title>
+    <style>{}</style>
+  </head>
+  <body>
+    <h1><a href=\"/\">☭ People's Revolutionary Package Registry ☭</a></h1>
+    {}
+  </body>
+</html>
+",
+        escape(title),
+        STYLES,
+        content
+    ))
+}
+
+/// Escape text for use in HTML, inside elements or quoted attributes.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Whether we can link to a URL a publisher gave us: it has to be relative,
+/// or use a scheme which can't run scripts on our pages.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim_start();
+    match url.find(&[':', '/', '?', '#'][..]) {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            scheme == "http" || scheme == "https" || scheme == "mailto"
+        }
+        _ => true,
+    }
+}
+
+fn link(url: &str, text: &str) -> String {
+    if is_safe_url(url) {
+        format!("<a href=\"{}\">{}</a>", escape(url), escape(text))
+    } else {
+        escape(text)
+    }
+}
+
+/// Render Markdown from a package. Anyone can publish a package, so raw HTML
+/// is shown as text, and links to unsafe URLs go nowhere.
+fn render_markdown(source: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let events = Parser::new_ext(source, options).map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(link_type, ref url, ref title)) if !is_safe_url(url) => {
+            Event::Start(Tag::Link(link_type, "".into(), title.clone()))
+        }
+        Event::Start(Tag::Image(link_type, ref url, ref title)) if !is_safe_url(url) => {
+            Event::Start(Tag::Image(link_type, "".into(), title.clone()))
+        }
+        event => event,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out
+}
+
+fn render_readme(readme: &NamedTextFile) -> String {
+    let name = readme.name.to_lowercase();
+    if name.ends_with(".md") || name.ends_with(".markdown") {
+        render_markdown(&readme.contents)
+    } else {
+        format!("<pre>{}</pre>", escape(&readme.contents))
+    }
+}
+
+fn format_time(time: i64) -> String {
+    NaiveDateTime::from_timestamp(time, 0)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+fn package_path(package: &PackageName) -> String {
+    format!("/packages/{}/{}", package.namespace, package.name)
+}
+
+fn release_path(package: &PackageName, version: &Version) -> String {
+    format!("{}/{}", package_path(package), version)
+}
+
+pub fn search_form(ns: &str, query: &str) -> String {
+    format!(
+        "
+<form class=\"pad\" action=\"/packages\" method=\"get\">
+  <input name=\"ns\" placeholder=\"Namespaces\" value=\"{}\">
+  <input name=\"q\" placeholder=\"Search packages\" value=\"{}\" autofocus>
+  <button type=\"submit\">Search</button>
+</form>
+",
+        escape(ns),
+        escape(query)
+    )
+}
+
+fn search_path(ns: &str, query: &str, page: usize) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .append_pair("ns", ns)
+        .append_pair("q", query)
+        .append_pair("page", &page.to_string())
+        .finish();
+    format!("/packages?{}", query)
+}
+
+/// The search form, and its results if a search has been made.
+pub fn search_page(
+    ns: &str,
+    query: &str,
+    results: Option<&SearchResults>,
+) -> content::Html<String> {
+    let mut out = search_form(ns, query);
+    match results {
+        None => {}
+        Some(results) if results.total == 0 => out.push_str("<p>No packages found.</p>\n"),
+        Some(results) => {
+            out.push_str("<div class=\"page\">\n<table class=\"results\">\n");
+            out.push_str(
+                "<tr><th>Package</th><th>Version</th><th>Description</th><th>Publisher</th>\
+                 <th>Downloads</th></tr>\n",
+            );
+            for result in &results.results {
+                writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    link(&package_path(&result.package), &result.package.to_string()),
+                    link(
+                        &release_path(&result.package, &result.version),
+                        &result.version.to_string()
+                    ),
+                    escape(&result.description),
+                    escape(&result.publisher),
+                    result.downloads
+                )
+                .unwrap();
+            }
+            out.push_str("</table>\n<p>\n");
+            let first = (results.page - 1) * results.limit;
+            if results.page > 1 {
+                writeln!(
+                    out,
+                    "{}",
+                    link(&search_path(ns, query, results.page - 1), "Previous")
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "{} of {} packages",
+                if results.results.is_empty() {
+                    "None".to_string()
+                } else {
+                    format!("{}-{}", first + 1, first + results.results.len())
+                },
+                results.total
+            )
+            .unwrap();
+            if first + results.results.len() < results.total {
+                writeln!(
+                    out,
+                    "{}",
+                    link(&search_path(ns, query, results.page + 1), "Next")
+                )
+                .unwrap();
+            }
+            out.push_str("</p>\n</div>\n");
+        }
+    }
+    let title = if query.is_empty() {
+        "Search packages".to_string()
+    } else {
+        format!("Search results for {}", query)
+    };
+    html_doc(&title, &out)
+}
+
+fn deleted_notice(out: &mut String, what: &str, reason: &str, deleted_on: Option<i64>) {
+    let on = deleted_on
+        .map(|time| format!(" on {}", format_time(time)))
+        .unwrap_or_default();
+    writeln!(
+        out,
+        "<p class=\"deleted\">This {} was yanked{}: {}</p>",
+        what,
+        on,
+        escape(reason)
+    )
+    .unwrap();
+}
+
+fn side_bar(
+    package: &PackageInfo,
+    release: Option<&ReleaseInfo>,
+    dependents: &[Dependent],
+) -> String {
+    let mut out = String::new();
+    if let Some(release) = release {
+        writeln!(
+            out,
+            "<h3>Published</h3>\n<p>{} by {}</p>",
+            format_time(release.publish_time),
+            escape(&release.publisher.name)
+        )
+        .unwrap();
+        match (&release.license, &release.license_file) {
+            (Some(license), _) => {
+                writeln!(out, "<h3>License</h3>\n<p>{}</p>", escape(license)).unwrap()
+            }
+            (None, Some(file)) => writeln!(
+                out,
+                "<h3>License</h3>\n<details><summary>{}</summary><pre>{}</pre></details>",
+                escape(&file.name),
+                escape(&file.contents)
+            )
+            .unwrap(),
+            (None, None) => {}
+        }
+        let mut links = Vec::new();
+        if let Some(ref url) = release.homepage_url {
+            links.push(link(url, "Homepage"));
+        }
+        if let Some(ref repository) = release.repository {
+            links.push(link(
+                &repository.url,
+                &format!("Repository ({})", repository.type_),
+            ));
+        }
+        if let Some(ref url) = release.bugs_url {
+            links.push(link(url, "Issues"));
+        }
+        if !links.is_empty() {
+            out.push_str("<h3>Links</h3>\n<ul>\n");
+            for link in links {
+                writeln!(out, "<li>{}</li>", link).unwrap();
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+
+    writeln!(
+        out,
+        "<h3>Downloads</h3>\n<p>{} in total</p>",
+        package.downloads
+    )
+    .unwrap();
+    if let Some(release) = release {
+        // The daily counts cover the last 90 days.
+        let recent: u64 = release
+            .daily_downloads
+            .iter()
+            .map(|day| day.downloads)
+            .sum();
+        writeln!(
+            out,
+            "<p>{} of version {}, {} in the last 90 days</p>",
+            release.downloads,
+            escape(&release.version.to_string()),
+            recent
+        )
+        .unwrap();
+    }
+
+    out.push_str("<h3>Owners</h3>\n<ul>\n");
+    for owner in &package.owners {
+        let avatar = match owner.user.avatar {
+            Some(ref avatar) if is_safe_url(avatar) => {
+                format!(
+                    "<img class=\"avatar\" src=\"{}\" alt=\"\"> ",
+                    escape(avatar)
+                )
+            }
+            _ => String::new(),
+        };
+        writeln!(out, "<li>{}{}</li>", avatar, escape(&owner.user.name)).unwrap();
+    }
+    out.push_str("</ul>\n");
+
+    out.push_str("<h3>Versions</h3>\n<ul>\n");
+    for summary in &package.releases {
+        let current = release.map(|release| &release.version) == Some(&summary.version);
+        let class = match (current, summary.deleted.is_some()) {
+            (true, _) => " class=\"current\"",
+            (false, true) => " class=\"yanked\"",
+            (false, false) => "",
+        };
+        let yanked = if summary.deleted.is_some() {
+            " (yanked)"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "<li{}>{} {}{}</li>",
+            class,
+            link(
+                &release_path(&package.package, &summary.version),
+                &summary.version.to_string()
+            ),
+            format_time(summary.publish_time),
+            yanked
+        )
+        .unwrap();
+    }
+    out.push_str("</ul>\n");
+
+    if let Some(release) = release {
+        if !release.dependencies.is_empty() {
+            out.push_str("<h3>Dependencies</h3>\n<ul>\n");
+            for dependency in &release.dependencies {
+                writeln!(
+                    out,
+                    "<li>{} {}</li>",
+                    link(
+                        &package_path(&dependency.package_name),
+                        &dependency.package_name.to_string()
+                    ),
+                    escape(&dependency.version_constraint.to_string())
+                )
+                .unwrap();
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+
+    // On a release's page, only the packages which accept that release.
+    let dependents = highest_dependents(dependents.iter().filter(|dependent| match release {
+        Some(release) => dependent.constraint.contains(&release.version),
+        None => true,
+    }));
+    if !dependents.is_empty() {
+        out.push_str("<h3>Dependents</h3>\n<ul>\n");
+        for dependent in dependents {
+            writeln!(
+                out,
+                "<li>{} {}</li>",
+                link(
+                    &release_path(&dependent.package, &dependent.version),
+                    &format!("{} {}", dependent.package, dependent.version)
+                ),
+                escape(&dependent.constraint.to_string())
+            )
+            .unwrap();
+        }
+        out.push_str("</ul>\n");
+    }
+    out
+}
+
+/// The page for a package, showing `release` in detail: the package's latest
+/// release, or whichever release was asked for.
+pub fn package_page(
+    package: &PackageInfo,
+    release: Option<&ReleaseInfo>,
+    dependents: &[Dependent],
+) -> content::Html<String> {
+    let title = match release {
+        Some(release) => format!("{} {}", package.package, release.version),
+        None => package.package.to_string(),
+    };
+    let mut out = String::new();
+    writeln!(out, "<div class=\"page\">\n<h2>{}</h2>", escape(&title)).unwrap();
+    if let Some(ref reason) = package.deleted {
+        deleted_notice(&mut out, "package", reason, package.deleted_on);
+    }
+    match release {
+        None => out.push_str("<p>No releases available.</p>\n"),
+        Some(release) => {
+            if let Some(ref reason) = release.deleted {
+                deleted_notice(&mut out, "release", reason, release.deleted_on);
+            }
+            writeln!(out, "<p>{}</p>", escape(&release.description)).unwrap();
+        }
+    }
+    out.push_str("<div class=\"columns\">\n<div class=\"main\">\n");
+    if let Some(release) = release {
+        match release.readme {
+            Some(ref readme) => out.push_str(&render_readme(readme)),
+            None => out.push_str("<p>This release has no readme.</p>\n"),
+        }
+    }
+    writeln!(
+        out,
+        "</div>\n<div class=\"side\">\n{}</div>\n</div>\n</div>",
+        side_bar(package, release, dependents)
+    )
+    .unwrap();
+    html_doc(&title, &out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The values of every `name` attribute in `html`.
+    fn attributes<'a>(html: &'a str, name: &str) -> Vec<&'a str> {
+        let start = format!("{}=\"", name);
+        html.match_indices(&start)
+            .map(|(i, _)| {
+                let value = &html[i + start.len()..];
+                &value[..value.find('"').unwrap()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escape_html() {
+        assert_eq!(
+            escape("<a href=\"x\" title='y'>&amp;</a>"),
+            "&lt;a href=&quot;x&quot; title=&#39;y&#39;&gt;&amp;amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn only_link_to_safe_urls() {
+        for url in &[
+            "https://example.com",
+            "HTTP://example.com",
+            "mailto:alice@example.com",
+            "docs/usage.md",
+            "/packages",
+            "?q=pad",
+            "#usage",
+            "docs/a:b",
+        ] {
+            assert!(is_safe_url(url), "{}", url);
+        }
+        for url in &[
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            " javascript:alert(1)",
+            "\tjavascript:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "DATA:image/svg+xml;base64,PHN2Zz4=",
+            "vbscript:msgbox(1)",
+        ] {
+            assert!(!is_safe_url(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn quote_links() {
+        assert_eq!(
+            link("https://example.com/?a=1&b=\"2\"'", "<b>"),
+            "<a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;&#39;\">&lt;b&gt;</a>"
+        );
+        assert_eq!(link("javascript:alert(1)", "<b>"), "&lt;b&gt;");
+    }
+
+    #[test]
+    fn render_markdown_safely() {
+        let html = render_markdown(
+            "<script>alert(1)</script>\n\nSome <img src=x onerror=alert(1)> text\n\n\
+             <div onclick=\"alert(1)\">block</div>\n",
+        );
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(!html.contains("<div"), "{}", html);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"), "{}", html);
+
+        for source in &[
+            "[x](javascript:alert(1))",
+            "[x](JaVaScRiPt:alert(1))",
+            "[x](< javascript:alert(1)>)",
+            "[x](data:text/html,alert)",
+            "[x][ref]\n\n[ref]: javascript:alert(1)",
+            "<javascript:alert(1)>",
+            "![x](javascript:alert(1))",
+            "![x](DATA:image/svg+xml;base64,PHN2Zz4=)",
+            "![x](< data:image/svg+xml;base64,PHN2Zz4=>)",
+        ] {
+            let html = render_markdown(source);
+            let mut urls = attributes(&html, "href");
+            urls.extend(attributes(&html, "src"));
+            assert_eq!(urls, vec![""], "{}", html);
+        }
+        assert!(render_markdown("[x](https://example.com)")
+            .contains("<a href=\"https://example.com\">x</a>"));
+        assert!(render_markdown("![x](logo.png)").contains("<img src=\"logo.png\" alt=\"x\""));
+    }
+
+    #[test]
+    fn render_other_readmes_as_text() {
+        let readme = |name: &str| NamedTextFile {
+            name: name.to_string(),
+            contents: "Hi <script>alert(1)</script> & [x](javascript:alert(1))".to_string(),
+        };
+        assert_eq!(
+            render_readme(&readme("README.txt")),
+            "<pre>Hi &lt;script&gt;alert(1)&lt;/script&gt; &amp; [x](javascript:alert(1))</pre>"
+        );
+        assert_eq!(
+            render_readme(&readme("README")),
+            render_readme(&readme("README.txt"))
+        );
+        let markdown = render_readme(&readme("README.MD"));
+        assert_eq!(
+            markdown,
+            "<p>Hi &lt;script&gt;alert(1)&lt;/script&gt; &amp; <a href=\"\">x</a></p>\n"
+        );
+    }
+}